@@ -42,7 +42,6 @@ fn example_1_1_1_message_id(b: &mut Bencher) {
     })
 }
 
-/*
 fn example_1_1_1_subject(b: &mut Bencher) {
     let raw = include_bytes!("examples/1_1.1.eml");
 
@@ -51,7 +50,6 @@ fn example_1_1_1_subject(b: &mut Bencher) {
         msg.subject().unwrap().to_string();
     })
 }
-*/
 
 fn example_1_1_2(b: &mut Bencher) {
     let raw = include_bytes!("examples/1_1.2.eml");
@@ -163,7 +161,7 @@ benchmark_group!(
     example_1_1_1_date,
     example_1_1_1_from,
     example_1_1_1_message_id,
-    // example_1_1_1_subject,
+    example_1_1_1_subject,
     example_1_1_2,
     example_1_2,
     example_1_3,
@@ -0,0 +1,505 @@
+//! Verifying DKIM signatures (RFC 6376)
+//!
+//! Public keys come from a `KeyResolver` rather than DNS, so callers can use
+//! their own resolver, or fixed records in tests.
+use std::collections::HashMap;
+use std::fmt;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use ring::digest;
+use ring::signature::{self, UnparsedPublicKey};
+
+use rfc6376::*;
+use util::decode_base64;
+use super::*;
+
+/// Looks up DKIM key records, which are DNS TXT records at
+/// "<selector>._domainkey.<domain>"
+pub trait KeyResolver {
+    /// Returns the TXT records at `name`, with the strings of each record
+    /// joined
+    fn txt_records(&self, name: &str) -> Result<Vec<String>, LookupError>;
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum LookupError {
+    /// The name doesn't exist or has no TXT records
+    NotFound,
+    /// The lookup failed in a way that may not last, e.g. a DNS timeout
+    Temporary(String),
+}
+
+/// Fixed records, keyed by name
+impl KeyResolver for HashMap<String, String> {
+    fn txt_records(&self, name: &str) -> Result<Vec<String>, LookupError> {
+        match self.get(name) {
+            Some(record) => Ok(vec!(record.clone())),
+            None => Err(LookupError::NotFound),
+        }
+    }
+}
+
+/// The outcome of verifying one signature
+#[derive(Clone, Debug, PartialEq)]
+pub struct Verification {
+    /// The signature, or None if its field didn't parse
+    pub signature: Option<DKIMSignature>,
+    pub status: Status,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Status {
+    Pass,
+    /// The body hash or signature doesn't match, so the message changed
+    /// after it was signed
+    Fail(Failure),
+    /// The signature can't be checked, e.g. it's malformed or has no key
+    PermError(Failure),
+    /// The key lookup failed, so checking again later may succeed
+    TempError(String),
+}
+
+impl Status {
+    /// Returns the result as an Authentication-Results field names it
+    /// (RFC 8601), e.g. "pass"
+    pub fn result(&self) -> &'static str {
+        match *self {
+            Status::Pass => "pass",
+            Status::Fail(_) => "fail",
+            Status::PermError(_) => "permerror",
+            Status::TempError(_) => "temperror",
+        }
+    }
+}
+
+/// Why a signature didn't verify
+#[derive(Clone, Debug, PartialEq)]
+pub enum Failure {
+    /// The DKIM-Signature field doesn't follow RFC 6376
+    Malformed(ParseError),
+    /// The algorithm is rsa-sha1, which RFC 8301 says not to accept
+    UnsupportedAlgorithm,
+    /// The "q" tag doesn't include "dns/txt"
+    UnsupportedQueryMethod,
+    /// The "i" tag's domain isn't the "d" domain or a subdomain of it
+    IdentityMismatch,
+    /// The "x" tag is in the past, or before the "t" tag
+    Expired,
+    KeyNotFound,
+    /// The key record's "p" tag is empty
+    KeyRevoked,
+    /// The key record doesn't parse, or doesn't allow this signature
+    BadKey,
+    /// The "l" tag is longer than the canonicalized body
+    BodyTooShort,
+    BodyHashMismatch,
+    SignatureMismatch,
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            Failure::Malformed(ref e) => write!(f, "malformed signature: {}", e),
+            Failure::UnsupportedAlgorithm => write!(f, "unsupported algorithm"),
+            Failure::UnsupportedQueryMethod => write!(f, "unsupported query method"),
+            Failure::IdentityMismatch => write!(f, "identity not in signing domain"),
+            Failure::Expired => write!(f, "signature expired"),
+            Failure::KeyNotFound => write!(f, "no key for signature"),
+            Failure::KeyRevoked => write!(f, "key revoked"),
+            Failure::BadKey => write!(f, "unusable key record"),
+            Failure::BodyTooShort => write!(f, "body shorter than signed length"),
+            Failure::BodyHashMismatch => write!(f, "body hash did not verify"),
+            Failure::SignatureMismatch => write!(f, "signature did not verify"),
+        }
+    }
+}
+
+/// Verifies each DKIM-Signature field of `message`, which must be the whole
+/// message as it was received, with CRLF line endings. The results are in
+/// the order of the fields, the most recent first.
+pub fn verify<R: KeyResolver>(message: &[u8], resolver: &R) -> Vec<Verification> {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+    verify_at(message, resolver, now.as_secs())
+}
+
+/// Like `verify`, but checks expiration against `now`, in seconds since the
+/// epoch
+pub fn verify_at<R: KeyResolver>(message: &[u8], resolver: &R, now: u64) -> Vec<Verification> {
+    let (fields, body) = split_message(message);
+    fields.iter().enumerate()
+        .filter(|&(_, f)| f.name.eq_ignore_ascii_case(b"DKIM-Signature"))
+        .map(|(n, f)| {
+            let value = parse_profile(f.value(), "dkim_signature", Profile::Strict.into(), dkim_signature_with);
+            if let Some(e) = value.error() {
+                return Verification{ signature: None, status: Status::PermError(Failure::Malformed(e.clone())) };
+            }
+            let signature = value.unwrap();
+            let status = match check_signature(&fields, n, body, &signature, resolver, now) {
+                Ok(()) => Status::Pass,
+                Err(status) => status,
+            };
+            Verification{ signature: Some(signature), status: status }
+        })
+        .collect()
+}
+
+fn check_signature<R: KeyResolver>(fields: &[RawField], n: usize, body: &[u8], signature: &DKIMSignature, resolver: &R, now: u64) -> Result<(), Status> {
+    if signature.algorithm == SigningAlgorithm::RsaSha1 {
+        return Err(Status::PermError(Failure::UnsupportedAlgorithm));
+    }
+    if !signature.query_methods.iter().any(|q| q.eq_ignore_ascii_case("dns/txt")) {
+        return Err(Status::PermError(Failure::UnsupportedQueryMethod));
+    }
+    let identity_domain = signature.identity.as_ref().map(|i| i.rsplit('@').next().unwrap_or(""));
+    if let Some(domain) = identity_domain {
+        if !is_subdomain(domain, &signature.domain) {
+            return Err(Status::PermError(Failure::IdentityMismatch));
+        }
+    }
+    if let Some(expiration) = signature.expiration {
+        if expiration < now || signature.timestamp.map_or(false, |t| expiration < t) {
+            return Err(Status::PermError(Failure::Expired));
+        }
+    }
+
+    let key = try!(fetch_key(signature, resolver));
+    if key.strict && identity_domain.map_or(false, |d| !d.eq_ignore_ascii_case(&signature.domain)) {
+        return Err(Status::PermError(Failure::IdentityMismatch));
+    }
+
+    let body = canonical_body(body, signature.body_canonicalization);
+    let signed_body = match signature.length {
+        Some(l) if l > body.len() as u64 => return Err(Status::Fail(Failure::BodyTooShort)),
+        Some(l) => &body[..l as usize],
+        None => &body[..],
+    };
+    if digest::digest(&digest::SHA256, signed_body).as_ref() != &signature.body_hash[..] {
+        return Err(Status::Fail(Failure::BodyHashMismatch));
+    }
+
+    let data = signed_headers(fields, n, signature);
+    if !key.verify(&data, &signature.signature) {
+        return Err(Status::Fail(Failure::SignatureMismatch));
+    }
+    Ok(())
+}
+
+// Returns true if `name` is `domain` or a name under it
+fn is_subdomain(name: &str, domain: &str) -> bool {
+    let name = name.to_lowercase();
+    let domain = domain.to_lowercase();
+    name == domain || name.ends_with(&format!(".{}", domain))
+}
+
+// A header field as it appears in the message
+struct RawField<'a> {
+    /// The name, without any whitespace before the colon
+    name: &'a [u8],
+    /// The whole field, including the name and the final CRLF
+    data: &'a [u8],
+}
+
+impl<'a> RawField<'a> {
+    fn value(&self) -> &'a [u8] {
+        let colon = self.data.iter().position(|&c| c == b':').unwrap_or(0);
+        &self.data[colon+1..]
+    }
+}
+
+// Splits a message into its header fields and body. Lines in the header
+// without a colon are skipped.
+fn split_message<'a>(message: &'a [u8]) -> (Vec<RawField<'a>>, &'a [u8]) {
+    let mut fields: Vec<RawField> = vec!();
+    let mut start = None;
+    let mut pos = 0;
+    while pos < message.len() {
+        let end = message[pos..].iter().position(|&c| c == b'\n').map_or(message.len(), |p| pos + p + 1);
+        let line = &message[pos..end];
+        if line == b"\r\n" || line == b"\n" {
+            break;
+        }
+        if line[0] == b' ' || line[0] == b'\t' {
+            // A folded line continues the previous field
+            if let Some(s) = start {
+                fields.last_mut().unwrap().data = &message[s..end];
+            }
+        } else {
+            start = None;
+            if let Some(colon) = line.iter().position(|&c| c == b':') {
+                let name_end = line[..colon].iter().rposition(|&c| c != b' ' && c != b'\t').map_or(0, |p| p + 1);
+                fields.push(RawField{ name: &line[..name_end], data: line });
+                start = Some(pos);
+            }
+        }
+        pos = end;
+    }
+    let body_start = message[pos..].iter().position(|&c| c == b'\n').map_or(message.len(), |p| pos + p + 1);
+    (fields, &message[body_start..])
+}
+
+// The key record at "<selector>._domainkey.<domain>" (section 3.6.1)
+struct PublicKey {
+    algorithm: SigningAlgorithm,
+    /// An Ed25519 key, or an RSAPublicKey in DER
+    data: Vec<u8>,
+    /// The "s" flag of the "t" tag: the "i" domain must be the "d" domain
+    strict: bool,
+}
+
+impl PublicKey {
+    fn verify(&self, data: &[u8], signature: &[u8]) -> bool {
+        match self.algorithm {
+            SigningAlgorithm::Ed25519Sha256 => {
+                // Ed25519 signs the hash rather than the data (RFC 8463)
+                let hash = digest::digest(&digest::SHA256, data);
+                UnparsedPublicKey::new(&signature::ED25519, &self.data).verify(hash.as_ref(), signature).is_ok()
+            },
+            _ => {
+                // RFC 8301 requires accepting keys of 1024 bits and up
+                let algorithm = &signature::RSA_PKCS1_1024_8192_SHA256_FOR_LEGACY_USE_ONLY;
+                UnparsedPublicKey::new(algorithm, &self.data).verify(data, signature).is_ok()
+            },
+        }
+    }
+}
+
+fn fetch_key<R: KeyResolver>(signature: &DKIMSignature, resolver: &R) -> Result<PublicKey, Status> {
+    let name = format!("{}._domainkey.{}", signature.selector, signature.domain);
+    let records = match resolver.txt_records(&name) {
+        Ok(records) => records,
+        Err(LookupError::NotFound) => return Err(Status::PermError(Failure::KeyNotFound)),
+        Err(LookupError::Temporary(e)) => return Err(Status::TempError(e)),
+    };
+    let record = match records.first() {
+        Some(record) => record,
+        None => return Err(Status::PermError(Failure::KeyNotFound)),
+    };
+    let tags = match parse_only(|i| tag_list(i).bind(|i, t| eof(i).map(|_| t)), record.trim().as_bytes()) {
+        Ok(tags) => tags,
+        Err(_) => return Err(Status::PermError(Failure::BadKey)),
+    };
+    let tag = |name: &str| tags.iter().find(|&&(ref n, _)| n == name).map(|&(_, ref v)| &v[..]);
+    let has = |name: &str, default: &str, value: &str| {
+        tag(name).unwrap_or(default).split(':').any(|v| v.eq_ignore_ascii_case(value))
+    };
+
+    let algorithm = match tag("k").unwrap_or("rsa") {
+        "rsa" => SigningAlgorithm::RsaSha256,
+        "ed25519" => SigningAlgorithm::Ed25519Sha256,
+        _ => return Err(Status::PermError(Failure::BadKey)),
+    };
+    let usable = tag("v").map_or(true, |v| v == "DKIM1") &&
+        algorithm == signature.algorithm &&
+        has("h", "sha256", "sha256") &&
+        (has("s", "*", "*") || has("s", "*", "email"));
+    if !usable {
+        return Err(Status::PermError(Failure::BadKey));
+    }
+    let data = match tag("p") {
+        Some("") => return Err(Status::PermError(Failure::KeyRevoked)),
+        Some(p) => decode_base64(p.as_bytes()),
+        None => None,
+    };
+    let data = match (data, algorithm) {
+        (Some(ref d), SigningAlgorithm::RsaSha256) => rsa_public_key(d).map(|k| k.to_vec()),
+        (d, _) => d,
+    };
+    match data {
+        Some(data) => Ok(PublicKey{ algorithm: algorithm, data: data, strict: has("t", "", "s") }),
+        None => Err(Status::PermError(Failure::BadKey)),
+    }
+}
+
+// Returns the contents of the DER element at the start of `data` if it has
+// the tag `tag`, and what follows it
+fn der_element(data: &[u8], tag: u8) -> Option<(&[u8], &[u8])> {
+    if data.len() < 2 || data[0] != tag {
+        return None;
+    }
+    let (length, header) = if data[1] < 0x80 {
+        (data[1] as usize, 2)
+    } else {
+        let octets = (data[1] & 0x7f) as usize;
+        if octets == 0 || octets > 4 || data.len() < 2 + octets {
+            return None;
+        }
+        (data[2..2+octets].iter().fold(0, |l, &b| l << 8 | b as usize), 2 + octets)
+    };
+    if data.len() - header < length {
+        return None;
+    }
+    Some((&data[header..header+length], &data[header+length..]))
+}
+
+// Keys are usually published as a SubjectPublicKeyInfo, which wraps the
+// RSAPublicKey that section 3.6.1 describes, so accept either
+fn rsa_public_key(data: &[u8]) -> Option<&[u8]> {
+    let contents = match der_element(data, 0x30) {
+        Some((contents, _)) => contents,
+        None => return None,
+    };
+    // A SubjectPublicKeyInfo starts with an AlgorithmIdentifier sequence, and
+    // an RSAPublicKey with the modulus
+    match der_element(contents, 0x30) {
+        Some((_, rest)) => match der_element(rest, 0x03) {
+            Some((bits, _)) if bits.first() == Some(&0) => Some(&bits[1..]),
+            _ => None,
+        },
+        None => Some(data),
+    }
+}
+
+// The data the signature covers: the fields named by the "h" tag, then the
+// DKIM-Signature field itself with an empty "b" tag and no final CRLF
+// (section 3.7)
+fn signed_headers(fields: &[RawField], n: usize, signature: &DKIMSignature) -> Vec<u8> {
+    let mut data = vec!();
+    // Each name selects the last instance not yet selected, so fields are
+    // signed from the bottom up (section 5.4.2). Names without an instance
+    // left select nothing.
+    let mut used = vec!(false; fields.len());
+    used[n] = true;
+    for name in signature.headers.iter() {
+        let found = (0..fields.len()).rev().find(|&k| !used[k] && fields[k].name.eq_ignore_ascii_case(name.as_bytes()));
+        if let Some(k) = found {
+            used[k] = true;
+            canonical_field(&mut data, fields[k].data, signature.header_canonicalization);
+        }
+    }
+    canonical_field(&mut data, &without_signature(fields[n].data), signature.header_canonicalization);
+    if data.ends_with(b"\r\n") {
+        let len = data.len();
+        data.truncate(len - 2);
+    }
+    data
+}
+
+// Removes the value of the "b" tag, up to the next ";", leaving the rest of
+// the field as it was
+fn without_signature(field: &[u8]) -> Vec<u8> {
+    let colon = field.iter().position(|&c| c == b':').unwrap_or(0);
+    let mut out = field[..colon+1].to_vec();
+    for (k, spec) in field[colon+1..].split(|&c| c == b';').enumerate() {
+        if k > 0 {
+            out.push(b';');
+        }
+        match spec.iter().position(|&c| c == b'=') {
+            Some(eq) if spec[..eq].iter().filter(|&&c| !is_fws(c)).eq(b"b".iter()) => out.extend_from_slice(&spec[..eq+1]),
+            _ => out.extend_from_slice(spec),
+        }
+    }
+    out
+}
+
+fn is_fws(c: u8) -> bool {
+    c == b' ' || c == b'\t' || c == b'\r' || c == b'\n'
+}
+
+// Appends `field`, canonicalized as section 3.4.1 or 3.4.2 describes
+fn canonical_field(out: &mut Vec<u8>, field: &[u8], canonicalization: Canonicalization) {
+    match canonicalization {
+        Canonicalization::Simple => out.extend_from_slice(field),
+        Canonicalization::Relaxed => {
+            let colon = field.iter().position(|&c| c == b':').unwrap_or(0);
+            let name = field[..colon].iter().filter(|&&c| !is_fws(c)).map(|c| c.to_ascii_lowercase());
+            out.extend(name);
+            out.push(b':');
+            // Unfold, reduce each run of whitespace to a space and drop
+            // whitespace at either end
+            let mut space = false;
+            let mut started = false;
+            for &c in field[colon+1..].iter() {
+                match c {
+                    b'\r' | b'\n' => {},
+                    b' ' | b'\t' => space = true,
+                    c => {
+                        if space && started {
+                            out.push(b' ');
+                        }
+                        space = false;
+                        started = true;
+                        out.push(c);
+                    },
+                }
+            }
+            out.extend_from_slice(b"\r\n");
+        },
+    }
+}
+
+// Canonicalizes a body as section 3.4.3 or 3.4.4 describes
+fn canonical_body(body: &[u8], canonicalization: Canonicalization) -> Vec<u8> {
+    let mut out = match canonicalization {
+        Canonicalization::Simple => body.to_vec(),
+        Canonicalization::Relaxed => {
+            // Reduce each run of whitespace to a space and drop whitespace at
+            // the end of each line
+            let mut out = Vec::with_capacity(body.len());
+            let mut space = false;
+            let mut pos = 0;
+            while pos < body.len() {
+                match body[pos] {
+                    b' ' | b'\t' => space = true,
+                    b'\r' if body.get(pos+1) == Some(&b'\n') => {
+                        out.extend_from_slice(b"\r\n");
+                        space = false;
+                        pos += 1;
+                    },
+                    c => {
+                        if space {
+                            out.push(b' ');
+                        }
+                        space = false;
+                        out.push(c);
+                    },
+                }
+                pos += 1;
+            }
+            out
+        },
+    };
+    // Empty lines at the end are dropped, and the last line ends in a CRLF.
+    // An empty body is a CRLF when simple and nothing when relaxed.
+    while out.ends_with(b"\r\n") {
+        let len = out.len();
+        out.truncate(len - 2);
+    }
+    if !out.is_empty() || canonicalization == Canonicalization::Simple {
+        out.extend_from_slice(b"\r\n");
+    }
+    out
+}
+
+#[test]
+fn test_canonicalization() {
+    // From RFC 6376 section 3.4.6
+    let message = b"A: X\r\nB : Y\t\r\n\tZ  \r\n\r\n C \r\nD \t E\r\n\r\n\r\n";
+    let (fields, body) = split_message(message);
+    assert_eq!(fields.iter().map(|f| f.name).collect::<Vec<_>>(), vec!(&b"A"[..], &b"B"[..]));
+
+    let mut relaxed = vec!();
+    for field in fields.iter() {
+        canonical_field(&mut relaxed, field.data, Canonicalization::Relaxed);
+    }
+    assert_eq!(&relaxed[..], &b"a:X\r\nb:Y Z\r\n"[..]);
+    assert_eq!(&canonical_body(body, Canonicalization::Relaxed)[..], &b" C\r\nD E\r\n"[..]);
+
+    let mut simple = vec!();
+    for field in fields.iter() {
+        canonical_field(&mut simple, field.data, Canonicalization::Simple);
+    }
+    assert_eq!(&simple[..], &b"A: X\r\nB : Y\t\r\n\tZ  \r\n"[..]);
+    assert_eq!(&canonical_body(body, Canonicalization::Simple)[..], &b" C \r\nD \t E\r\n"[..]);
+
+    assert_eq!(&canonical_body(b"", Canonicalization::Simple)[..], &b"\r\n"[..]);
+    assert_eq!(&canonical_body(b"\r\n \r\n", Canonicalization::Relaxed)[..], &b""[..]);
+}
+
+#[test]
+fn test_without_signature() {
+    let field = b"DKIM-Signature: v=1; bh=abc=;\r\n b=dzdV\r\n yOfA; d=example.com\r\n";
+    assert_eq!(&without_signature(field)[..], &b"DKIM-Signature: v=1; bh=abc=;\r\n b=; d=example.com\r\n"[..]);
+
+    let field = b"DKIM-Signature: v=1; b = dzdV\r\n";
+    assert_eq!(&without_signature(field)[..], &b"DKIM-Signature: v=1; b ="[..]);
+}
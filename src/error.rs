@@ -0,0 +1,167 @@
+//! Errors describing where and why parsing failed
+
+use std::cell::Cell;
+use std::error;
+use std::fmt;
+
+use chomp::*;
+use chomp::types::*;
+use chomp::parsers::{Error, SimpleResult};
+use chomp::primitives::{IntoInner, Primitives};
+
+// Number of input bytes included in `ParseError::snippet`
+const SNIPPET_LEN: usize = 24;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParseError {
+    /// The innermost grammar production that failed, e.g. "angle_addr", or
+    /// the production being parsed, e.g. "address_list", if no production it
+    /// names failed where parsing stopped
+    pub production: &'static str,
+    /// Byte offset into the parsed input where parsing stopped
+    pub offset: usize,
+    /// Line of `offset`, starting at 1
+    pub line: usize,
+    /// Column of `offset` in bytes, starting at 1
+    pub column: usize,
+    /// The input starting at `offset`
+    pub snippet: String,
+    /// The byte the parser was expecting, if it was expecting a specific one
+    pub expected: Option<u8>,
+}
+
+impl ParseError {
+    /// Builds an error from the result of `parse_only`, where `rest` is the
+    /// unconsumed remainder of `input`
+    pub fn new(production: &'static str, input: &[u8], rest: &[u8], err: Error<u8>) -> ParseError {
+        ParseError::at(production, input, rest, err.expected_token().cloned())
+    }
+
+    fn at(production: &'static str, input: &[u8], rest: &[u8], expected: Option<u8>) -> ParseError {
+        let offset = input.len() - rest.len();
+        let consumed = &input[..offset];
+        let line = 1 + consumed.iter().filter(|&&c| c == b'\n').count();
+        let column = match consumed.iter().rposition(|&c| c == b'\n') {
+            Some(lf) => offset - lf,
+            None => offset + 1,
+        };
+        let end = if rest.len() < SNIPPET_LEN { rest.len() } else { SNIPPET_LEN };
+
+        ParseError {
+            production: production,
+            offset: offset,
+            line: line,
+            column: column,
+            snippet: String::from_utf8_lossy(&rest[..end]).into_owned(),
+            expected: expected,
+        }
+    }
+}
+
+// The failure furthest into the input of a production named with
+// `production`: the length of the input left, the production and the byte it
+// expected. Only recorded while `parse_tracked` runs, as measuring the input
+// left would mark streaming input as incomplete.
+thread_local!(static FURTHEST: Cell<Option<(usize, &'static str, Option<u8>)>> = Cell::new(None));
+thread_local!(static TRACKING: Cell<bool> = Cell::new(false));
+
+/// Runs `parser` as the grammar production `name`, so a ParseError can name
+/// it if it's where parsing failed
+pub fn production<I: U8Input, T, F>(i: I, name: &'static str, parser: F) -> SimpleResult<I, T> where
+F: FnOnce(I) -> SimpleResult<I, T>,
+{
+    match parser(i).into_inner() {
+        (i, Ok(t)) => i.ret(t),
+        (mut i, Err(e)) => {
+            if TRACKING.with(|t| t.get()) {
+                let m = i.mark();
+                let left = i.consume_remaining().len();
+                i = i.restore(m);
+                FURTHEST.with(|f| match f.get() {
+                    // Inner productions fail first, so keep the earliest
+                    // failure at a position
+                    Some((l, _, _)) if l <= left => {},
+                    _ => f.set(Some((left, name, e.expected_token().cloned()))),
+                });
+            }
+            i.err(e)
+        },
+    }
+}
+
+/// Parses all of `input` with `parser`, naming the innermost production that
+/// failed furthest into the input if it doesn't parse
+pub fn parse_tracked<'a, T, F>(production: &'static str, input: &'a [u8], parser: F) -> Result<T, ParseError> where
+F: FnOnce(&'a [u8]) -> SimpleResult<&'a [u8], T>,
+{
+    let tracking = TRACKING.with(|t| t.replace(true));
+    let outer = FURTHEST.with(|f| f.replace(None));
+    let result = parse_only(parser, input);
+    let furthest = FURTHEST.with(|f| f.replace(outer));
+    TRACKING.with(|t| t.set(tracking));
+
+    result.map_err(|(rest, e)| match furthest {
+        Some((left, name, expected)) if left <= rest.len() => {
+            ParseError::at(name, input, &input[input.len()-left..], expected)
+        },
+        _ => ParseError::new(production, input, rest, e),
+    })
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        try!(write!(f, "failed to parse {} at line {}, column {}", self.production, self.line, self.column));
+        if let Some(c) = self.expected {
+            try!(write!(f, ": expected {:?}", c as char));
+        }
+        write!(f, " near {:?}", self.snippet)
+    }
+}
+
+impl error::Error for ParseError {
+    fn description(&self) -> &str {
+        "parse error"
+    }
+}
+
+/// A domain name that can't be converted between U-labels and A-labels
+#[derive(Clone, Debug, PartialEq)]
+pub struct IdnaError {
+    pub domain: String,
+}
+
+impl fmt::Display for IdnaError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(f, "invalid internationalized domain name {:?}", self.domain)
+    }
+}
+
+impl error::Error for IdnaError {
+    fn description(&self) -> &str {
+        "invalid internationalized domain name"
+    }
+}
+
+#[test]
+fn test_parse_error() {
+    let input = b"Thu, 22 Sep 2016\r\n 1-46:40 -0700";
+    let err = ParseError::new("date_time", input, &input[20..], Error::expected(b':'));
+    assert_eq!(err.offset, 20);
+    assert_eq!(err.line, 2);
+    assert_eq!(err.column, 3);
+    assert_eq!(err.snippet, "-46:40 -0700");
+    assert_eq!(err.to_string(), "failed to parse date_time at line 2, column 3: expected ':' near \"-46:40 -0700\"");
+}
+
+#[test]
+fn test_parse_tracked() {
+    use chomp::parsers::token;
+
+    let input = b"ab";
+    let parser = |i| production(i, "b", |i| production(i, "a", |i| token(i, b'a')).then(|i| token(i, b'c')));
+    let err = parse_tracked("top", input, parser).unwrap_err();
+    assert_eq!((err.production, err.offset, err.expected), ("b", 1, Some(b'c')));
+
+    let err = parse_tracked("top", input, |i| token(i, b'x')).unwrap_err();
+    assert_eq!((err.production, err.offset), ("top", 0));
+}
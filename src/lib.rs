@@ -5,16 +5,34 @@
 extern crate chomp;
 extern crate chrono;
 extern crate bytes;
+extern crate idna;
+extern crate ring;
 #[macro_use]
 extern crate log;
 
 // pub mod rfc2822;
 pub mod rfc5322;
 pub mod mime;
+pub mod stream;
+pub mod error;
+pub mod lint;
+pub mod normalize;
+pub mod rfc2047;
+pub mod rfc2369;
+pub mod rfc8601;
+pub mod rfc6376;
+pub mod recover;
+pub mod subject;
+pub mod thread;
+pub mod message_id;
+pub mod dkim;
 mod util;
 
 use std::fmt;
+use std::str;
+use std::net::{Ipv4Addr, Ipv6Addr};
 
+use chrono::{Datelike, Weekday};
 use chrono::datetime::DateTime;
 use chrono::offset::fixed::FixedOffset;
 use bytes::Bytes;
@@ -25,10 +43,162 @@ use chomp::parsers::*;
 use chomp::combinators::*;
 
 use rfc5322::*;
+use rfc2369::*;
+use rfc8601::*;
+use rfc6376::*;
+use error::{ParseError, IdnaError, parse_tracked};
+
+/// How closely field values must follow the RFC 5322 grammar
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Profile {
+    /// Only the current syntax of section 3. Obsolete folding whitespace and
+    /// quoted-pairs are still accepted inside words and comments.
+    Strict,
+    /// The current syntax plus the obsolete syntax of section 4
+    Obsolete,
+    /// The obsolete syntax plus malformations common in archived mail:
+    /// msg-ids without an id-left, "Sept", single digit hours, zone names
+    /// from a `ZoneTable` and unknown alphabetic zones, trailing garbage
+    /// after a value, and UTF-8 in values when `ParseOptions::utf8` is off
+    Lenient,
+}
+
+/// Options for interpreting field values
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ParseOptions {
+    pub profile: Profile,
+    /// Accept UTF-8 in words, quoted strings, comments, domain literals and
+    /// unstructured text (RFC 6532). Otherwise values must be ASCII, though
+    /// the lenient profile accepts UTF-8 with a warning.
+    pub utf8: bool,
+}
+
+impl From<Profile> for ParseOptions {
+    fn from(profile: Profile) -> ParseOptions {
+        ParseOptions{ profile: profile, utf8: false }
+    }
+}
+
+// Time zone names that aren't part of RFC 5322, and their offsets east of UTC
+// in minutes. Several abbreviations name more than one zone; each is given the
+// meaning most common in mail:
+//
+//  BST is British Summer Time, not Bangladesh Standard Time (+0600)
+//  IST is India Standard Time, not Irish (+0100) or Israel (+0200)
+//  AST is Atlantic Standard Time, not Arabia Standard Time (+0300)
+//
+// CST is always US Central Standard Time as obs-zone defines it, not China
+// Standard Time (+0800).
+pub const ZONE_ABBREVIATIONS: &'static [(&'static str, i32)] = &[
+    ("UTC", 0), ("WET", 0), ("WEST", 60), ("BST", 60), ("IST", 330),
+    ("CET", 60), ("CEST", 120), ("MET", 60), ("MEST", 120),
+    ("EET", 120), ("EEST", 180), ("MSK", 180), ("SAST", 120),
+    ("WAT", 60), ("CAT", 120), ("EAT", 180), ("PKT", 300),
+    ("ICT", 420), ("WIB", 420), ("HKT", 480), ("SGT", 480),
+    ("AWST", 480), ("PHT", 480), ("KST", 540), ("JST", 540),
+    ("ACST", 570), ("ACDT", 630), ("AEST", 600), ("AEDT", 660),
+    ("NZST", 720), ("NZDT", 780), ("HST", -600), ("AKST", -540),
+    ("AKDT", -480), ("AST", -240), ("ADT", -180), ("NST", -210),
+    ("NDT", -150), ("BRT", -180), ("ART", -180),
+];
+
+/// How to interpret time zone names in dates
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ZoneTable<'a> {
+    /// Zone names and their offsets east of UTC in minutes, used by the
+    /// lenient profile. Names match case-insensitively and the first match
+    /// wins, so entries can be put ahead of `ZONE_ABBREVIATIONS` to override
+    /// its choices.
+    pub names: &'a [(&'a str, i32)],
+    /// Read the single letter military zones as military time, "A" through
+    /// "M" east of UTC and "N" through "Y" west. RFC 822 had their signs
+    /// reversed, so RFC 5322 suggests treating them all as UTC, which is what
+    /// happens otherwise.
+    pub military: bool,
+}
+
+impl Default for ZoneTable<'static> {
+    fn default() -> ZoneTable<'static> {
+        ZoneTable{
+            names: ZONE_ABBREVIATIONS,
+            military: true,
+        }
+    }
+}
+
+/// Whether a date's zone gives the sender's local offset from UTC
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LocalZone {
+    Known,
+    /// The zone was "-0000", which section 3.3 says means the time is UTC
+    /// but the local zone is unknown. Military zones, when the `ZoneTable`
+    /// reads them as UTC, and zone names the lenient profile doesn't
+    /// recognize are unknown too.
+    Unknown,
+}
+
+/// A date-time, along with whether its offset is the sender's local zone
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ZonedDateTime {
+    pub date_time: DateTime<FixedOffset>,
+    pub zone: LocalZone,
+}
+
+impl ZonedDateTime {
+    /// Returns the date-time in the sender's local zone, or None if the
+    /// zone is unknown
+    pub fn local(&self) -> Option<DateTime<FixedOffset>> {
+        match self.zone {
+            LocalZone::Known => Some(self.date_time),
+            LocalZone::Unknown => None,
+        }
+    }
+}
+
+impl fmt::Display for ZonedDateTime {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match self.zone {
+            LocalZone::Known => write!(f, "{}", format_date_time(&self.date_time)),
+            LocalZone::Unknown => write!(f, "{}", format_date_time_unknown_zone(&self.date_time)),
+        }
+    }
+}
+
+/// Which relaxation was needed to parse a value
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Deviation {
+    /// The value uses the obsolete syntax
+    Obsolete,
+    /// The value only parses with the lenient profile
+    Lenient,
+    /// The day-of-week doesn't match the date
+    DayOfWeek,
+}
+
+/// Records that a value parsed, but not under the strict profile or with a
+/// day-of-week that contradicts the date
+#[derive(Clone, Debug, PartialEq)]
+pub struct Warning {
+    /// The grammar production being parsed, e.g. "address_list"
+    pub production: &'static str,
+    pub deviation: Deviation,
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match self.deviation {
+            Deviation::Obsolete => write!(f, "{} uses obsolete syntax", self.production),
+            Deviation::Lenient => write!(f, "{} is malformed", self.production),
+            Deviation::DayOfWeek => write!(f, "{} doesn't match the date", self.production),
+        }
+    }
+}
 
 pub enum FieldValue<T> {
     Ok(T),
-    Raw(Bytes),
+    /// Parsed, but only by relaxing the grammar
+    Accepted(T, Vec<Warning>),
+    Raw(Bytes, ParseError),
     Missing,
 }
 
@@ -36,12 +206,19 @@ impl<T> FieldValue<T> {
     pub fn is_ok(&self) -> bool {
         match self {
             &FieldValue::Ok(_) => true,
+            &FieldValue::Accepted(..) => true,
+            _ => false,
+        }
+    }
+    pub fn is_accepted(&self) -> bool {
+        match self {
+            &FieldValue::Accepted(..) => true,
             _ => false,
         }
     }
     pub fn is_raw(&self) -> bool {
         match self {
-            &FieldValue::Raw(_) => true,
+            &FieldValue::Raw(..) => true,
             _ => false,
         }
     }
@@ -54,42 +231,262 @@ impl<T> FieldValue<T> {
     pub fn unwrap(self) -> T {
         match self {
             FieldValue::Ok(v) => v,
-            FieldValue::Raw(b) => panic!("unwrap raw value {:?}", b),
+            FieldValue::Accepted(v, _) => v,
+            FieldValue::Raw(b, e) => panic!("unwrap raw value {:?}: {}", b, e),
             FieldValue::Missing => panic!("unwrap missing value"),
         }
     }
     // TODO: Return Vec<u8>
     pub fn raw(&self) -> String {
         match self {
-            &FieldValue::Raw(ref b) => String::from_utf8(b.buf().bytes().to_vec()).unwrap(),
+            &FieldValue::Raw(ref b, _) => String::from_utf8_lossy(b.buf().bytes()).into_owned(),
             &FieldValue::Ok(_) => panic!("raw called on parsed value"),
+            &FieldValue::Accepted(..) => panic!("raw called on parsed value"),
             &FieldValue::Missing => panic!("raw called on missing value"),
         }
     }
+    /// Returns the reason parsing failed for raw values
+    pub fn error(&self) -> Option<&ParseError> {
+        match self {
+            &FieldValue::Raw(_, ref e) => Some(e),
+            _ => None,
+        }
+    }
+    /// Converts to an Option, discarding raw values and warnings
+    pub fn ok(self) -> Option<T> {
+        match self {
+            FieldValue::Ok(v) => Some(v),
+            FieldValue::Accepted(v, _) => Some(v),
+            _ => None,
+        }
+    }
+    /// Returns the relaxations needed to parse accepted values
+    pub fn warnings(&self) -> &[Warning] {
+        match self {
+            &FieldValue::Accepted(_, ref w) => w,
+            _ => &[],
+        }
+    }
+    /// Converts a parsed value, keeping any warnings
+    pub fn map<U, F: FnOnce(T) -> U>(self, f: F) -> FieldValue<U> {
+        match self {
+            FieldValue::Ok(v) => FieldValue::Ok(f(v)),
+            FieldValue::Accepted(v, w) => FieldValue::Accepted(f(v), w),
+            FieldValue::Raw(b, e) => FieldValue::Raw(b, e),
+            FieldValue::Missing => FieldValue::Missing,
+        }
+    }
+}
+
+// Parses a field value, keeping the raw value and the reason for failure if
+// it doesn't parse
+fn parse_value<'a, T, F>(data: &'a [u8], production: &'static str, parser: F) -> FieldValue<T> where
+F: FnOnce(&'a [u8]) -> SimpleResult<&'a [u8], T>,
+{
+    match parse_tracked(production, data, parser) {
+        Ok(v) => FieldValue::Ok(v),
+        Err(e) => FieldValue::Raw(Bytes::from_slice(data), e),
+    }
+}
+
+// Returns an error if `data` isn't ASCII, or with `utf8` isn't well-formed
+// UTF-8. Text productions only check UTF-8 inside `utf8`, and lenient
+// parsing can skip over any bytes.
+fn check_encoding(data: &[u8], production: &'static str, utf8: bool) -> Option<ParseError> {
+    let pos = if utf8 {
+        match str::from_utf8(data) {
+            Ok(_) => return None,
+            Err(e) => e.valid_up_to(),
+        }
+    } else {
+        match data.iter().position(|&c| c > 127) {
+            Some(pos) => pos,
+            None => return None,
+        }
+    };
+    Some(ParseError::new(production, data, &data[pos..], Error::unexpected()))
+}
+
+// Runs `parser` with UTF-8 enabled if `enabled`
+fn utf8_if<'a, T, F>(i: &'a [u8], enabled: bool, parser: F) -> SimpleResult<&'a [u8], T> where
+F: FnOnce(&'a [u8]) -> SimpleResult<&'a [u8], T>,
+{
+    if enabled { utf8(i, parser) } else { parser(i) }
+}
+
+// Parses a field value with each profile up to `profile` in turn, from
+// strictest to most lenient, recording a warning if the strict profile wasn't
+// enough. Only the lenient profile may leave part of the value unparsed, or
+// contain UTF-8 without `utf8`.
+fn parse_profile<'a, T, F>(data: &'a [u8], production: &'static str, options: ParseOptions, parser: F) -> FieldValue<T> where
+F: Fn(&'a [u8], Profile) -> SimpleResult<&'a [u8], T>,
+{
+    let profile = options.profile;
+    let mut utf8 = options.utf8;
+    if let Some(e) = check_encoding(data, production, utf8) {
+        if profile != Profile::Lenient || str::from_utf8(data).is_err() {
+            return FieldValue::Raw(Bytes::from_slice(data), e);
+        }
+        utf8 = true;
+    }
+    let encoding_warning = if utf8 == options.utf8 {
+        None
+    } else {
+        Some(Warning{ production: production, deviation: Deviation::Lenient })
+    };
+
+    let tiers = [
+        (Profile::Strict, None),
+        (Profile::Obsolete, Some(Deviation::Obsolete)),
+        (Profile::Lenient, Some(Deviation::Lenient)),
+    ];
+    let mut value = FieldValue::Missing;
+    for &(tier, deviation) in tiers.iter() {
+        value = match tier {
+            Profile::Lenient => parse_value(data, production, |i| {
+                utf8_if(i, utf8, |i| parser(i, tier))
+            }),
+            _ => parse_value(data, production, |i| {
+                utf8_if(i, utf8, |i| parser(i, tier)).bind(|i, v| end_of_value(i).map(|_| v))
+            }),
+        };
+        value = match value {
+            FieldValue::Ok(v) => {
+                let warnings: Vec<Warning> = encoding_warning.clone().into_iter()
+                    .chain(deviation.map(|d| Warning{ production: production, deviation: d }))
+                    .collect();
+                return if warnings.is_empty() {
+                    FieldValue::Ok(v)
+                } else {
+                    FieldValue::Accepted(v, warnings)
+                };
+            },
+            v => v,
+        };
+        if tier == profile {
+            break;
+        }
+    }
+    value
+}
+
+/// Parses a complete message, reporting where parsing stopped if the input
+/// isn't entirely a message
+pub fn parse_message(input: &[u8]) -> Result<Message<&[u8]>, ParseError> {
+    parse_tracked("message", input, message_eof)
 }
 
 impl<T: fmt::Debug> fmt::Debug for FieldValue<T> {
     fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
         match self {
             &FieldValue::Ok(ref v) => write!(f, "{:?}", v),
-            &FieldValue::Raw(ref b) => write!(f, "{:?}", b),
+            &FieldValue::Accepted(ref v, ref w) => write!(f, "{:?} {:?}", v, w),
+            &FieldValue::Raw(ref b, ref e) => write!(f, "{:?} ({})", b, e),
             &FieldValue::Missing => write!(f, "<missing>"),
         }
     }
 }
 
-#[derive(PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum Day { Mon, Tue, Wed, Thu, Fri, Sat, Sun }
 
+impl Day {
+    pub fn weekday(&self) -> Weekday {
+        match *self {
+            Day::Mon => Weekday::Mon,
+            Day::Tue => Weekday::Tue,
+            Day::Wed => Weekday::Wed,
+            Day::Thu => Weekday::Thu,
+            Day::Fri => Weekday::Fri,
+            Day::Sat => Weekday::Sat,
+            Day::Sun => Weekday::Sun,
+        }
+    }
+}
+
 #[derive(Debug, PartialEq)]
 pub enum Month { Jan, Feb, Mar, Apr, May, Jun, Jul, Aug, Sep, Oct, Nov, Dec }
 
-#[derive(Debug, PartialEq)]
+/// The part of an addr-spec after the "@"
+#[derive(Clone, Debug, PartialEq)]
+pub enum Domain {
+    /// A domain name, e.g. "example.com"
+    Name(String),
+    /// An IPv4 address literal, e.g. "[192.0.2.1]"
+    Ipv4(Ipv4Addr),
+    /// An IPv6 address literal, e.g. "[IPv6:2001:db8::1]"
+    Ipv6(Ipv6Addr),
+    /// Any other address literal, "[" tag ":" content "]"
+    General {
+        tag: String,
+        content: String,
+    },
+}
+
+impl fmt::Display for Domain {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match self {
+            &Domain::Name(ref name) => write!(f, "{}", name),
+            &Domain::Ipv4(ref addr) => write!(f, "[{}]", addr),
+            &Domain::Ipv6(ref addr) => write!(f, "[IPv6:{}]", addr),
+            &Domain::General{ref tag, ref content} => write!(f, "[{}:{}]", tag, content),
+        }
+    }
+}
+
+impl Domain {
+    /// Converts a domain name to A-labels following UTS 46, e.g.
+    /// "bücher.example" to "xn--bcher-kva.example". Address literals are
+    /// returned unchanged.
+    pub fn to_ascii(&self) -> Result<Domain, IdnaError> {
+        match self {
+            &Domain::Name(ref name) => match idna::domain_to_ascii(name) {
+                Ok(ascii) => Ok(Domain::Name(ascii)),
+                Err(_) => Err(IdnaError{ domain: name.clone() }),
+            },
+            _ => Ok(self.clone()),
+        }
+    }
+
+    /// Converts a domain name to U-labels following UTS 46, e.g.
+    /// "xn--bcher-kva.example" to "bücher.example". Address literals are
+    /// returned unchanged.
+    pub fn to_unicode(&self) -> Result<Domain, IdnaError> {
+        match self {
+            &Domain::Name(ref name) => match idna::domain_to_unicode(name) {
+                (unicode, Ok(())) => Ok(Domain::Name(unicode)),
+                (_, Err(_)) => Err(IdnaError{ domain: name.clone() }),
+            },
+            _ => Ok(self.clone()),
+        }
+    }
+
+    /// Returns a key that's equal for equivalent domains, e.g.
+    /// "Bücher.example" and "xn--bcher-kva.example"
+    pub fn comparison_key(&self) -> String {
+        match self.to_ascii() {
+            Ok(Domain::Name(ascii)) => ascii,
+            // NOTE: Names UTS 46 rejects can still be compared, just without
+            // any mapping beyond case
+            Err(e) => e.domain.to_lowercase(),
+            Ok(literal) => literal.to_string(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
 pub enum Address {
     Mailbox {
         local_part: String,
-        domain: String,
+        domain: Domain,
+        /// The display name, or if there isn't one, a comment following the
+        /// address, e.g. "John Doe" in "jdoe@example.com (John Doe)"
         display_name: Option<String>,
+        /// The text of each comment in the mailbox, in order
+        comments: Vec<String>,
+        /// The obsolete source route, e.g. relay1 and relay2 in
+        /// "<@relay1,@relay2:jdoe@example.com>"
+        route: Option<Vec<Domain>>,
     },
     Group {
         display_name: String,
@@ -97,116 +494,658 @@ pub enum Address {
     },
 }
 
-#[derive(Debug, PartialEq)]
+impl fmt::Display for Address {
+    // Formats the address as it's written in a header field, without comments
+    // or a route, which RFC 5322 says mustn't be generated
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match self {
+            &Address::Mailbox{ref local_part, ref domain, ref display_name, ..} => {
+                let local_part = if is_dot_atom(local_part) {
+                    local_part.clone()
+                } else {
+                    util::quote(local_part)
+                };
+                match display_name.as_ref().map(|n| n.trim()) {
+                    Some(name) if !name.is_empty() => {
+                        write!(f, "{} <{}@{}>", format_phrase(name), local_part, domain)
+                    },
+                    _ => write!(f, "{}@{}", local_part, domain),
+                }
+            },
+            &Address::Group{ref display_name, ref mailboxes} => {
+                try!(write!(f, "{}:", format_phrase(display_name.trim())));
+                for (n, mailbox) in mailboxes.iter().enumerate() {
+                    try!(write!(f, "{} {}", if n == 0 { "" } else { "," }, mailbox));
+                }
+                write!(f, ";")
+            },
+        }
+    }
+}
+
+// Formats a display name as a phrase: atoms as they are, other ASCII text as
+// a quoted-string, and anything else as encoded-words. Display names are
+// parsed without decoding, so encoded-words are written as they are, but other
+// text that looks like an encoded-word is quoted, as encoded-words aren't
+// decoded in quoted-strings.
+fn format_phrase(name: &str) -> String {
+    let printable = name.chars().all(|c| c == ' ' || c == '\t' || (c > ' ' && c <= '~'));
+    let words: Vec<&str> = name.split_whitespace().collect();
+    let plain = |w: &&str| is_atom(w) && (!w.contains("=?") || rfc2047::is_encoded_word(w));
+    if !printable {
+        rfc2047::encode_phrase(name)
+    } else if !words.is_empty() && words.iter().all(plain) {
+        words.join(" ")
+    } else {
+        util::quote(name)
+    }
+}
+
+impl Address {
+    /// Converts the domains of the address, or of each mailbox in a group, to
+    /// A-labels
+    pub fn to_ascii(&self) -> Result<Address, IdnaError> {
+        self.map_domains(&Domain::to_ascii)
+    }
+
+    /// Converts the domains of the address, or of each mailbox in a group, to
+    /// U-labels
+    pub fn to_unicode(&self) -> Result<Address, IdnaError> {
+        self.map_domains(&Domain::to_unicode)
+    }
+
+    /// Returns "local-part@domain" with the domain's comparison key, so
+    /// mailboxes at equivalent domains compare equal. Groups have no key.
+    pub fn comparison_key(&self) -> Option<String> {
+        match self {
+            &Address::Mailbox{ref local_part, ref domain, ..} => {
+                Some(format!("{}@{}", local_part, domain.comparison_key()))
+            },
+            &Address::Group{..} => None,
+        }
+    }
+
+    fn map_domains<F>(&self, f: &F) -> Result<Address, IdnaError> where
+    F: Fn(&Domain) -> Result<Domain, IdnaError>,
+    {
+        match self {
+            &Address::Mailbox{ref local_part, ref domain, ref display_name, ref comments, ref route} => {
+                let route = match route {
+                    &Some(ref domains) => {
+                        let mut converted = Vec::with_capacity(domains.len());
+                        for domain in domains.iter() {
+                            converted.push(try!(f(domain)));
+                        }
+                        Some(converted)
+                    },
+                    &None => None,
+                };
+                Ok(Address::Mailbox{
+                    local_part: local_part.clone(),
+                    domain: try!(f(domain)),
+                    display_name: display_name.clone(),
+                    comments: comments.clone(),
+                    route: route,
+                })
+            },
+            &Address::Group{ref display_name, ref mailboxes} => {
+                let mut converted = Vec::with_capacity(mailboxes.len());
+                for mailbox in mailboxes.iter() {
+                    converted.push(try!(mailbox.map_domains(f)));
+                }
+                Ok(Address::Group{
+                    display_name: display_name.clone(),
+                    mailboxes: converted,
+                })
+            },
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct MessageID {
     pub id_left: Option<String>,
     pub id_right: String,
 }
 
-#[derive(Debug, PartialEq)]
-pub struct Message<I: U8Input> {
-    // pub traces: Vec<Trace>,
-    pub fields: Vec<Field<I>>,
-    body: Option<I::Buffer>,
-}
+// Formats the ID as a msg-id, e.g. "<1234@local.machine.example>"
+impl fmt::Display for MessageID {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match self.id_left {
+            Some(ref left) => write!(f, "<{}@{}>", left, self.id_right),
+            None => write!(f, "<{}>", self.id_right),
+        }
+    }
+}
+
+/// A server's record of receiving the message, from a Received field
+#[derive(Debug, PartialEq)]
+pub struct Received {
+    /// The received-tokens, e.g. "from", "x.y.test", "by", "example.net"
+    pub tokens: Vec<String>,
+    /// When the message was received, which obs-received may omit
+    pub date_time: Option<DateTime<FixedOffset>>,
+}
+
+/// A mailing list's identifier, from a List-Id field (RFC 2919)
+#[derive(Clone, Debug, PartialEq)]
+pub struct ListId {
+    /// The phrase before the identifier, e.g. "Rust users"
+    pub description: Option<String>,
+    /// The identifier, e.g. "users.rust-lang.org"
+    pub id: String,
+}
+
+/// Where to post to a mailing list, from a List-Post field
+#[derive(Clone, Debug, PartialEq)]
+pub enum ListPost {
+    /// URLs for posting, most preferred first
+    Urls(Vec<String>),
+    /// "NO": posting to the list isn't allowed
+    No,
+}
+
+/// A summary of a message's mailing list fields. The URLs of each field
+/// are in order of preference.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ListInfo {
+    pub id: Option<ListId>,
+    pub help: Vec<String>,
+    pub unsubscribe: Vec<String>,
+    /// List-Unsubscribe-Post says an HTTPS unsubscribe URL accepts a POST
+    /// without further interaction (RFC 8058)
+    pub one_click: bool,
+    pub subscribe: Vec<String>,
+    pub post: Option<ListPost>,
+    pub owner: Vec<String>,
+    pub archive: Vec<String>,
+}
+
+impl ListInfo {
+    /// Returns the URL to POST "List-Unsubscribe=One-Click" to, if the list
+    /// supports one-click unsubscription
+    pub fn one_click_url(&self) -> Option<&str> {
+        if !self.one_click {
+            return None;
+        }
+        self.unsubscribe.iter()
+            .find(|u| u.len() >= 8 && u.is_char_boundary(8) && u[..8].eq_ignore_ascii_case("https://"))
+            .map(|u| &u[..])
+    }
+}
+
+/// An Authentication-Results field value (RFC 8601)
+#[derive(Clone, Debug, PartialEq)]
+pub struct AuthenticationResults {
+    /// The host that did the checks, e.g. "mx.example.com"
+    pub authserv_id: String,
+    /// The field's version, which means 1 when it's omitted
+    pub version: Option<u32>,
+    /// The result of each check, or none for "none"
+    pub results: Vec<AuthResult>,
+}
+
+/// The result of one method of checking a message, e.g. "dkim=pass". The
+/// method, result and property names are case-insensitive, so they're in
+/// lower case.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AuthResult {
+    /// The method, e.g. "spf", "dkim", "dmarc", "arc", "iprev" or "auth"
+    pub method: String,
+    pub method_version: Option<u32>,
+    /// The result, e.g. "pass", "fail" or "none"
+    pub result: String,
+    pub reason: Option<String>,
+    pub properties: Vec<AuthProperty>,
+    /// The comments, which often explain the result, e.g. "1024-bit key"
+    pub comments: Vec<String>,
+}
+
+impl AuthResult {
+    /// Returns the value of the property `ptype`.`property`, e.g. "header"
+    /// and "d" for "header.d=example.com"
+    pub fn property(&self, ptype: &str, property: &str) -> Option<&str> {
+        self.properties.iter()
+            .find(|p| p.ptype.eq_ignore_ascii_case(ptype) && p.property.eq_ignore_ascii_case(property))
+            .map(|p| &p.value[..])
+    }
+}
+
+/// A property of the message that a result applies to, e.g.
+/// "smtp.mailfrom=example.net"
+#[derive(Clone, Debug, PartialEq)]
+pub struct AuthProperty {
+    pub ptype: String,
+    pub property: String,
+    pub value: String,
+}
+
+/// A DKIM-Signature field value (RFC 6376)
+#[derive(Clone, Debug, PartialEq)]
+pub struct DKIMSignature {
+    /// The "v" tag, which must be 1
+    pub version: u32,
+    pub algorithm: SigningAlgorithm,
+    pub header_canonicalization: Canonicalization,
+    pub body_canonicalization: Canonicalization,
+    /// The signing domain, e.g. "example.com"
+    pub domain: String,
+    /// The selector, which names the key under "_domainkey" in the domain
+    pub selector: String,
+    /// The names of the signed fields, in the order they were signed
+    pub headers: Vec<String>,
+    pub body_hash: Vec<u8>,
+    pub signature: Vec<u8>,
+    /// The "i" tag, the agent the signer acts for, e.g. "@example.com"
+    pub identity: Option<String>,
+    /// How many octets of the body are signed, or none for all of it
+    pub length: Option<u64>,
+    /// How to fetch the public key, which means "dns/txt" when omitted
+    pub query_methods: Vec<String>,
+    /// When the message was signed, in seconds since the epoch
+    pub timestamp: Option<u64>,
+    /// When the signature expires, in seconds since the epoch
+    pub expiration: Option<u64>,
+    /// The "z" tag, copies of the fields as they were signed
+    pub copied_headers: Vec<(String, String)>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SigningAlgorithm {
+    RsaSha1,
+    RsaSha256,
+    Ed25519Sha256,
+}
+
+/// How a header or body is normalized before it's hashed
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Canonicalization {
+    /// Almost no changes
+    Simple,
+    /// Whitespace and field names are normalized, so signatures survive
+    /// refolding
+    Relaxed,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Message<I: U8Input> {
+    // pub traces: Vec<Trace>,
+    pub fields: Vec<Field<I>>,
+    body: Option<I::Buffer>,
+    /// The profile used to parse field values
+    pub profile: Profile,
+    /// Whether field values may contain UTF-8 (RFC 6532)
+    pub utf8: bool,
+    // Indexes of the fields with whitespace between the name and the colon,
+    // which only the obsolete syntax allows
+    obsolete_names: Vec<usize>,
+}
+
+impl<I: U8Input> Message<I> {
+    fn options(&self) -> ParseOptions {
+        ParseOptions{ profile: self.profile, utf8: self.utf8 }
+    }
+
+    pub fn body(&self) -> Bytes {
+        match self.body {
+            Some(ref buf) => Bytes::from_slice(&buf.to_vec()),
+            None => Bytes::empty(),
+        }
+    }
+
+    pub fn from(&self) -> FieldValue<Vec<Address>> {
+        self.fields.iter().filter_map(|i| {
+            match i {
+                &Field::From(ref f) => Some(f.addresses_with(self.options())),
+                _ => None,
+            }
+        }).next().unwrap_or(FieldValue::Missing)
+    }
+
+    pub fn date(&self) -> FieldValue<DateTime<FixedOffset>> {
+        self.fields.iter().filter_map(|i| {
+            match i {
+                &Field::Date(ref f) => Some(f.date_time_with(self.options())),
+                _ => None,
+            }
+        }).next().unwrap_or(FieldValue::Missing)
+    }
+
+    /// Returns the date, keeping whether its zone was the sender's local
+    /// zone
+    pub fn zoned_date(&self) -> FieldValue<ZonedDateTime> {
+        self.fields.iter().filter_map(|i| {
+            match i {
+                &Field::Date(ref f) => Some(f.zoned_date_time_with(self.options(), ZoneTable::default())),
+                _ => None,
+            }
+        }).next().unwrap_or(FieldValue::Missing)
+    }
+
+    pub fn sender(&self) -> FieldValue<Address> {
+        self.fields.iter().filter_map(|i| {
+            match i {
+                &Field::Sender(ref f) => Some(f.address_with(self.options())),
+                _ => None,
+            }
+        }).next().unwrap_or(FieldValue::Missing)
+    }
+
+    pub fn reply_to(&self) -> FieldValue<Vec<Address>> {
+        self.fields.iter().filter_map(|i| {
+            match i {
+                &Field::ReplyTo(ref f) => Some(f.addresses_with(self.options())),
+                _ => None,
+            }
+        }).next().unwrap_or(FieldValue::Missing)
+    }
+
+    pub fn to(&self) -> FieldValue<Vec<Address>> {
+        self.fields.iter().filter_map(|i| {
+            match i {
+                &Field::To(ref f) => Some(f.addresses_with(self.options())),
+                _ => None,
+            }
+        }).next().unwrap_or(FieldValue::Missing)
+    }
+
+    pub fn cc(&self) -> FieldValue<Vec<Address>> {
+        self.fields.iter().filter_map(|i| {
+            match i {
+                &Field::Cc(ref f) => Some(f.addresses_with(self.options())),
+                _ => None,
+            }
+        }).next().unwrap_or(FieldValue::Missing)
+    }
+
+    pub fn bcc(&self) -> FieldValue<Vec<Address>> {
+        self.fields.iter().filter_map(|i| {
+            match i {
+                &Field::Bcc(ref f) => Some(f.bcc_addresses_with(self.options())),
+                _ => None,
+            }
+        }).next().unwrap_or(FieldValue::Missing)
+    }
+
+    pub fn message_id(&self) -> FieldValue<MessageID> {
+        self.fields.iter().filter_map(|i| {
+            match i {
+                &Field::MessageID(ref f) => Some(f.message_id_with(self.options())),
+                _ => None,
+            }
+        }).next().unwrap_or(FieldValue::Missing)
+    }
+
+    pub fn references(&self) -> FieldValue<Vec<MessageID>> {
+        self.fields.iter().filter_map(|i| {
+            match i {
+                &Field::References(ref f) => Some(f.message_ids_with(self.options())),
+                _ => None,
+            }
+        }).next().unwrap_or(FieldValue::Missing)
+    }
+
+    pub fn in_reply_to(&self) -> FieldValue<Vec<MessageID>> {
+        self.fields.iter().filter_map(|i| {
+            match i {
+                &Field::InReplyTo(ref f) => Some(f.message_ids_with(self.options())),
+                _ => None,
+            }
+        }).next().unwrap_or(FieldValue::Missing)
+    }
+
+    /// Returns the subject with encoded-words decoded
+    pub fn subject(&self) -> FieldValue<String> {
+        self.fields.iter().filter_map(|i| {
+            match i {
+                &Field::Subject(ref f) => Some(f.decoded_with(self.options())),
+                _ => None,
+            }
+        }).next().unwrap_or(FieldValue::Missing)
+    }
+
+    /// Returns each Comments field with encoded-words decoded
+    pub fn comments(&self) -> Vec<FieldValue<String>> {
+        self.fields.iter().filter_map(|i| {
+            match i {
+                &Field::Comments(ref f) => Some(f.decoded_with(self.options())),
+                _ => None,
+            }
+        }).collect()
+    }
+
+    /// Returns the phrases of each Keywords field
+    pub fn keywords(&self) -> Vec<FieldValue<Vec<String>>> {
+        self.fields.iter().filter_map(|i| {
+            match i {
+                &Field::Keywords(ref f) => Some(f.keywords_with(self.options())),
+                _ => None,
+            }
+        }).collect()
+    }
+
+    /// Returns the address bounces go to, or None for the null path "<>"
+    pub fn return_path(&self) -> FieldValue<Option<Address>> {
+        self.fields.iter().filter_map(|i| {
+            match i {
+                &Field::ReturnPath(ref f) => Some(f.path_with(self.options())),
+                _ => None,
+            }
+        }).next().unwrap_or(FieldValue::Missing)
+    }
+
+    /// Returns each Received field, the most recent first
+    pub fn received(&self) -> Vec<FieldValue<Received>> {
+        self.fields.iter().filter_map(|i| {
+            match i {
+                &Field::Received(ref f) => Some(f.received_with(self.options())),
+                _ => None,
+            }
+        }).collect()
+    }
+
+    // Resent fields are prepended to the message in blocks, one for each time
+    // it's resent, so the first of each is from the most recent block
+
+    pub fn resent_date(&self) -> FieldValue<DateTime<FixedOffset>> {
+        self.fields.iter().filter_map(|i| {
+            match i {
+                &Field::ResentDate(ref f) => Some(f.date_time_with(self.options())),
+                _ => None,
+            }
+        }).next().unwrap_or(FieldValue::Missing)
+    }
+
+    pub fn resent_from(&self) -> FieldValue<Vec<Address>> {
+        self.fields.iter().filter_map(|i| {
+            match i {
+                &Field::ResentFrom(ref f) => Some(f.addresses_with(self.options())),
+                _ => None,
+            }
+        }).next().unwrap_or(FieldValue::Missing)
+    }
+
+    pub fn resent_sender(&self) -> FieldValue<Address> {
+        self.fields.iter().filter_map(|i| {
+            match i {
+                &Field::ResentSender(ref f) => Some(f.address_with(self.options())),
+                _ => None,
+            }
+        }).next().unwrap_or(FieldValue::Missing)
+    }
+
+    pub fn resent_to(&self) -> FieldValue<Vec<Address>> {
+        self.fields.iter().filter_map(|i| {
+            match i {
+                &Field::ResentTo(ref f) => Some(f.addresses_with(self.options())),
+                _ => None,
+            }
+        }).next().unwrap_or(FieldValue::Missing)
+    }
+
+    pub fn resent_cc(&self) -> FieldValue<Vec<Address>> {
+        self.fields.iter().filter_map(|i| {
+            match i {
+                &Field::ResentCc(ref f) => Some(f.addresses_with(self.options())),
+                _ => None,
+            }
+        }).next().unwrap_or(FieldValue::Missing)
+    }
 
-impl<I: U8Input> Message<I> {
-    pub fn body(&self) -> Bytes {
-        match self.body {
-            Some(ref buf) => Bytes::from_slice(&buf.to_vec()),
-            None => Bytes::empty(),
-        }
+    pub fn resent_bcc(&self) -> FieldValue<Vec<Address>> {
+        self.fields.iter().filter_map(|i| {
+            match i {
+                &Field::ResentBcc(ref f) => Some(f.bcc_addresses_with(self.options())),
+                _ => None,
+            }
+        }).next().unwrap_or(FieldValue::Missing)
     }
 
-    pub fn from(&self) -> FieldValue<Vec<Address>> {
+    pub fn resent_reply_to(&self) -> FieldValue<Vec<Address>> {
         self.fields.iter().filter_map(|i| {
             match i {
-                &Field::From(ref f) => Some(f.addresses()),
+                &Field::ResentReplyTo(ref f) => Some(f.addresses_with(self.options())),
                 _ => None,
             }
         }).next().unwrap_or(FieldValue::Missing)
     }
 
-    pub fn date(&self) -> FieldValue<DateTime<FixedOffset>> {
+    pub fn resent_message_id(&self) -> FieldValue<MessageID> {
         self.fields.iter().filter_map(|i| {
             match i {
-                &Field::Date(ref f) => Some(f.date_time()),
+                &Field::ResentMessageID(ref f) => Some(f.message_id_with(self.options())),
                 _ => None,
             }
         }).next().unwrap_or(FieldValue::Missing)
     }
 
-    pub fn sender(&self) -> FieldValue<Address> {
+    pub fn list_id(&self) -> FieldValue<ListId> {
         self.fields.iter().filter_map(|i| {
             match i {
-                &Field::Sender(ref f) => Some(f.address()),
+                &Field::ListId(ref f) => Some(f.list_id_with(self.options())),
                 _ => None,
             }
         }).next().unwrap_or(FieldValue::Missing)
     }
 
-    pub fn reply_to(&self) -> FieldValue<Vec<Address>> {
+    pub fn list_help(&self) -> FieldValue<Vec<String>> {
         self.fields.iter().filter_map(|i| {
             match i {
-                &Field::ReplyTo(ref f) => Some(f.addresses()),
+                &Field::ListHelp(ref f) => Some(f.urls_with(self.options())),
                 _ => None,
             }
         }).next().unwrap_or(FieldValue::Missing)
     }
 
-    pub fn to(&self) -> FieldValue<Vec<Address>> {
+    pub fn list_unsubscribe(&self) -> FieldValue<Vec<String>> {
         self.fields.iter().filter_map(|i| {
             match i {
-                &Field::To(ref f) => Some(f.addresses()),
+                &Field::ListUnsubscribe(ref f) => Some(f.urls_with(self.options())),
                 _ => None,
             }
         }).next().unwrap_or(FieldValue::Missing)
     }
 
-    pub fn cc(&self) -> FieldValue<Vec<Address>> {
+    pub fn list_unsubscribe_post(&self) -> FieldValue<()> {
         self.fields.iter().filter_map(|i| {
             match i {
-                &Field::Cc(ref f) => Some(f.addresses()),
+                &Field::ListUnsubscribePost(ref f) => Some(f.one_click_with(self.options())),
                 _ => None,
             }
         }).next().unwrap_or(FieldValue::Missing)
     }
 
-    pub fn bcc(&self) -> FieldValue<Vec<Address>> {
+    pub fn list_subscribe(&self) -> FieldValue<Vec<String>> {
         self.fields.iter().filter_map(|i| {
             match i {
-                &Field::Bcc(ref f) => Some(f.addresses()),
+                &Field::ListSubscribe(ref f) => Some(f.urls_with(self.options())),
                 _ => None,
             }
         }).next().unwrap_or(FieldValue::Missing)
     }
 
-    pub fn message_id(&self) -> FieldValue<MessageID> {
+    pub fn list_post(&self) -> FieldValue<ListPost> {
         self.fields.iter().filter_map(|i| {
             match i {
-                &Field::MessageID(ref f) => Some(f.message_id()),
+                &Field::ListPost(ref f) => Some(f.post_with(self.options())),
                 _ => None,
             }
         }).next().unwrap_or(FieldValue::Missing)
     }
 
-    pub fn references(&self) -> FieldValue<Vec<MessageID>> {
+    pub fn list_owner(&self) -> FieldValue<Vec<String>> {
         self.fields.iter().filter_map(|i| {
             match i {
-                &Field::References(ref f) => Some(f.message_ids()),
+                &Field::ListOwner(ref f) => Some(f.urls_with(self.options())),
                 _ => None,
             }
         }).next().unwrap_or(FieldValue::Missing)
     }
 
-    pub fn in_reply_to(&self) -> FieldValue<Vec<MessageID>> {
+    pub fn list_archive(&self) -> FieldValue<Vec<String>> {
         self.fields.iter().filter_map(|i| {
             match i {
-                &Field::InReplyTo(ref f) => Some(f.message_ids()),
+                &Field::ListArchive(ref f) => Some(f.urls_with(self.options())),
                 _ => None,
             }
         }).next().unwrap_or(FieldValue::Missing)
     }
+
+    /// Returns each Authentication-Results field, the most recent first
+    pub fn authentication_results(&self) -> Vec<FieldValue<AuthenticationResults>> {
+        self.fields.iter().filter_map(|i| {
+            match i {
+                &Field::AuthenticationResults(ref f) => Some(f.results_with(self.options())),
+                _ => None,
+            }
+        }).collect()
+    }
+
+    /// Returns each DKIM-Signature field, the most recent first
+    pub fn dkim_signatures(&self) -> Vec<FieldValue<DKIMSignature>> {
+        self.fields.iter().filter_map(|i| {
+            match i {
+                &Field::DKIMSignature(ref f) => Some(f.signature_with(self.options())),
+                _ => None,
+            }
+        }).collect()
+    }
+
+    /// Summarizes the mailing list fields, or returns None if there aren't
+    /// any. Fields that don't parse are left out.
+    pub fn list_info(&self) -> Option<ListInfo> {
+        let has_list_fields = self.fields.iter().any(|f| {
+            match f {
+                &Field::ListId(_) | &Field::ListHelp(_) | &Field::ListUnsubscribe(_) |
+                &Field::ListUnsubscribePost(_) | &Field::ListSubscribe(_) | &Field::ListPost(_) |
+                &Field::ListOwner(_) | &Field::ListArchive(_) => true,
+                _ => false,
+            }
+        });
+        if !has_list_fields {
+            return None;
+        }
+
+        Some(ListInfo{
+            id: self.list_id().ok(),
+            help: self.list_help().ok().unwrap_or(vec!()),
+            unsubscribe: self.list_unsubscribe().ok().unwrap_or(vec!()),
+            one_click: self.list_unsubscribe_post().is_ok(),
+            subscribe: self.list_subscribe().ok().unwrap_or(vec!()),
+            post: self.list_post().ok(),
+            owner: self.list_owner().ok().unwrap_or(vec!()),
+            archive: self.list_archive().ok().unwrap_or(vec!()),
+        })
+    }
+
+    /// Returns the relaxations of `profile` needed to parse each field,
+    /// along with the field's name
+    pub fn warnings(&self) -> Vec<(String, Warning)> {
+        self.fields.iter().flat_map(|f| {
+            let name = f.name().to_string();
+            f.warnings(self.options()).into_iter().map(move |w| (name.clone(), w))
+        }).collect()
+    }
 }
 
 #[derive(Debug, PartialEq)]
@@ -241,6 +1180,16 @@ pub enum Field<I: U8Input> {
     ResentReplyTo(AddressesField<I>),
     ResentMessageID(MessageIDField<I>),
     MIMEVersion(MIMEVersionField),
+    ListId(ListIdField<I>),
+    ListHelp(ListUrlsField<I>),
+    ListUnsubscribe(ListUrlsField<I>),
+    ListUnsubscribePost(ListUnsubscribePostField<I>),
+    ListSubscribe(ListUrlsField<I>),
+    ListPost(ListUrlsField<I>),
+    ListOwner(ListUrlsField<I>),
+    ListArchive(ListUrlsField<I>),
+    AuthenticationResults(AuthenticationResultsField<I>),
+    DKIMSignature(DKIMSignatureField<I>),
     Optional(String, UnstructuredField<I>),
 }
 
@@ -271,6 +1220,16 @@ impl<I: U8Input> fmt::Debug for Field<I> {
             &Field::ResentReplyTo(ref v) =>     write!(f, "Resent-Reply-To: {}", v.to_string()),
             &Field::ResentMessageID(ref v) =>   write!(f, "Resent-Message-ID: {}", v.to_string()),
             &Field::MIMEVersion(ref v) =>       write!(f, "MIME-Version: {}.{}", v.top_version, v.sub_version),
+            &Field::ListId(ref v) =>            write!(f, "List-Id: {}", v.to_string()),
+            &Field::ListHelp(ref v) =>          write!(f, "List-Help: {}", v.to_string()),
+            &Field::ListUnsubscribe(ref v) =>   write!(f, "List-Unsubscribe: {}", v.to_string()),
+            &Field::ListUnsubscribePost(ref v) => write!(f, "List-Unsubscribe-Post: {}", v.to_string()),
+            &Field::ListSubscribe(ref v) =>     write!(f, "List-Subscribe: {}", v.to_string()),
+            &Field::ListPost(ref v) =>          write!(f, "List-Post: {}", v.to_string()),
+            &Field::ListOwner(ref v) =>         write!(f, "List-Owner: {}", v.to_string()),
+            &Field::ListArchive(ref v) =>       write!(f, "List-Archive: {}", v.to_string()),
+            &Field::AuthenticationResults(ref v) => write!(f, "Authentication-Results: {}", v.to_string()),
+            &Field::DKIMSignature(ref v) => write!(f, "DKIM-Signature: {}", v.to_string()),
             &Field::Optional(ref n, ref v) =>   write!(f, "{}: {}", n, v.to_string()),
         }
     }
@@ -283,26 +1242,14 @@ pub struct ReceivedField<I: U8Input> {
 
 impl<I: U8Input> ReceivedField<I> {
     // *received-token ";" date-time
-    /*
-    pub fn tokens(&self) -> FieldValue<(Vec<Bytes>, DateTime<FixedOffset>)> {
+    pub fn received(&self) -> FieldValue<Received> {
+        self.received_with(Profile::Lenient)
+    }
+
+    pub fn received_with<O: Into<ParseOptions>>(&self, options: O) -> FieldValue<Received> {
         let data = self.data.to_vec();
-        let parser = |i| {
-            many(i, received_token).bind(|i, tokens: Vec<Vec<I::Buffer>>| {
-                token(i, b';').then(|i| {
-                    let token_bytes: Vec<Bytes> = tokens.into_iter().map(|t: Vec<I::Buffer>| {
-                        t.into_iter().fold(Bytes::empty(), |l, r| l.concat(&Bytes::from_slice(&r.into_vec())))
-                    }).collect();
-
-                    date_time(i).map(|dt: DateTime<FixedOffset>| (token_bytes, dt))
-                })
-            })
-        };
-        match parse_only(parser, &data[..]) {
-            Ok(v) => FieldValue::Ok(v),
-            Err(_) => FieldValue::Raw(Bytes::from_slice(&data[..])),
-        }
+        parse_profile(&data[..], "received", options.into(), received_with)
     }
-    */
 
     pub fn to_string(&self) -> String {
         let s = &self.data.to_vec()[..self.data.len()-2];
@@ -325,11 +1272,41 @@ pub struct DateTimeField<I: U8Input> {
 impl<I: U8Input> DateTimeField<I> {
     // date-time
     pub fn date_time(&self) -> FieldValue<DateTime<FixedOffset>> {
+        self.date_time_with(Profile::Lenient)
+    }
+
+    pub fn date_time_with<O: Into<ParseOptions>>(&self, options: O) -> FieldValue<DateTime<FixedOffset>> {
+        self.date_time_zones(options, ZoneTable::default())
+    }
+
+    /// Parses the date, interpreting zone names with `zones`
+    pub fn date_time_zones<O: Into<ParseOptions>>(&self, options: O, zones: ZoneTable) -> FieldValue<DateTime<FixedOffset>> {
+        self.zoned_date_time_with(options, zones).map(|d| d.date_time)
+    }
+
+    /// Parses the date, keeping whether the zone was the sender's local zone
+    pub fn zoned_date_time(&self) -> FieldValue<ZonedDateTime> {
+        self.zoned_date_time_with(Profile::Lenient, ZoneTable::default())
+    }
+
+    /// A day-of-week that doesn't match the date is an error with the
+    /// strict profile, and a warning otherwise.
+    pub fn zoned_date_time_with<O: Into<ParseOptions>>(&self, options: O, zones: ZoneTable) -> FieldValue<ZonedDateTime> {
+        let options = options.into();
         let data = self.data.to_vec();
-        match parse_only(date_time, &data[..]) {
-            Ok(v) => FieldValue::Ok(v),
-            Err(_) => FieldValue::Raw(Bytes::from_slice(&data[..])),
+        let value = parse_profile(&data[..], "date_time", options, |i, profile| date_time_day_with(i, profile, zones));
+        let (date, mut warnings) = match value {
+            FieldValue::Ok((Some(day), date)) if day.weekday() != date.date_time.weekday() => (date, vec!()),
+            FieldValue::Accepted((Some(day), date), w) if day.weekday() != date.date_time.weekday() => (date, w),
+            v => return v.map(|(_, date)| date),
+        };
+        if options.profile == Profile::Strict {
+            // Point at the day-of-week, after any leading FWS
+            let start = data.iter().position(|&c| c != b' ' && c != b'\t' && c != b'\r' && c != b'\n').unwrap_or(0);
+            return FieldValue::Raw(Bytes::from_slice(&data), ParseError::new("day_of_week", &data, &data[start..], Error::unexpected()));
         }
+        warnings.push(Warning{ production: "day_of_week", deviation: Deviation::DayOfWeek });
+        FieldValue::Accepted(date, warnings)
     }
 
     pub fn to_string(&self) -> String {
@@ -353,11 +1330,22 @@ pub struct AddressesField<I: U8Input> {
 impl<I: U8Input> AddressesField<I> {
     // address-list
     pub fn addresses(&self) -> FieldValue<Vec<Address>> {
+        self.addresses_with(Profile::Lenient)
+    }
+
+    pub fn addresses_with<O: Into<ParseOptions>>(&self, options: O) -> FieldValue<Vec<Address>> {
         let data = self.data.to_vec();
-        match parse_only(address_list, &data[..]) {
-            Ok(v) => FieldValue::Ok(v),
-            Err(_) => FieldValue::Raw(Bytes::from_slice(&data[..])),
-        }
+        parse_profile(&data[..], "address_list", options.into(), address_list_with)
+    }
+
+    // [address-list / CFWS], for Bcc and Resent-Bcc
+    pub fn bcc_addresses(&self) -> FieldValue<Vec<Address>> {
+        self.bcc_addresses_with(Profile::Lenient)
+    }
+
+    pub fn bcc_addresses_with<O: Into<ParseOptions>>(&self, options: O) -> FieldValue<Vec<Address>> {
+        let data = self.data.to_vec();
+        parse_profile(&data[..], "bcc", options.into(), bcc_list_with)
     }
 
     pub fn to_string(&self) -> String {
@@ -382,11 +1370,22 @@ impl<I: U8Input> AddressField<I> {
 
     // mailbox
     pub fn address(&self) -> FieldValue<Address> {
+        self.address_with(Profile::Lenient)
+    }
+
+    pub fn address_with<O: Into<ParseOptions>>(&self, options: O) -> FieldValue<Address> {
         let data = self.data.to_vec();
-        match parse_only(mailbox, &data[..]) {
-            Ok(v) => FieldValue::Ok(v),
-            Err(_) => FieldValue::Raw(Bytes::from_slice(&data[..])),
-        }
+        parse_profile(&data[..], "mailbox", options.into(), mailbox_with)
+    }
+
+    // path, for Return-Path
+    pub fn path(&self) -> FieldValue<Option<Address>> {
+        self.path_with(Profile::Lenient)
+    }
+
+    pub fn path_with<O: Into<ParseOptions>>(&self, options: O) -> FieldValue<Option<Address>> {
+        let data = self.data.to_vec();
+        parse_profile(&data[..], "path", options.into(), path_with)
     }
 
     pub fn to_string(&self) -> String {
@@ -409,11 +1408,12 @@ pub struct MessageIDField<I: U8Input> {
 
 impl<I: U8Input> MessageIDField<I> {
     pub fn message_id(&self) -> FieldValue<MessageID> {
+        self.message_id_with(Profile::Lenient)
+    }
+
+    pub fn message_id_with<O: Into<ParseOptions>>(&self, options: O) -> FieldValue<MessageID> {
         let data = self.data.to_vec();
-        match parse_only(msg_id, &data[..]) {
-            Ok(v) => FieldValue::Ok(v),
-            Err(_) => FieldValue::Raw(Bytes::from_slice(&data[..])),
-        }
+        parse_profile(&data[..], "msg_id", options.into(), msg_id_with)
     }
 
     pub fn to_string(&self) -> String {
@@ -435,27 +1435,31 @@ pub struct MessageIDsField<I: U8Input> {
 }
 
 impl<I: U8Input> MessageIDsField<I> {
-    //  *(phrase / msg-id)
+    pub fn message_ids(&self) -> FieldValue<Vec<MessageID>> {
+        self.message_ids_with(Profile::Lenient)
+    }
+
+    //  1*msg-id, or *(phrase / msg-id) with obsolete syntax
     //  For purposes of interpretation, the phrases in the "In-Reply-To:" and
     //  "References:" fields are ignored.
-    pub fn message_ids(&self) -> FieldValue<Vec<MessageID>> {
+    pub fn message_ids_with<O: Into<ParseOptions>>(&self, options: O) -> FieldValue<Vec<MessageID>> {
         let data = self.data.to_vec();
-        let parser = |i| {
-            many(i, |i| {
-                or(i, 
-                   |i| phrase(i).map(|_| None),
-                   |i| msg_id(i).map(|v| Some(v)))
-            }).map(|vs: Vec<Option<MessageID>>| {
-                vs.into_iter()
-                    .filter(|v| v.is_some())
-                    .map(|v| v.unwrap())
-                    .collect::<Vec<MessageID>>()
-            })
+        let parser = |i, profile| {
+            match profile {
+                Profile::Strict => many1(i, |i| msg_id_with(i, profile)),
+                _ => many(i, |i| {
+                    or(i, 
+                       |i| phrase(i).map(|_| None),
+                       |i| msg_id_with(i, profile).map(|v| Some(v)))
+                }).map(|vs: Vec<Option<MessageID>>| {
+                    vs.into_iter()
+                        .filter(|v| v.is_some())
+                        .map(|v| v.unwrap())
+                        .collect::<Vec<MessageID>>()
+                }),
+            }
         };
-        match parse_only(parser, &data[..]) {
-            Ok(v) => FieldValue::Ok(v),
-            Err(_) => FieldValue::Raw(Bytes::from_slice(&data[..])),
-        }
+        parse_profile(&data[..], "message_ids", options.into(), parser)
     }
 
     pub fn to_string(&self) -> String {
@@ -483,6 +1487,60 @@ pub struct UnstructuredField<I: U8Input> {
 }
 
 impl<I: U8Input> UnstructuredField<I> {
+    // unstructured
+    pub fn text(&self) -> FieldValue<String> {
+        self.text_with(Profile::Lenient)
+    }
+
+    /// Returns the text without the whitespace, or folding, between it and
+    /// the colon. The lenient profile accepts text that isn't in the expected
+    /// encoding, replacing invalid UTF-8.
+    pub fn text_with<O: Into<ParseOptions>>(&self, options: O) -> FieldValue<String> {
+        let data = self.data.to_vec();
+        let data = &data[..data.len()-2];
+        let start = data.iter().position(|&c| !b" \t\r\n".contains(&c)).unwrap_or(data.len());
+        let data = &data[start..];
+        let options = options.into();
+        let text = String::from_utf8_lossy(data).into_owned();
+        match check_encoding(data, "unstructured", options.utf8) {
+            None => FieldValue::Ok(text),
+            Some(_) if options.profile == Profile::Lenient => {
+                let warning = Warning{ production: "unstructured", deviation: Deviation::Lenient };
+                FieldValue::Accepted(text, vec!(warning))
+            },
+            Some(e) => FieldValue::Raw(Bytes::from_slice(data), e),
+        }
+    }
+
+    /// Returns the text with line breaks removed, as section 2.2.3 describes.
+    /// Bare CR and LF, which obs-unstruct allows, are line breaks too.
+    pub fn unfolded(&self) -> FieldValue<String> {
+        self.unfolded_with(Profile::Lenient)
+    }
+
+    pub fn unfolded_with<O: Into<ParseOptions>>(&self, options: O) -> FieldValue<String> {
+        self.text_with(options).map(|text| util::unfold(&text))
+    }
+
+    /// Returns the unfolded text with each run of whitespace replaced by a
+    /// single space, and without leading or trailing whitespace
+    pub fn normalized(&self) -> FieldValue<String> {
+        self.normalized_with(Profile::Lenient)
+    }
+
+    pub fn normalized_with<O: Into<ParseOptions>>(&self, options: O) -> FieldValue<String> {
+        self.text_with(options).map(|text| util::collapse_whitespace(&text))
+    }
+
+    /// Returns the unfolded text with RFC 2047 encoded-words decoded
+    pub fn decoded(&self) -> FieldValue<String> {
+        self.decoded_with(Profile::Lenient)
+    }
+
+    pub fn decoded_with<O: Into<ParseOptions>>(&self, options: O) -> FieldValue<String> {
+        self.unfolded_with(options).map(|text| rfc2047::decode(&text))
+    }
+
     pub fn to_string(&self) -> String {
         let s = &self.data.to_vec()[..self.data.len()-2];
         let cow = String::from_utf8_lossy(s);
@@ -502,8 +1560,14 @@ pub struct KeywordsField<I: U8Input> {
 }
 
 impl<I: U8Input> KeywordsField<I> {
-    pub fn keywords(&self) -> Vec<Bytes> {
-        vec!()
+    // phrase *("," phrase)
+    pub fn keywords(&self) -> FieldValue<Vec<String>> {
+        self.keywords_with(Profile::Lenient)
+    }
+
+    pub fn keywords_with<O: Into<ParseOptions>>(&self, options: O) -> FieldValue<Vec<String>> {
+        let data = self.data.to_vec();
+        parse_profile(&data[..], "keywords", options.into(), keywords_with)
     }
 
     pub fn to_string(&self) -> String {
@@ -519,7 +1583,245 @@ impl<I: U8Input> fmt::Debug for KeywordsField<I> {
     }
 }
 
+#[derive(PartialEq)]
+pub struct ListIdField<I: U8Input> {
+    data: I::Buffer,
+}
+
+impl<I: U8Input> ListIdField<I> {
+    // [phrase] "<" list-id ">"
+    pub fn list_id(&self) -> FieldValue<ListId> {
+        self.list_id_with(Profile::Lenient)
+    }
+
+    pub fn list_id_with<O: Into<ParseOptions>>(&self, options: O) -> FieldValue<ListId> {
+        let data = self.data.to_vec();
+        parse_profile(&data[..], "list_id", options.into(), list_id_with)
+    }
+
+    pub fn to_string(&self) -> String {
+        let s = &self.data.to_vec()[..self.data.len()-2];
+        let cow = String::from_utf8_lossy(s);
+        cow.into_owned().to_string()
+    }
+}
+
+impl<I: U8Input> fmt::Debug for ListIdField<I> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(f, "{}", self.to_string())
+    }
+}
+
+/// A List-Help, List-Unsubscribe, List-Subscribe, List-Post, List-Owner or
+/// List-Archive field
+#[derive(PartialEq)]
+pub struct ListUrlsField<I: U8Input> {
+    data: I::Buffer,
+}
+
+impl<I: U8Input> ListUrlsField<I> {
+    // list-url *("," list-url)
+    pub fn urls(&self) -> FieldValue<Vec<String>> {
+        self.urls_with(Profile::Lenient)
+    }
+
+    pub fn urls_with<O: Into<ParseOptions>>(&self, options: O) -> FieldValue<Vec<String>> {
+        let data = self.data.to_vec();
+        parse_profile(&data[..], "list_urls", options.into(), list_urls_with)
+    }
+
+    /// Parses a List-Post value, which may be "NO" instead of URLs
+    pub fn post(&self) -> FieldValue<ListPost> {
+        self.post_with(Profile::Lenient)
+    }
+
+    pub fn post_with<O: Into<ParseOptions>>(&self, options: O) -> FieldValue<ListPost> {
+        let data = self.data.to_vec();
+        parse_profile(&data[..], "list_post", options.into(), list_post_with)
+    }
+
+    pub fn to_string(&self) -> String {
+        let s = &self.data.to_vec()[..self.data.len()-2];
+        let cow = String::from_utf8_lossy(s);
+        cow.into_owned().to_string()
+    }
+}
+
+impl<I: U8Input> fmt::Debug for ListUrlsField<I> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(f, "{}", self.to_string())
+    }
+}
+
+#[derive(PartialEq)]
+pub struct ListUnsubscribePostField<I: U8Input> {
+    data: I::Buffer,
+}
+
+impl<I: U8Input> ListUnsubscribePostField<I> {
+    /// Parses the value, which RFC 8058 requires to be
+    /// "List-Unsubscribe=One-Click"
+    pub fn one_click(&self) -> FieldValue<()> {
+        self.one_click_with(Profile::Lenient)
+    }
+
+    pub fn one_click_with<O: Into<ParseOptions>>(&self, options: O) -> FieldValue<()> {
+        let data = self.data.to_vec();
+        parse_profile(&data[..], "list_unsubscribe_post", options.into(), list_unsubscribe_post_with)
+    }
+
+    pub fn to_string(&self) -> String {
+        let s = &self.data.to_vec()[..self.data.len()-2];
+        let cow = String::from_utf8_lossy(s);
+        cow.into_owned().to_string()
+    }
+}
+
+impl<I: U8Input> fmt::Debug for ListUnsubscribePostField<I> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(f, "{}", self.to_string())
+    }
+}
+
+#[derive(PartialEq)]
+pub struct AuthenticationResultsField<I: U8Input> {
+    data: I::Buffer,
+}
+
+impl<I: U8Input> AuthenticationResultsField<I> {
+    // authserv-id [ CFWS authres-version ] ( no-result / 1*resinfo )
+    pub fn results(&self) -> FieldValue<AuthenticationResults> {
+        self.results_with(Profile::Lenient)
+    }
+
+    pub fn results_with<O: Into<ParseOptions>>(&self, options: O) -> FieldValue<AuthenticationResults> {
+        let data = self.data.to_vec();
+        parse_profile(&data[..], "authres_payload", options.into(), authres_payload_with)
+    }
+
+    pub fn to_string(&self) -> String {
+        let s = &self.data.to_vec()[..self.data.len()-2];
+        let cow = String::from_utf8_lossy(s);
+        cow.into_owned().to_string()
+    }
+}
+
+impl<I: U8Input> fmt::Debug for AuthenticationResultsField<I> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(f, "{}", self.to_string())
+    }
+}
+
+#[derive(PartialEq)]
+pub struct DKIMSignatureField<I: U8Input> {
+    data: I::Buffer,
+}
+
+impl<I: U8Input> DKIMSignatureField<I> {
+    // tag-list with the tags of RFC 6376 section 3.5
+    pub fn signature(&self) -> FieldValue<DKIMSignature> {
+        self.signature_with(Profile::Lenient)
+    }
+
+    pub fn signature_with<O: Into<ParseOptions>>(&self, options: O) -> FieldValue<DKIMSignature> {
+        let data = self.data.to_vec();
+        parse_profile(&data[..], "dkim_signature", options.into(), dkim_signature_with)
+    }
+
+    pub fn to_string(&self) -> String {
+        let s = &self.data.to_vec()[..self.data.len()-2];
+        let cow = String::from_utf8_lossy(s);
+        cow.into_owned().to_string()
+    }
+}
+
+impl<I: U8Input> fmt::Debug for DKIMSignatureField<I> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(f, "{}", self.to_string())
+    }
+}
+
 impl<I: U8Input> Field<I> {
+    /// Returns the field name as it's conventionally written
+    pub fn name(&self) -> &str {
+        match self {
+            &Field::Date(_) =>              "Date",
+            &Field::From(_) =>              "From",
+            &Field::Sender(_) =>            "Sender",
+            &Field::ReplyTo(_) =>           "Reply-To",
+            &Field::To(_) =>                "To",
+            &Field::Cc(_) =>                "Cc",
+            &Field::Bcc(_) =>               "Bcc",
+            &Field::MessageID(_) =>         "Message-ID",
+            &Field::InReplyTo(_) =>         "In-Reply-To",
+            &Field::References(_) =>        "References",
+            &Field::Subject(_) =>           "Subject",
+            &Field::Comments(_) =>          "Comments",
+            &Field::Keywords(_) =>          "Keywords",
+            &Field::ReturnPath(_) =>        "Return-Path",
+            &Field::Received(_) =>          "Received",
+            &Field::ResentDate(_) =>        "Resent-Date",
+            &Field::ResentFrom(_) =>        "Resent-From",
+            &Field::ResentSender(_) =>      "Resent-Sender",
+            &Field::ResentTo(_) =>          "Resent-To",
+            &Field::ResentCc(_) =>          "Resent-Cc",
+            &Field::ResentBcc(_) =>         "Resent-Bcc",
+            &Field::ResentReplyTo(_) =>     "Resent-Reply-To",
+            &Field::ResentMessageID(_) =>   "Resent-Message-ID",
+            &Field::MIMEVersion(_) =>       "MIME-Version",
+            &Field::ListId(_) =>            "List-Id",
+            &Field::ListHelp(_) =>          "List-Help",
+            &Field::ListUnsubscribe(_) =>   "List-Unsubscribe",
+            &Field::ListUnsubscribePost(_) => "List-Unsubscribe-Post",
+            &Field::ListSubscribe(_) =>     "List-Subscribe",
+            &Field::ListPost(_) =>          "List-Post",
+            &Field::ListOwner(_) =>         "List-Owner",
+            &Field::ListArchive(_) =>       "List-Archive",
+            &Field::AuthenticationResults(_) => "Authentication-Results",
+            &Field::DKIMSignature(_) =>     "DKIM-Signature",
+            &Field::Optional(ref n, _) =>   n,
+        }
+    }
+
+    /// Returns the relaxations of `options` needed to parse the field value
+    pub fn warnings<O: Into<ParseOptions>>(&self, options: O) -> Vec<Warning> {
+        let options = options.into();
+        match self {
+            &Field::Date(ref v) =>              v.date_time_with(options).warnings().to_vec(),
+            &Field::From(ref v) =>              v.addresses_with(options).warnings().to_vec(),
+            &Field::Sender(ref v) =>            v.address_with(options).warnings().to_vec(),
+            &Field::ReplyTo(ref v) =>           v.addresses_with(options).warnings().to_vec(),
+            &Field::To(ref v) =>                v.addresses_with(options).warnings().to_vec(),
+            &Field::Cc(ref v) =>                v.addresses_with(options).warnings().to_vec(),
+            &Field::Bcc(ref v) =>               v.bcc_addresses_with(options).warnings().to_vec(),
+            &Field::MessageID(ref v) =>         v.message_id_with(options).warnings().to_vec(),
+            &Field::InReplyTo(ref v) =>         v.message_ids_with(options).warnings().to_vec(),
+            &Field::References(ref v) =>        v.message_ids_with(options).warnings().to_vec(),
+            &Field::Keywords(ref v) =>          v.keywords_with(options).warnings().to_vec(),
+            &Field::ReturnPath(ref v) =>        v.path_with(options).warnings().to_vec(),
+            &Field::Received(ref v) =>          v.received_with(options).warnings().to_vec(),
+            &Field::ResentFrom(ref v) =>        v.addresses_with(options).warnings().to_vec(),
+            &Field::ResentSender(ref v) =>      v.address_with(options).warnings().to_vec(),
+            &Field::ResentDate(ref v) =>        v.date_time_with(options).warnings().to_vec(),
+            &Field::ResentTo(ref v) =>          v.addresses_with(options).warnings().to_vec(),
+            &Field::ResentCc(ref v) =>          v.addresses_with(options).warnings().to_vec(),
+            &Field::ResentBcc(ref v) =>         v.bcc_addresses_with(options).warnings().to_vec(),
+            &Field::ResentMessageID(ref v) =>   v.message_id_with(options).warnings().to_vec(),
+            &Field::ResentReplyTo(ref v) =>     v.addresses_with(options).warnings().to_vec(),
+            &Field::ListId(ref v) =>            v.list_id_with(options).warnings().to_vec(),
+            &Field::ListHelp(ref v) =>          v.urls_with(options).warnings().to_vec(),
+            &Field::ListUnsubscribe(ref v) =>   v.urls_with(options).warnings().to_vec(),
+            &Field::ListUnsubscribePost(ref v) => v.one_click_with(options).warnings().to_vec(),
+            &Field::ListSubscribe(ref v) =>     v.urls_with(options).warnings().to_vec(),
+            &Field::ListPost(ref v) =>          v.post_with(options).warnings().to_vec(),
+            &Field::ListOwner(ref v) =>         v.urls_with(options).warnings().to_vec(),
+            &Field::ListArchive(ref v) =>       v.urls_with(options).warnings().to_vec(),
+            &Field::AuthenticationResults(ref v) => v.results_with(options).warnings().to_vec(),
+            &Field::DKIMSignature(ref v) => v.signature_with(options).warnings().to_vec(),
+            _ =>                                vec!(),
+        }
+    }
+
     /// Returns true if this is an "unstructured" field
     pub fn is_unstructured(&self) -> bool {
         match self {
@@ -532,7 +1834,7 @@ impl<I: U8Input> Field<I> {
     pub fn is_malformed(&self) -> bool {
         match self {
             // Received formats vary wildly, so ignore it here
-            // &Field::Received(ref v) =>          v.tokens().is_raw(),
+            // &Field::Received(ref v) =>          v.received().is_raw(),
             &Field::Date(ref v) =>              v.date_time().is_raw(),
             &Field::From(ref v) =>              v.addresses().is_raw(),
             &Field::Sender(ref v) =>            v.address().is_raw(),
@@ -544,15 +1846,183 @@ impl<I: U8Input> Field<I> {
             &Field::References(ref v) =>        v.message_ids().is_raw(),
             &Field::Subject(_) =>               false,
             &Field::Comments(_) =>              false,
+            &Field::Keywords(ref v) =>          v.keywords().is_raw(),
+            &Field::ReturnPath(ref v) =>        v.path().is_raw(),
             &Field::ResentFrom(ref v) =>        v.addresses().is_raw(),
             &Field::ResentSender(ref v) =>      v.address().is_raw(),
             &Field::ResentDate(ref v) =>        v.date_time().is_raw(),
             &Field::ResentTo(ref v) =>          v.addresses().is_raw(),
             &Field::ResentCc(ref v) =>          v.addresses().is_raw(),
-            &Field::ResentBcc(ref v) =>         v.addresses().is_raw(),
+            &Field::ResentBcc(ref v) =>         v.bcc_addresses().is_raw(),
             &Field::ResentMessageID(ref v) =>   v.message_id().is_raw(),
             &Field::ResentReplyTo(ref v) =>     v.addresses().is_raw(),
+            &Field::ListId(ref v) =>            v.list_id().is_raw(),
+            &Field::ListHelp(ref v) =>          v.urls().is_raw(),
+            &Field::ListUnsubscribe(ref v) =>   v.urls().is_raw(),
+            &Field::ListUnsubscribePost(ref v) => v.one_click().is_raw(),
+            &Field::ListSubscribe(ref v) =>     v.urls().is_raw(),
+            &Field::ListPost(ref v) =>          v.post().is_raw(),
+            &Field::ListOwner(ref v) =>         v.urls().is_raw(),
+            &Field::ListArchive(ref v) =>       v.urls().is_raw(),
+            &Field::AuthenticationResults(ref v) => v.results().is_raw(),
+            &Field::DKIMSignature(ref v) => v.signature().is_raw(),
             _ =>                                false,
         }
     }
 }
+
+#[test]
+fn test_domain_idna() {
+    let unicode = Domain::Name("bücher.example".to_string());
+    let ascii = Domain::Name("xn--bcher-kva.example".to_string());
+    assert_eq!(unicode.to_ascii(), Ok(ascii.clone()));
+    assert_eq!(ascii.to_unicode(), Ok(unicode.clone()));
+    assert_eq!(Domain::Name("Bücher.Example".to_string()).comparison_key(), ascii.comparison_key());
+
+    let literal = Domain::Ipv4("192.0.2.1".parse().unwrap());
+    assert_eq!(literal.to_ascii(), Ok(literal.clone()));
+    assert_eq!(literal.comparison_key(), "[192.0.2.1]");
+
+    assert!(Domain::Name("xn--a.example".to_string()).to_unicode().is_err());
+
+    let a = Address::Mailbox{
+        local_part: "user".to_string(),
+        domain: unicode,
+        display_name: None,
+        comments: vec!(),
+        route: None,
+    };
+    let b = Address::Mailbox{
+        local_part: "user".to_string(),
+        domain: ascii,
+        display_name: Some("User".to_string()),
+        comments: vec!(),
+        route: None,
+    };
+    assert_eq!(a.comparison_key(), b.comparison_key());
+    assert_eq!(a.to_ascii().unwrap().comparison_key(), Some("user@xn--bcher-kva.example".to_string()));
+}
+
+#[test]
+fn test_address_display() {
+    let cases: Vec<&[u8]> = vec!(
+        b"jdoe@example.com",
+        b"John Doe <jdoe@example.com>",
+        b"\"Joe Q. Public\" <john.q.public@example.com>",
+        b"\"Giant; \\\"Big\\\" Box\" <sysservices@example.net>",
+        b"\"j doe\"@[192.0.2.1]",
+        b"=?utf-8?Q?Humble=20Bundle?= <contact@humblebundle.com>",
+        b"=?utf-8?Q?J=C3=B6rg?= =?utf-8?Q?_M=C3=BCller?= <jdoe@example.com>",
+        b"Team: a@x, b@y;",
+        b"Undisclosed recipients:;",
+    );
+    for raw in cases.into_iter() {
+        let address = parse_only(address, raw).unwrap();
+        assert_eq!(address.to_string().as_bytes(), raw);
+    }
+
+    let address = parse_only(address, b"(Jo) \"Joe\" \t<joe@example.com> (x)").unwrap();
+    assert_eq!(address.to_string(), "Joe <joe@example.com>");
+
+    let address = Address::Mailbox{
+        local_part: "jörg".to_string(),
+        domain: Domain::Name("example.com".to_string()),
+        display_name: Some("Jörg Müller".to_string()),
+        comments: vec!(),
+        route: None,
+    };
+    assert_eq!(address.to_string(), "=?utf-8?Q?J=C3=B6rg_M=C3=BCller?= <jörg@example.com>");
+
+    let address = Address::Mailbox{
+        local_part: "jdoe".to_string(),
+        domain: Domain::Name("example.com".to_string()),
+        display_name: Some("=?not encoded?=".to_string()),
+        comments: vec!(),
+        route: None,
+    };
+    assert_eq!(address.to_string(), "\"=?not encoded?=\" <jdoe@example.com>");
+}
+
+#[test]
+fn test_unstructured_unfolding() {
+    let i = b"Subject: Saying\r\n Hello\r\n\tagain\n  and\ragain \r\n";
+    let field = match parse_only(raw_obs_subject, i).unwrap() {
+        Field::Subject(f) => f,
+        _ => unreachable!(),
+    };
+    assert_eq!(field.text().unwrap(), "Saying\r\n Hello\r\n\tagain\n  and\ragain ");
+    assert_eq!(field.unfolded().unwrap(), "Saying Hello\tagain  and again ");
+    assert_eq!(field.normalized().unwrap(), "Saying Hello again and again");
+
+    let i = b"Subject: =?utf-8?Q?Saying?=\r\n =?utf-8?Q?_Hello?=\r\n";
+    let field = match parse_only(raw_obs_subject, i).unwrap() {
+        Field::Subject(f) => f,
+        _ => unreachable!(),
+    };
+    assert_eq!(field.decoded().unwrap(), "Saying Hello");
+
+    let i = b"Subject:\r\n \tSaying Hello\r\n";
+    let field = match parse_only(raw_obs_subject, i).unwrap() {
+        Field::Subject(f) => f,
+        _ => unreachable!(),
+    };
+    assert_eq!(field.unfolded().unwrap(), "Saying Hello");
+
+    // Only the lenient profile accepts 8-bit text without UTF-8 enabled
+    let i = b"Subject: Sayin\xe2\x80\x99 H\xe9llo\r\n";
+    let field = match parse_only(raw_obs_subject, i).unwrap() {
+        Field::Subject(f) => f,
+        _ => unreachable!(),
+    };
+    let subject = field.decoded();
+    assert!(subject.is_accepted());
+    assert_eq!(subject.unwrap(), "Sayin\u{2019} H\u{fffd}llo");
+    assert!(field.decoded_with(Profile::Obsolete).is_raw());
+    let utf8 = ParseOptions{ profile: Profile::Strict, utf8: true };
+    assert!(field.decoded_with(utf8).is_raw());
+}
+
+#[test]
+fn test_zoned_date_time() {
+    let field = |i: &'static [u8]| match parse_only(raw_obs_orig_date, i).unwrap() {
+        Field::Date(f) => f,
+        _ => unreachable!(),
+    };
+
+    let date = field(b"Date: Fri, 21 Nov 1997 09:55:06 -0000\r\n").zoned_date_time().unwrap();
+    assert_eq!(date.zone, LocalZone::Unknown);
+    assert_eq!(date.local(), None);
+    assert_eq!(date.to_string(), "Fri, 21 Nov 1997 09:55:06 -0000");
+
+    let date = field(b"Date: Fri, 21 Nov 1997 09:55:06 +0000\r\n").zoned_date_time().unwrap();
+    assert_eq!(date.zone, LocalZone::Known);
+    assert_eq!(date.local(), Some(date.date_time));
+    assert_eq!(date.to_string(), "Fri, 21 Nov 1997 09:55:06 +0000");
+
+    let date = field(b"Date: Fri, 21 Nov 1997 09:55:06 -0600\r\n").zoned_date_time().unwrap();
+    assert_eq!(date.zone, LocalZone::Known);
+    assert_eq!(date.to_string(), "Fri, 21 Nov 1997 09:55:06 -0600");
+}
+
+#[test]
+fn test_day_of_week_mismatch() {
+    let i = b"Date: Thu, 21 Nov 1997 09:55:06 -0600\r\n";
+    let field = match parse_only(raw_obs_orig_date, i).unwrap() {
+        Field::Date(f) => f,
+        _ => unreachable!(),
+    };
+    let date = field.date_time();
+    assert_eq!(date.warnings(), &[Warning{ production: "day_of_week", deviation: Deviation::DayOfWeek }]);
+    assert_eq!(date.unwrap().to_rfc2822(), "Fri, 21 Nov 1997 09:55:06 -0600");
+
+    let date = field.date_time_with(Profile::Strict);
+    assert_eq!(date.error().unwrap().to_string(), "failed to parse day_of_week at line 1, column 2 near \"Thu, 21 Nov 1997 09:55:0\"");
+
+    let i = b"Date: Thu, 21 Nov 97 09:55:06 GMT\r\n";
+    let field = match parse_only(raw_obs_orig_date, i).unwrap() {
+        Field::Date(f) => f,
+        _ => unreachable!(),
+    };
+    let deviations: Vec<Deviation> = field.date_time().warnings().iter().map(|w| w.deviation).collect();
+    assert_eq!(deviations, vec!(Deviation::Obsolete, Deviation::DayOfWeek));
+}
@@ -0,0 +1,380 @@
+//! Reports where a parsed message deviates from RFC 5322
+use std::fmt;
+use std::str;
+
+use chomp::types::*;
+
+use error::ParseError;
+use super::*;
+
+// Maximum line length excluding the CRLF, from RFC 5322 section 2.1.1
+const MAX_LINE_LEN: usize = 998;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Category {
+    /// A CR or LF that isn't part of a CRLF pair
+    BareNewline,
+    /// A line longer than 998 characters, excluding the CRLF
+    LineLength,
+    /// A field containing bytes outside US-ASCII
+    EightBit,
+    /// A field using the obsolete syntax of section 4
+    Obsolete,
+    /// A structured field that only parses leniently, or doesn't parse
+    Malformed,
+    /// A field required by section 3.6 is absent
+    MissingField,
+    /// A Message-ID, In-Reply-To or References value that isn't a valid msg-id
+    MessageID,
+    /// A day-of-week that doesn't match the date
+    DayOfWeek,
+    /// An RFC 2047 encoded-word where section 5 of that RFC forbids one
+    EncodedWord,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Diagnostic {
+    pub category: Category,
+    /// The field name, or None for the body and the message as a whole
+    pub field: Option<String>,
+    pub description: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match self.field {
+            Some(ref name) => write!(f, "{}: {}", name, self.description),
+            None => write!(f, "{}", self.description),
+        }
+    }
+}
+
+impl<I: U8Input> Message<I> {
+    /// Checks the message against RFC 5322, returning a diagnostic for each
+    /// deviation found
+    pub fn lint(&self) -> Vec<Diagnostic> {
+        let mut diagnostics = vec!();
+
+        let options = self.options();
+        for (n, field) in self.fields.iter().enumerate() {
+            let obsolete_name = self.obsolete_names.contains(&n);
+            lint_field(field, options, obsolete_name, &mut diagnostics);
+        }
+        lint_required_fields(self, &mut diagnostics);
+        if let Some(ref body) = self.body {
+            lint_text(None, &body.to_vec(), 0, &mut diagnostics);
+        }
+
+        diagnostics
+    }
+}
+
+fn diagnostic(field: Option<&str>, category: Category, description: String) -> Diagnostic {
+    Diagnostic {
+        category: category,
+        field: field.map(|n| n.to_string()),
+        description: description,
+    }
+}
+
+// Checks line endings and lengths, where `prefix_len` is the length of the
+// text preceding `data` on its first line
+fn lint_text(field: Option<&str>, data: &[u8], prefix_len: usize, diagnostics: &mut Vec<Diagnostic>) {
+    let bare = data.iter().enumerate().filter(|&(n, &c)| {
+        match c {
+            b'\r' => data.get(n + 1) != Some(&b'\n'),
+            b'\n' => n == 0 || data[n - 1] != b'\r',
+            _ => false,
+        }
+    }).count();
+    if bare > 0 {
+        diagnostics.push(diagnostic(field, Category::BareNewline,
+            format!("{} bare CR or LF characters", bare)));
+    }
+
+    let long: Vec<(usize, usize)> = data.split(|&c| c == b'\n').enumerate().map(|(n, line)| {
+        let len = if line.last() == Some(&b'\r') { line.len() - 1 } else { line.len() };
+        (n + 1, if n == 0 { len + prefix_len } else { len })
+    }).filter(|&(_, len)| len > MAX_LINE_LEN).collect();
+    if let Some(&(line, len)) = long.first() {
+        diagnostics.push(diagnostic(field, Category::LineLength,
+            format!("{} lines longer than {} characters, the first is line {} with {}", long.len(), MAX_LINE_LEN, line, len)));
+    }
+}
+
+fn lint_field<I: U8Input>(field: &Field<I>, options: ParseOptions, obsolete_name: bool, diagnostics: &mut Vec<Diagnostic>) {
+    let name = field.name();
+    if obsolete_name {
+        let warning = Warning{ production: "field_name", deviation: Deviation::Obsolete };
+        diagnostics.push(diagnostic(Some(name), Category::Obsolete, warning.to_string()));
+    }
+    let data = match field_data(field) {
+        Some(data) => data,
+        None => return,
+    };
+
+    // The value follows the field name and colon on the first line
+    lint_text(Some(name), &data, name.len() + 1, diagnostics);
+    // RFC 6532 allows UTF-8 when the message was parsed with it
+    let utf8 = options.utf8 && str::from_utf8(&data).is_ok();
+    if !utf8 && data.iter().any(|&c| c >= 0x80) {
+        diagnostics.push(diagnostic(Some(name), Category::EightBit,
+            "contains bytes outside US-ASCII".to_string()));
+    }
+
+    let is_msg_id = match field {
+        &Field::MessageID(_) | &Field::ResentMessageID(_) | &Field::InReplyTo(_) | &Field::References(_) => true,
+        _ => false,
+    };
+    let malformed = if is_msg_id { Category::MessageID } else { Category::Malformed };
+    let (warnings, error) = check_value(field, options);
+    for w in warnings {
+        let category = match w.deviation {
+            Deviation::Obsolete => Category::Obsolete,
+            Deviation::Lenient => malformed,
+            Deviation::DayOfWeek => Category::DayOfWeek,
+        };
+        diagnostics.push(diagnostic(Some(name), category, w.to_string()));
+    }
+    if let Some(e) = error {
+        diagnostics.push(diagnostic(Some(name), malformed, e.to_string()));
+    }
+
+    match field {
+        &Field::Date(_) | &Field::ResentDate(_) => {
+            if contains_encoded_word(&data) {
+                diagnostics.push(diagnostic(Some(name), Category::EncodedWord,
+                    "encoded-word in a date".to_string()));
+            }
+        },
+        &Field::MessageID(ref v) | &Field::ResentMessageID(ref v) => {
+            if let Some(id) = v.message_id_with(options).ok() {
+                lint_msg_id_encoding(name, &id, diagnostics);
+            }
+        },
+        &Field::InReplyTo(ref v) | &Field::References(ref v) => {
+            if let Some(ids) = v.message_ids_with(options).ok() {
+                for id in ids.iter() {
+                    lint_msg_id_encoding(name, id, diagnostics);
+                }
+            }
+        },
+        &Field::Sender(ref v) | &Field::ResentSender(ref v) => {
+            lint_address_encoding(name, &data, v.address_with(options).ok().into_iter().collect(), diagnostics);
+        },
+        &Field::From(ref v) | &Field::ReplyTo(ref v) | &Field::To(ref v) |
+        &Field::Cc(ref v) | &Field::Bcc(ref v) | &Field::ResentFrom(ref v) |
+        &Field::ResentTo(ref v) | &Field::ResentCc(ref v) | &Field::ResentBcc(ref v) |
+        &Field::ResentReplyTo(ref v) => {
+            lint_address_encoding(name, &data, v.addresses_with(options).ok().unwrap_or(vec!()), diagnostics);
+        },
+        _ => {},
+    }
+}
+
+// Returns the unparsed value of fields that have one
+fn field_data<I: U8Input>(field: &Field<I>) -> Option<Vec<u8>> {
+    match field {
+        &Field::Date(ref v) | &Field::ResentDate(ref v) => Some(v.data.to_vec()),
+        &Field::From(ref v) | &Field::ReplyTo(ref v) | &Field::To(ref v) |
+        &Field::Cc(ref v) | &Field::Bcc(ref v) | &Field::ResentFrom(ref v) |
+        &Field::ResentTo(ref v) | &Field::ResentCc(ref v) | &Field::ResentBcc(ref v) |
+        &Field::ResentReplyTo(ref v) => Some(v.data.to_vec()),
+        &Field::Sender(ref v) | &Field::ResentSender(ref v) | &Field::ReturnPath(ref v) => Some(v.data.to_vec()),
+        &Field::MessageID(ref v) | &Field::ResentMessageID(ref v) => Some(v.data.to_vec()),
+        &Field::InReplyTo(ref v) | &Field::References(ref v) => Some(v.data.to_vec()),
+        &Field::Subject(ref v) | &Field::Comments(ref v) | &Field::Optional(_, ref v) => Some(v.data.to_vec()),
+        &Field::Keywords(ref v) => Some(v.data.to_vec()),
+        &Field::Received(ref v) => Some(v.data.to_vec()),
+        &Field::ListId(ref v) => Some(v.data.to_vec()),
+        &Field::ListHelp(ref v) | &Field::ListUnsubscribe(ref v) | &Field::ListSubscribe(ref v) |
+        &Field::ListPost(ref v) | &Field::ListOwner(ref v) | &Field::ListArchive(ref v) => Some(v.data.to_vec()),
+        &Field::ListUnsubscribePost(ref v) => Some(v.data.to_vec()),
+        &Field::AuthenticationResults(ref v) => Some(v.data.to_vec()),
+        &Field::DKIMSignature(ref v) => Some(v.data.to_vec()),
+        &Field::MIMEVersion(_) => None,
+    }
+}
+
+// Returns the relaxations needed to parse a structured field with `options`,
+// and the reason it didn't parse at all
+fn check_value<I: U8Input>(field: &Field<I>, options: ParseOptions) -> (Vec<Warning>, Option<ParseError>) {
+    fn check<T>(value: FieldValue<T>) -> (Vec<Warning>, Option<ParseError>) {
+        (value.warnings().to_vec(), value.error().cloned())
+    }
+
+    match field {
+        &Field::Date(ref v) | &Field::ResentDate(ref v) => check(v.date_time_with(options)),
+        &Field::From(ref v) | &Field::ReplyTo(ref v) | &Field::To(ref v) |
+        &Field::Cc(ref v) | &Field::ResentFrom(ref v) | &Field::ResentTo(ref v) |
+        &Field::ResentCc(ref v) | &Field::ResentReplyTo(ref v) => check(v.addresses_with(options)),
+        &Field::Bcc(ref v) | &Field::ResentBcc(ref v) => check(v.bcc_addresses_with(options)),
+        &Field::Sender(ref v) | &Field::ResentSender(ref v) => check(v.address_with(options)),
+        &Field::ReturnPath(ref v) => check(v.path_with(options)),
+        &Field::Keywords(ref v) => check(v.keywords_with(options)),
+        &Field::MessageID(ref v) | &Field::ResentMessageID(ref v) => check(v.message_id_with(options)),
+        &Field::InReplyTo(ref v) | &Field::References(ref v) => check(v.message_ids_with(options)),
+        &Field::ListId(ref v) => check(v.list_id_with(options)),
+        &Field::ListHelp(ref v) | &Field::ListUnsubscribe(ref v) | &Field::ListSubscribe(ref v) |
+        &Field::ListOwner(ref v) | &Field::ListArchive(ref v) => check(v.urls_with(options)),
+        &Field::ListPost(ref v) => check(v.post_with(options)),
+        &Field::ListUnsubscribePost(ref v) => check(v.one_click_with(options)),
+        &Field::AuthenticationResults(ref v) => check(v.results_with(options)),
+        &Field::DKIMSignature(ref v) => check(v.signature_with(options)),
+        _ => (vec!(), None),
+    }
+}
+
+// orig-date and from are the only fields required by section 3.6, and sender
+// is required when from has more than one mailbox
+fn lint_required_fields<I: U8Input>(message: &Message<I>, diagnostics: &mut Vec<Diagnostic>) {
+    if message.date().is_missing() {
+        diagnostics.push(diagnostic(None, Category::MissingField, "missing Date field".to_string()));
+    }
+    match message.from() {
+        FieldValue::Missing => {
+            diagnostics.push(diagnostic(None, Category::MissingField, "missing From field".to_string()));
+        },
+        FieldValue::Ok(ref addresses) | FieldValue::Accepted(ref addresses, _) => {
+            let mailboxes = addresses.iter().map(|a| {
+                match a {
+                    &Address::Mailbox{..} => 1,
+                    &Address::Group{ref mailboxes, ..} => mailboxes.len(),
+                }
+            }).fold(0, |l, r| l + r);
+            if mailboxes > 1 && message.sender().is_missing() {
+                diagnostics.push(diagnostic(None, Category::MissingField,
+                    "missing Sender field, which is required when From has more than one mailbox".to_string()));
+            }
+        },
+        FieldValue::Raw(..) => {},
+    }
+}
+
+fn lint_msg_id_encoding(name: &str, id: &MessageID, diagnostics: &mut Vec<Diagnostic>) {
+    let left = id.id_left.as_ref().map(|l| contains_encoded_word(l.as_bytes())).unwrap_or(false);
+    if left || contains_encoded_word(id.id_right.as_bytes()) {
+        diagnostics.push(diagnostic(Some(name), Category::EncodedWord,
+            "encoded-word in a msg-id".to_string()));
+    }
+}
+
+// Encoded-words are only allowed in a phrase or comment, so not in an
+// addr-spec or a quoted-string
+fn lint_address_encoding(name: &str, data: &[u8], addresses: Vec<Address>, diagnostics: &mut Vec<Diagnostic>) {
+    if quoted_encoded_word(data) {
+        diagnostics.push(diagnostic(Some(name), Category::EncodedWord,
+            "encoded-word in a quoted-string".to_string()));
+    }
+
+    let mut mailboxes = vec!();
+    for address in addresses.iter() {
+        match address {
+            &Address::Mailbox{..} => mailboxes.push(address),
+            &Address::Group{mailboxes: ref members, ..} => mailboxes.extend(members.iter()),
+        }
+    }
+    let in_addr_spec = mailboxes.iter().any(|mb| {
+        match *mb {
+            &Address::Mailbox{ref local_part, ref domain, ..} => {
+                contains_encoded_word(local_part.as_bytes()) || contains_encoded_word(domain.to_string().as_bytes())
+            },
+            _ => false,
+        }
+    });
+    if in_addr_spec {
+        diagnostics.push(diagnostic(Some(name), Category::EncodedWord,
+            "encoded-word in an addr-spec".to_string()));
+    }
+}
+
+// Returns true if `data` contains something shaped like an encoded-word,
+// "=?" charset "?" encoding "?" encoded-text "?="
+fn contains_encoded_word(data: &[u8]) -> bool {
+    data.windows(2).enumerate().any(|(n, w)| {
+        w == b"=?" && {
+            let rest = &data[n + 2..];
+            let marks = rest.iter().take_while(|&&c| c != b' ' && c != b'\t').collect::<Vec<_>>();
+            marks.iter().filter(|&&&c| c == b'?').count() >= 3 && marks.ends_with(&[&b'?', &b'='])
+        }
+    })
+}
+
+// Returns true if an encoded-word appears inside a quoted-string, skipping
+// comments and quoted-pairs
+fn quoted_encoded_word(data: &[u8]) -> bool {
+    let mut quoted = false;
+    let mut escaped = false;
+    let mut comment_depth = 0;
+    let mut start = 0;
+    for (n, &c) in data.iter().enumerate() {
+        if escaped {
+            escaped = false;
+            continue
+        }
+        match c {
+            b'\\' => escaped = true,
+            b'(' if !quoted => comment_depth += 1,
+            b')' if !quoted && comment_depth > 0 => comment_depth -= 1,
+            b'"' if comment_depth == 0 => {
+                if quoted && contains_encoded_word(&data[start..n]) {
+                    return true
+                }
+                quoted = !quoted;
+                start = n + 1;
+            },
+            _ => {},
+        }
+    }
+    false
+}
+
+#[test]
+fn test_lint() {
+    let i = b"From: a@example.com, \"=?utf-8?q?B?=\" <b@example.com>\r\nDate: Thu, 21 Nov 1997 09:55:06 GMT\r\nMessage-ID: <comm-tagged-1077147628989448>\r\nSubject: caf\xc3\xa9\nbar\r\n\r\nbody\r\n";
+    let msg = parse_only(message, i).unwrap();
+    let categories: Vec<(Option<String>, Category)> = msg.lint().into_iter().map(|d| (d.field, d.category)).collect();
+    assert_eq!(categories, vec!(
+        (Some("From".to_string()), Category::EncodedWord),
+        (Some("Date".to_string()), Category::Obsolete),
+        (Some("Date".to_string()), Category::DayOfWeek),
+        (Some("Message-ID".to_string()), Category::MessageID),
+        (Some("Subject".to_string()), Category::BareNewline),
+        (Some("Subject".to_string()), Category::EightBit),
+        (None, Category::MissingField),
+    ));
+
+    let i = b"Subject: hi\r\n\r\nbody\r\n";
+    let msg = parse_only(message, i).unwrap();
+    let categories: Vec<Category> = msg.lint().into_iter().map(|d| d.category).collect();
+    assert_eq!(categories, vec!(Category::MissingField, Category::MissingField));
+
+    // Values are checked with the message's options
+    let i = b"From: jdoe@example.com\r\nDate: 21 Nov 97 09:55:06 GMT\r\n\r\n";
+    let msg = parse_only(|i| message_with(i, Profile::Strict), i).unwrap();
+    let categories: Vec<Category> = msg.lint().into_iter().map(|d| d.category).collect();
+    assert_eq!(categories, vec!(Category::Malformed));
+
+    let i = b"From : jdoe@example.com\r\nDate: Fri, 21 Nov 1997 09:55:06 -0600\r\n\r\n";
+    let msg = parse_only(message, i).unwrap();
+    let diagnostics = msg.lint();
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].category, Category::Obsolete);
+    assert_eq!(diagnostics[0].to_string(), "From: field_name uses obsolete syntax");
+
+    let i = "From: J\u{f6}rg <j\u{f6}rg@example.com>\r\nDate: Fri, 21 Nov 1997 09:55:06 -0600\r\nSubject: Gr\u{fc}\u{df}e\r\n\r\n".as_bytes();
+    let utf8 = ParseOptions{ profile: Profile::Strict, utf8: true };
+    let msg = parse_only(|i| message_with(i, utf8), i).unwrap();
+    assert_eq!(msg.lint(), vec!());
+    let msg = parse_only(message, i).unwrap();
+    let categories: Vec<Category> = msg.lint().into_iter().map(|d| d.category).collect();
+    assert_eq!(categories, vec!(Category::EightBit, Category::Malformed, Category::EightBit));
+}
+
+#[test]
+fn test_contains_encoded_word() {
+    assert!(contains_encoded_word(b"=?utf-8?Q?Humble=20Bundle?="));
+    assert!(!contains_encoded_word(b"a=?b"));
+    assert!(quoted_encoded_word(b" \"=?utf-8?q?B?=\" <b@example.com>"));
+    assert!(!quoted_encoded_word(b" =?utf-8?q?B?= (\"=?utf-8?q?B?=\") <b@example.com>"));
+}
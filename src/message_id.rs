@@ -0,0 +1,143 @@
+//! Creating Message-IDs for outgoing mail, and checking received ones
+use std::collections::hash_map::RandomState;
+use std::fmt;
+use std::hash::{BuildHasher, Hash, Hasher};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use idna;
+
+use rfc5322::*;
+use error::IdnaError;
+use super::*;
+
+/// Creates Message-IDs that are unique across hosts and processes, as
+/// section 3.6.4 requires. The id-left is a timestamp, a random part and a
+/// counter, e.g. "<5a1b2c3d.4e5f60718293a4b5.1@example.com>".
+pub struct Generator {
+    domain: String,
+    counter: AtomicUsize,
+}
+
+impl Generator {
+    /// Creates a generator for IDs ending in `domain`, which should be a
+    /// name the sender controls. Internationalized names are converted to
+    /// A-labels.
+    pub fn new(domain: &str) -> Result<Generator, IdnaError> {
+        let error = IdnaError{ domain: domain.to_string() };
+        let ascii = try!(idna::domain_to_ascii(domain).map_err(|_| error.clone()));
+        if !is_dot_atom(&ascii) {
+            return Err(error);
+        }
+
+        Ok(Generator{
+            domain: ascii,
+            counter: AtomicUsize::new(0),
+        })
+    }
+
+    /// Returns a new ID
+    pub fn generate(&self) -> MessageID {
+        let count = self.counter.fetch_add(1, Ordering::SeqCst);
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+
+        // RandomState is seeded from the operating system's random source
+        let mut hasher = RandomState::new().build_hasher();
+        (now, count, process::id()).hash(&mut hasher);
+
+        MessageID{
+            id_left: Some(format!("{:x}.{:016x}.{:x}", now.as_secs(), hasher.finish(), count)),
+            id_right: self.domain.clone(),
+        }
+    }
+}
+
+/// A way a msg-id accepted by the obsolete or lenient profile breaks the
+/// strict grammar, or is a poor choice of unique identifier
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Defect {
+    /// There's no id-left "@", as in "<comm-tagged-1077147628989448>"
+    MissingAt,
+    /// There's whitespace or a comment inside the angle brackets, which
+    /// obs-id-left and obs-id-right allow
+    Whitespace,
+    /// The id-left isn't a dot-atom-text, e.g. it's a quoted-string
+    ObsoleteLeft,
+    /// The id-right isn't a dot-atom-text or no-fold-literal
+    ObsoleteRight,
+    /// The id-right is a domain literal, as in "<1234@[192.0.2.1]>".
+    /// no-fold-literal allows this, but an address doesn't identify a host
+    /// over time the way a name does, so IDs are more likely to collide.
+    DomainLiteral,
+}
+
+impl fmt::Display for Defect {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        let description = match *self {
+            Defect::MissingAt => "missing id-left and \"@\"",
+            Defect::Whitespace => "whitespace or comment inside msg-id",
+            Defect::ObsoleteLeft => "id-left isn't a dot-atom-text",
+            Defect::ObsoleteRight => "id-right isn't a dot-atom-text or no-fold-literal",
+            Defect::DomainLiteral => "domain literal as id-right",
+        };
+        write!(f, "{}", description)
+    }
+}
+
+impl MessageID {
+    /// Returns each way the ID deviates from the strict msg-id grammar, or
+    /// uses a domain literal
+    pub fn defects(&self) -> Vec<Defect> {
+        let mut defects = vec!();
+        let parts: Vec<&str> = self.id_left.iter().map(|l| &l[..]).chain(Some(&self.id_right[..])).collect();
+        if parts.iter().any(|p| p.contains(|c| c == ' ' || c == '\t' || c == '\r' || c == '\n' || c == '(')) {
+            defects.push(Defect::Whitespace);
+        }
+        match self.id_left {
+            Some(ref left) if !is_dot_atom(left) => defects.push(Defect::ObsoleteLeft),
+            Some(_) => {},
+            None => defects.push(Defect::MissingAt),
+        }
+        if self.id_right.starts_with('[') {
+            if parse_only(|i| no_fold_literal(i).then(|i| eof(i)), self.id_right.as_bytes()).is_err() {
+                defects.push(Defect::ObsoleteRight);
+            }
+            defects.push(Defect::DomainLiteral);
+        } else if !is_dot_atom(&self.id_right) {
+            defects.push(Defect::ObsoleteRight);
+        }
+        defects
+    }
+
+    /// Returns true if the ID has no defects
+    pub fn is_valid(&self) -> bool {
+        self.defects().is_empty()
+    }
+}
+
+#[test]
+fn test_generator() {
+    let generator = Generator::new("Bücher.example").unwrap();
+    let a = generator.generate();
+    let b = generator.generate();
+    assert!(a != b);
+    assert_eq!(a.id_right, "xn--bcher-kva.example");
+    assert!(a.is_valid());
+
+    let formatted = a.to_string();
+    assert_eq!(parse_only(|i| msg_id_with(i, Profile::Strict), formatted.as_bytes()).unwrap(), a);
+
+    assert!(Generator::new("not a domain").is_err());
+}
+
+#[test]
+fn test_defects() {
+    let id = |s: &[u8]| parse_only(msg_id, s).unwrap();
+    assert_eq!(id(b"<1234@local.machine.example>").defects(), vec!());
+    assert_eq!(id(b"<comm-tagged-1077147628989448>").defects(), vec!(Defect::MissingAt));
+    assert_eq!(id(b"<1234@ local(blah).machine.example >").defects(), vec!(Defect::Whitespace, Defect::ObsoleteRight));
+    assert_eq!(id(b"<\"12..34\"@local.machine.example>").defects(), vec!(Defect::ObsoleteLeft));
+    assert_eq!(id(b"<1234@[192.0.2.1]>").defects(), vec!(Defect::DomainLiteral));
+    assert_eq!(id(b"<1234@[192.0.2.1]>").to_string(), "<1234@[192.0.2.1]>");
+}
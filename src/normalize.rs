@@ -0,0 +1,285 @@
+//! Canonical forms of addresses, for deciding whether two addresses reach
+//! the same mailbox
+use std::fmt;
+use std::hash::{Hash, Hasher};
+
+use rfc5322::*;
+use util::*;
+use super::*;
+
+/// Provider-specific rules for local parts, applied to the domains they list
+#[derive(Clone, Debug, PartialEq)]
+pub struct Rule {
+    /// Domains the rule applies to, or every domain if empty
+    pub domains: Vec<String>,
+    /// Drops the local part from this separator on, e.g. "jdoe+lists" is
+    /// "jdoe" with '+'
+    pub subaddress: Option<char>,
+    /// Drops dots from the local part, e.g. "j.doe" is "jdoe"
+    pub ignore_dots: bool,
+    /// Replaces the domain, e.g. googlemail.com mailboxes are gmail.com ones
+    pub alias: Option<String>,
+}
+
+impl Rule {
+    /// "+" subaddresses on any domain
+    pub fn plus_addressing() -> Rule {
+        Rule{
+            domains: vec!(),
+            subaddress: Some('+'),
+            ignore_dots: false,
+            alias: None,
+        }
+    }
+
+    /// Gmail ignores dots and "+" subaddresses, and googlemail.com is an
+    /// alias for gmail.com
+    pub fn gmail() -> Rule {
+        Rule{
+            domains: vec!("gmail.com".to_string(), "googlemail.com".to_string()),
+            subaddress: Some('+'),
+            ignore_dots: true,
+            alias: Some("gmail.com".to_string()),
+        }
+    }
+
+    fn applies_to(&self, domain: &Domain) -> bool {
+        match domain {
+            &Domain::Name(ref name) => {
+                self.domains.is_empty() ||
+                    self.domains.iter().any(|d| d.eq_ignore_ascii_case(name))
+            },
+            _ => self.domains.is_empty(),
+        }
+    }
+}
+
+/// How to normalize addresses
+#[derive(Clone, Debug, PartialEq)]
+pub struct Policy {
+    /// Keep display names, including group names, with whitespace
+    /// collapsed. Otherwise they're dropped and don't affect comparisons.
+    pub display_name: bool,
+    /// Lowercase local parts. Section 2.4 of RFC 5321 says they're case
+    /// sensitive, though most hosts ignore their case.
+    pub fold_case: bool,
+    pub rules: Vec<Rule>,
+}
+
+impl Default for Policy {
+    fn default() -> Policy {
+        Policy{
+            display_name: false,
+            fold_case: false,
+            rules: vec!(),
+        }
+    }
+}
+
+impl Address {
+    /// Returns the address in canonical form: comments and surrounding
+    /// whitespace removed, the domain lowercased with A-labels, and `policy`
+    /// applied
+    pub fn normalize(&self, policy: &Policy) -> Address {
+        match self {
+            &Address::Mailbox{ref local_part, ref domain, ref display_name, ..} => {
+                let mut local_part = local_part.clone();
+                let mut domain = normalize_domain(domain);
+                if policy.fold_case {
+                    local_part = local_part.to_lowercase();
+                }
+                let rules: Vec<&Rule> = policy.rules.iter().filter(|r| r.applies_to(&domain)).collect();
+                for rule in rules {
+                    if let Some(separator) = rule.subaddress {
+                        // NOTE: A local part starting with the separator is
+                        // left alone, there's nothing before it to keep
+                        if let Some(pos) = local_part.find(separator) {
+                            if pos > 0 {
+                                local_part.truncate(pos);
+                            }
+                        }
+                    }
+                    if rule.ignore_dots {
+                        local_part = local_part.replace(".", "");
+                    }
+                    if let Some(ref alias) = rule.alias {
+                        domain = Domain::Name(alias.clone());
+                    }
+                }
+
+                Address::Mailbox{
+                    local_part: local_part,
+                    domain: domain,
+                    display_name: match display_name {
+                        &Some(ref name) if policy.display_name => Some(normalize_phrase(name)),
+                        _ => None,
+                    },
+                    comments: vec!(),
+                    route: None,
+                }
+            },
+            &Address::Group{ref display_name, ref mailboxes} => {
+                Address::Group{
+                    display_name: if policy.display_name { normalize_phrase(display_name) } else { String::new() },
+                    mailboxes: mailboxes.iter().map(|m| m.normalize(policy)).collect(),
+                }
+            },
+        }
+    }
+
+    /// Returns whether the addresses are the same once normalized
+    pub fn equivalent(&self, other: &Address, policy: &Policy) -> bool {
+        self.normalize(policy) == other.normalize(policy)
+    }
+}
+
+fn normalize_domain(domain: &Domain) -> Domain {
+    match domain {
+        &Domain::Name(_) => Domain::Name(domain.comparison_key()),
+        _ => domain.clone(),
+    }
+}
+
+fn normalize_phrase(phrase: &str) -> String {
+    phrase.split_whitespace().collect::<Vec<&str>>().join(" ")
+}
+
+/// An address in canonical form, which compares and hashes by that form so it
+/// can be used to deduplicate addresses in sets and maps
+#[derive(Clone, Debug)]
+pub struct NormalizedAddress {
+    address: Address,
+    key: String,
+}
+
+impl NormalizedAddress {
+    pub fn new(address: &Address, policy: &Policy) -> NormalizedAddress {
+        let address = address.normalize(policy);
+        let key = address_key(&address);
+
+        NormalizedAddress{
+            address: address,
+            key: key,
+        }
+    }
+
+    /// The normalized address
+    pub fn address(&self) -> &Address {
+        &self.address
+    }
+
+    /// The normalized address as a string, e.g. "jdoe@example.com", with the
+    /// local part quoted if it isn't a dot-atom
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+}
+
+fn address_key(address: &Address) -> String {
+    match address {
+        &Address::Mailbox{ref local_part, ref domain, ref display_name, ..} => {
+            let local_part = if is_dot_atom(local_part) {
+                local_part.clone()
+            } else {
+                quote(local_part)
+            };
+            match display_name {
+                &Some(ref name) => format!("{} <{}@{}>", quote(name), local_part, domain),
+                &None => format!("{}@{}", local_part, domain),
+            }
+        },
+        &Address::Group{ref display_name, ref mailboxes} => {
+            let keys: Vec<String> = mailboxes.iter().map(address_key).collect();
+            if display_name.is_empty() {
+                format!(": {};", keys.join(", "))
+            } else {
+                format!("{}: {};", quote(display_name), keys.join(", "))
+            }
+        },
+    }
+}
+
+impl PartialEq for NormalizedAddress {
+    fn eq(&self, other: &NormalizedAddress) -> bool {
+        self.key == other.key
+    }
+}
+
+impl Eq for NormalizedAddress {}
+
+impl Hash for NormalizedAddress {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.key.hash(state)
+    }
+}
+
+impl fmt::Display for NormalizedAddress {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(f, "{}", self.key)
+    }
+}
+
+#[test]
+fn test_normalize() {
+    let a = parse_only(mailbox, b"\"John\" <JDoe@Example.COM>").unwrap();
+    let b = parse_only(mailbox, b"jdoe@example.com").unwrap();
+    assert!(!a.equivalent(&b, &Policy::default()));
+    assert_eq!(NormalizedAddress::new(&a, &Policy::default()).key(), "JDoe@example.com");
+
+    let policy = Policy{ fold_case: true, ..Policy::default() };
+    assert!(a.equivalent(&b, &policy));
+    assert_eq!(NormalizedAddress::new(&a, &policy).key(), "jdoe@example.com");
+
+    let policy = Policy{ display_name: true, fold_case: true, ..Policy::default() };
+    assert!(!a.equivalent(&b, &policy));
+    assert_eq!(NormalizedAddress::new(&a, &policy).key(), "\"John\" <jdoe@example.com>");
+
+    let a = parse_only(mailbox, b"\"jdoe\"@example.com").unwrap();
+    assert_eq!(NormalizedAddress::new(&a, &Policy::default()).key(), "jdoe@example.com");
+    let a = parse_only(mailbox, b"\"j doe\\\"\"@example.com").unwrap();
+    assert_eq!(NormalizedAddress::new(&a, &Policy::default()).key(), "\"j doe\\\"\"@example.com");
+
+    let a = parse_only(mailbox, b"jdoe@ (comment) Example.COM (comment)").unwrap();
+    assert_eq!(NormalizedAddress::new(&a, &Policy::default()).key(), "jdoe@example.com");
+}
+
+#[test]
+fn test_normalize_group() {
+    let a = parse_only(address, b"Team: a@x, b@y;").unwrap();
+    let b = parse_only(address, b"Staff  : a@x, b@y;").unwrap();
+    assert!(a.equivalent(&b, &Policy::default()));
+    assert_eq!(NormalizedAddress::new(&a, &Policy::default()).key(), ": a@x, b@y;");
+
+    let policy = Policy{ display_name: true, ..Policy::default() };
+    assert!(!a.equivalent(&b, &policy));
+    assert_eq!(NormalizedAddress::new(&a, &policy).key(), "\"Team\": a@x, b@y;");
+}
+
+#[test]
+fn test_normalize_rules() {
+    let policy = Policy{ fold_case: true, rules: vec!(Rule::gmail()), ..Policy::default() };
+    let a = parse_only(mailbox, b"J.Doe+lists@googlemail.com").unwrap();
+    let b = parse_only(mailbox, b"jdoe@gmail.com").unwrap();
+    assert!(a.equivalent(&b, &policy));
+
+    let a = parse_only(mailbox, b"J.Doe+lists@example.com").unwrap();
+    assert_eq!(NormalizedAddress::new(&a, &policy).key(), "j.doe+lists@example.com");
+
+    let policy = Policy{ fold_case: true, rules: vec!(Rule::plus_addressing()), ..Policy::default() };
+    assert_eq!(NormalizedAddress::new(&a, &policy).key(), "j.doe@example.com");
+    let a = parse_only(mailbox, b"+lists@example.com").unwrap();
+    assert_eq!(NormalizedAddress::new(&a, &policy).key(), "+lists@example.com");
+}
+
+#[test]
+fn test_normalized_address_hash() {
+    use std::collections::HashSet;
+
+    let addresses = parse_only(address_list, b"JDoe@Example.COM, jdoe@example.com, mary@example.com").unwrap();
+    let unique = |policy: &Policy| addresses.iter()
+        .map(|a| NormalizedAddress::new(a, policy))
+        .collect::<HashSet<NormalizedAddress>>()
+        .len();
+    assert_eq!(unique(&Policy::default()), 3);
+    assert_eq!(unique(&Policy{ fold_case: true, ..Policy::default() }), 2);
+}
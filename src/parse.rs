@@ -9,12 +9,13 @@ extern crate env_logger;
 use std::io;
 use std::collections::HashSet;
 
-use bytes::str::ByteStr;
+use bytes::ByteStr;
 use chomp::buffer::{Source, Stream, StreamError};
 use chomp::types::*;
 
 use mail::*;
 use mail::rfc5322::*;
+use mail::error::{ParseError, parse_tracked};
 
 pub fn main() {
     env_logger::init().unwrap();
@@ -36,17 +37,29 @@ pub fn main() {
     let mut input = Source::new(io::stdin());
 
     loop {
-        match input.parse(message) {
+        let failure = match input.parse(message) {
             Ok(m) => {
                 output_message(m, &parsed_field_names);
                 break
             },
             Err(StreamError::Retry) => continue,
+            Err(StreamError::ParseError(rest, e)) => (rest.len(), e),
             Err(e) => {
                 println!("Error parsing from STDIN: {:?}", e);
                 break
             }
-        }
+        };
+        // Nothing is consumed before the message, so the buffer holds the
+        // input from its start. Parse it again while tracking productions,
+        // to report the innermost one that failed.
+        let (rest_len, e) = failure;
+        let buffer = input.buffer();
+        let error = match parse_tracked("message", buffer, message) {
+            Err(tracked) => tracked,
+            Ok(_) => ParseError::new("message", buffer, &buffer[buffer.len() - rest_len..], e),
+        };
+        println!("Error parsing from STDIN: {}", error);
+        break
     }
 }
 
@@ -54,48 +67,57 @@ fn output_message<I: U8Input>(m: Message<I>, parsed_field_names: &HashSet<String
     for field in m.fields.iter() {
         match field {
             &Field::Date(ref v) =>  {
-                if v.date_time().is_raw() {
-                    error!("failed to parse Date: {}", v.date_time().raw());
+                let value = v.date_time();
+                if let Some(e) = value.error() {
+                    error!("failed to parse Date: {} ({})", value.raw(), e);
                 }
             },
             &Field::From(ref v) => {
-                if v.addresses().is_raw() {
-                    error!("failed to parse From: {}", v.addresses().raw());
+                let value = v.addresses();
+                if let Some(e) = value.error() {
+                    error!("failed to parse From: {} ({})", value.raw(), e);
                 }
             },
             &Field::Sender(ref v) => {
-                if v.address().is_raw() {
-                    error!("failed to parse Sender: {}", v.address().raw());
+                let value = v.address();
+                if let Some(e) = value.error() {
+                    error!("failed to parse Sender: {} ({})", value.raw(), e);
                 }
             },
             &Field::ReplyTo(ref v) => {
-                if v.addresses().is_raw() {
-                    error!("failed to parse Reply-To: {}", v.addresses().raw());
+                let value = v.addresses();
+                if let Some(e) = value.error() {
+                    error!("failed to parse Reply-To: {} ({})", value.raw(), e);
                 }
             },
             &Field::To(ref v) => {
-                if v.addresses().is_raw() {
-                    error!("failed to parse To: {}", v.addresses().raw());
+                let value = v.addresses();
+                if let Some(e) = value.error() {
+                    error!("failed to parse To: {} ({})", value.raw(), e);
                 }
             },
             &Field::Cc(ref v) => {
-                if v.addresses().is_raw() {
-                    error!("failed to parse Cc: {}", v.addresses().raw());
+                let value = v.addresses();
+                if let Some(e) = value.error() {
+                    error!("failed to parse Cc: {} ({})", value.raw(), e);
                 }
             },
             &Field::MessageID(ref v) => {
-                if v.message_id().is_raw() {
-                    error!("failed to parse Message-ID: {}", v.message_id().raw());
+                let value = v.message_id();
+                if let Some(e) = value.error() {
+                    error!("failed to parse Message-ID: {} ({})", value.raw(), e);
                 }
             },
             &Field::InReplyTo(ref v) => {
-                if v.message_ids().is_raw() {
-                    error!("failed to parse In-Reply-To: {}", v.message_ids().raw());
+                let value = v.message_ids();
+                if let Some(e) = value.error() {
+                    error!("failed to parse In-Reply-To: {} ({})", value.raw(), e);
                 }
             },
             &Field::References(ref v) => {
-                if v.message_ids().is_raw() {
-                    error!("failed to parse References: {}", v.message_ids().raw());
+                let value = v.message_ids();
+                if let Some(e) = value.error() {
+                    error!("failed to parse References: {} ({})", value.raw(), e);
                 }
             },
             &Field::Optional(ref n, ref f) => {
@@ -0,0 +1,469 @@
+//! Best-effort recovery of dates from malformed Date fields
+//!
+//! Unlike the grammar in `rfc5322`, recovery splits the value into tokens and
+//! guesses what each one is, recording every guess it had to make.
+use std::fmt;
+
+use chrono::datetime::DateTime;
+use chrono::offset::LocalResult;
+use chrono::offset::TimeZone;
+use chrono::offset::fixed::FixedOffset;
+use chrono::naive::date::NaiveDate;
+use chrono::naive::datetime::NaiveDateTime;
+use chrono::naive::time::NaiveTime;
+use chomp::types::*;
+
+use rfc5322::*;
+use util::*;
+use super::*;
+
+const MONTHS: [&'static str; 12] = [
+    "january", "february", "march", "april", "may", "june",
+    "july", "august", "september", "october", "november", "december",
+];
+
+const DAYS: [&'static str; 7] = [
+    "monday", "tuesday", "wednesday", "thursday", "friday", "saturday", "sunday",
+];
+
+/// How likely a recovered date is to be what the sender meant
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Confidence {
+    /// A guess that's wrong for some plausible inputs
+    Low,
+    /// A guess that's right for almost all inputs
+    Medium,
+    /// No guessing beyond reading a non-standard format
+    High,
+}
+
+/// Something recovery had to assume
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Assumption {
+    /// There were no seconds, so they're taken as 0
+    NoSeconds,
+    /// There was no time, so midnight is used
+    NoTime,
+    /// A two- or three-digit year, and the year it was read as
+    ShortYear(i32),
+    /// A numeric date where either number could be the month, read as day
+    /// then month
+    DayFirst,
+    /// A numeric date where either number could be the month, read as month
+    /// then day
+    MonthFirst,
+    /// A date with its day and month in the wrong order for its format
+    SwappedDayMonth,
+    /// The zone was only named in a comment, e.g. "(PDT)"
+    ZoneFromComment,
+    /// There was no zone, so UTC is used
+    NoZone,
+}
+
+impl Assumption {
+    pub fn confidence(&self) -> Confidence {
+        match *self {
+            Assumption::NoSeconds => Confidence::High,
+            Assumption::ZoneFromComment => Confidence::High,
+            Assumption::ShortYear(_) => Confidence::Medium,
+            Assumption::SwappedDayMonth => Confidence::Medium,
+            Assumption::NoTime => Confidence::Low,
+            Assumption::DayFirst => Confidence::Low,
+            Assumption::MonthFirst => Confidence::Low,
+            Assumption::NoZone => Confidence::Low,
+        }
+    }
+}
+
+impl fmt::Display for Assumption {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            Assumption::NoSeconds => write!(f, "no seconds, assumed 0"),
+            Assumption::NoTime => write!(f, "no time, assumed midnight"),
+            Assumption::ShortYear(y) => write!(f, "short year, assumed {}", y),
+            Assumption::DayFirst => write!(f, "ambiguous numeric date, assumed day first"),
+            Assumption::MonthFirst => write!(f, "ambiguous numeric date, assumed month first"),
+            Assumption::SwappedDayMonth => write!(f, "day and month swapped"),
+            Assumption::ZoneFromComment => write!(f, "zone taken from a comment"),
+            Assumption::NoZone => write!(f, "no zone, assumed UTC"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct RecoveredDate {
+    pub date_time: DateTime<FixedOffset>,
+    /// The lowest confidence of the assumptions, or high if there were none
+    pub confidence: Confidence,
+    pub assumptions: Vec<Assumption>,
+}
+
+impl RecoveredDate {
+    fn new(date_time: DateTime<FixedOffset>, assumptions: Vec<Assumption>) -> RecoveredDate {
+        let confidence = assumptions.iter()
+            .map(|a| a.confidence())
+            .min()
+            .unwrap_or(Confidence::High);
+
+        RecoveredDate{
+            date_time: date_time,
+            confidence: confidence,
+            assumptions: assumptions,
+        }
+    }
+
+    /// Describes what was assumed, e.g. "no seconds, assumed 0; no zone,
+    /// assumed UTC"
+    pub fn description(&self) -> String {
+        self.assumptions.iter().map(|a| a.to_string()).collect::<Vec<String>>().join("; ")
+    }
+}
+
+impl<I: U8Input> DateTimeField<I> {
+    /// Parses the date leniently, and if that fails recovers what it can from
+    /// the common malformations: ISO 8601 dates, numeric or swapped days and
+    /// months, short years, missing seconds, and missing zones or zones only
+    /// named in a comment.
+    pub fn recover(&self) -> Option<RecoveredDate> {
+        match self.date_time() {
+            FieldValue::Ok(dt) | FieldValue::Accepted(dt, _) => Some(RecoveredDate::new(dt, vec!())),
+            _ => recover_date_time(&self.data.to_vec()),
+        }
+    }
+}
+
+// The parts of a date found so far
+#[derive(Default)]
+struct Parts {
+    // Numbers that aren't part of a time or a numeric date, with their length
+    numbers: Vec<(u32, usize)>,
+    month: Option<u32>,
+    // Numeric date fields with their lengths, and the separator
+    numeric_date: Option<([(u32, usize); 3], char)>,
+    time: Option<(u32, u32, Option<u32>)>,
+    pm: Option<bool>,
+    zone: Option<i32>,
+}
+
+/// Recovers a date from a malformed date-time, or returns None if it doesn't
+/// have at least a day, month and year
+pub fn recover_date_time(data: &[u8]) -> Option<RecoveredDate> {
+    let text = string_from_bytes(data.to_vec());
+    let mut parts = Parts::default();
+    let mut assumptions = vec!();
+
+    for token in strip_comments(&text).split(|c: char| c.is_whitespace() || c == ',') {
+        // ISO 8601 puts a "T" between the date and the time
+        match token.find('T') {
+            Some(n) if n > 0 && token[..n].contains('-') => {
+                if !read_token(&token[..n], &mut parts) || !read_token(&token[n + 1..], &mut parts) {
+                    return None
+                }
+            },
+            _ => if !read_token(token, &mut parts) {
+                return None
+            },
+        }
+    }
+
+    let (year, month, day) = match resolve_date(&mut parts, &mut assumptions) {
+        Some(date) => date,
+        None => return None,
+    };
+    let year = if year < 1000 {
+        let full = if year < 50 { year + 2000 } else { year + 1900 };
+        assumptions.push(Assumption::ShortYear(full));
+        full
+    } else {
+        year
+    };
+    let date = match NaiveDate::from_ymd_opt(year, month, day) {
+        Some(d) => d,
+        None => return None,
+    };
+
+    let (mut hour, minute, second) = match parts.time {
+        Some((h, m, Some(s))) => (h, m, s),
+        Some((h, m, None)) => {
+            assumptions.push(Assumption::NoSeconds);
+            (h, m, 0)
+        },
+        None => {
+            assumptions.push(Assumption::NoTime);
+            (0, 0, 0)
+        },
+    };
+    match parts.pm {
+        Some(true) if hour < 12 => hour += 12,
+        Some(false) if hour == 12 => hour = 0,
+        _ => {},
+    }
+    let time = match NaiveTime::from_hms_opt(hour, minute, second) {
+        Some(t) => t,
+        None => return None,
+    };
+
+    let zone = match parts.zone {
+        Some(minutes) => minutes,
+        None => {
+            let named = comments(data).iter()
+                .filter_map(|c| table_zone_offset(c.trim(), ZoneTable::default())
+                    .or(obs_zone_offset(&c.trim().to_uppercase(), ZoneTable::default())))
+                .next();
+            match named {
+                Some(minutes) => {
+                    assumptions.push(Assumption::ZoneFromComment);
+                    minutes
+                },
+                None => {
+                    assumptions.push(Assumption::NoZone);
+                    0
+                },
+            }
+        },
+    };
+    let offset = match FixedOffset::east_opt(zone * 60) {
+        Some(o) => o,
+        None => return None,
+    };
+
+    match offset.from_local_datetime(&NaiveDateTime::new(date, time)) {
+        LocalResult::Single(dt) => Some(RecoveredDate::new(dt, assumptions)),
+        _ => None,
+    }
+}
+
+// Records what `token` is in `parts`, or returns false if it's something a
+// date shouldn't contain
+fn read_token(token: &str, parts: &mut Parts) -> bool {
+    let lower = token.to_lowercase();
+    let starts_with_digit = token.chars().next().map(|c| c.is_digit(10)).unwrap_or(false);
+
+    if token.is_empty() || lower == "at" {
+        true
+    } else if starts_with_digit && token.contains(':') {
+        read_time(&lower, parts)
+    } else if token.starts_with('+') || token.starts_with('-') {
+        match numeric_zone(token) {
+            Some(z) if parts.zone.is_none() => {
+                parts.zone = Some(z);
+                true
+            },
+            _ => false,
+        }
+    } else if starts_with_digit && token.contains(|c| c == '/' || c == '-' || c == '.') {
+        read_numeric_date(token, parts)
+    } else if token.chars().all(|c| c.is_digit(10)) {
+        match token.parse() {
+            Ok(n) => {
+                parts.numbers.push((n, token.len()));
+                true
+            },
+            Err(_) => false,
+        }
+    } else if token.chars().all(|c| c.is_alphabetic() || c == '.') {
+        read_name(&lower, parts)
+    } else {
+        false
+    }
+}
+
+// Reads H:MM[:SS[.fraction]] followed by "am", "pm", "Z" or a numeric zone
+fn read_time(token: &str, parts: &mut Parts) -> bool {
+    let end = token.find(|c: char| !c.is_digit(10) && c != ':' && c != '.').unwrap_or(token.len());
+    let (time, rest) = token.split_at(end);
+    let time = match time.find('.') {
+        Some(n) => &time[..n],
+        None => time,
+    };
+    let fields: Vec<u32> = time.split(':').filter_map(|f| f.parse().ok()).collect();
+    parts.time = match (fields.len(), time.split(':').count()) {
+        (2, 2) => Some((fields[0], fields[1], None)),
+        (3, 3) => Some((fields[0], fields[1], Some(fields[2]))),
+        _ => return false,
+    };
+    match rest {
+        "" => true,
+        "am" | "pm" => read_name(rest, parts),
+        "z" => {
+            parts.zone = Some(0);
+            true
+        },
+        _ => match numeric_zone(rest) {
+            Some(z) => {
+                parts.zone = Some(z);
+                true
+            },
+            None => false,
+        },
+    }
+}
+
+// Reads a zone written +HHMM, +HH:MM or +HH as minutes east of UTC
+fn numeric_zone(token: &str) -> Option<i32> {
+    let sign = match token.chars().next() {
+        Some('+') => 1,
+        Some('-') => -1,
+        _ => return None,
+    };
+    let digits: String = token[1..].chars().filter(|&c| c != ':').collect();
+    if !digits.chars().all(|c| c.is_digit(10)) {
+        return None
+    }
+    let (hours, minutes) = match digits.len() {
+        2 => (digits.parse::<i32>().ok(), Some(0)),
+        4 => (digits[..2].parse::<i32>().ok(), digits[2..].parse::<i32>().ok()),
+        _ => return None,
+    };
+    match (hours, minutes) {
+        (Some(h), Some(m)) if h < 24 && m < 60 => Some(sign * (h * 60 + m)),
+        _ => None,
+    }
+}
+
+// Reads a date written with three numbers, e.g. 2016-09-22 or 22/09/16
+fn read_numeric_date(token: &str, parts: &mut Parts) -> bool {
+    let separator = match token.chars().find(|&c| c == '/' || c == '-' || c == '.') {
+        Some(c) => c,
+        None => return false,
+    };
+    let fields: Vec<&str> = token.split(separator).collect();
+    if fields.len() != 3 || parts.numeric_date.is_some() {
+        return false
+    }
+    let mut numbers = [(0, 0); 3];
+    for (n, field) in fields.iter().enumerate() {
+        match field.parse() {
+            Ok(v) => numbers[n] = (v, field.len()),
+            Err(_) => return false,
+        }
+    }
+    parts.numeric_date = Some((numbers, separator));
+    true
+}
+
+// Reads a month, day of the week, zone name, "am" or "pm"
+fn read_name(name: &str, parts: &mut Parts) -> bool {
+    let name = name.trim_right_matches('.');
+    let is_prefix = |full: &&str| name.len() >= 3 && full.starts_with(name);
+
+    if name == "sept" {
+        parts.month = Some(9);
+    } else if let Some(n) = MONTHS.iter().position(|m| is_prefix(&m)) {
+        parts.month = Some(n as u32 + 1);
+    } else if DAYS.iter().any(|d| is_prefix(&d)) {
+        // NOTE: The day of the week is ignored, it's often wrong
+    } else if name == "am" || name == "pm" {
+        parts.pm = Some(name == "pm");
+    } else {
+        let upper = name.to_uppercase();
+        // NOTE: Single letter military zones aren't trusted, they're more
+        // likely to be stray text
+        let zone = match upper.len() {
+            1 => None,
+            _ => obs_zone_offset(&upper, ZoneTable::default())
+                .or(table_zone_offset(&upper, ZoneTable::default())),
+        };
+        match zone {
+            Some(z) => parts.zone = Some(z),
+            None => return false,
+        }
+    }
+    true
+}
+
+// Works out the year, month and day from the parts read
+fn resolve_date(parts: &mut Parts, assumptions: &mut Vec<Assumption>) -> Option<(i32, u32, u32)> {
+    if let Some((numbers, separator)) = parts.numeric_date {
+        let (a, a_len) = numbers[0];
+        let (b, c) = (numbers[1].0, numbers[2].0);
+        if a_len == 4 {
+            // Year first, which is always followed by the month in ISO 8601
+            if b > 12 && c <= 12 {
+                assumptions.push(Assumption::SwappedDayMonth);
+                return Some((a as i32, c, b))
+            }
+            return Some((a as i32, b, c))
+        }
+        if a > 12 && b <= 12 {
+            return Some((c as i32, b, a))
+        }
+        if b > 12 && a <= 12 {
+            return Some((c as i32, a, b))
+        }
+        if a == b {
+            return Some((c as i32, a, b))
+        }
+        // NOTE: Slashes are mostly used month first, as in the US, and dots
+        // and dashes day first
+        if separator == '/' {
+            assumptions.push(Assumption::MonthFirst);
+            return Some((c as i32, a, b))
+        }
+        assumptions.push(Assumption::DayFirst);
+        return Some((c as i32, b, a))
+    }
+
+    let month = match parts.month {
+        Some(m) => m,
+        None => return None,
+    };
+    if parts.numbers.len() != 2 {
+        return None
+    }
+    // The day comes first as in RFC 5322, unless the first number can only be
+    // a year, e.g. "2016 Sep 22"
+    let (first, first_len) = parts.numbers[0];
+    let (second, _) = parts.numbers[1];
+    if first > 31 || first_len > 2 {
+        Some((first as i32, month, second))
+    } else {
+        Some((second as i32, month, first))
+    }
+}
+
+#[test]
+fn test_recover_date_time() {
+    use chrono::Datelike;
+
+    let recover = |s: &str| recover_date_time(s.as_bytes()).unwrap();
+
+    let r = recover("2016-09-22T19:51:07+02:00");
+    assert_eq!(r.date_time, FixedOffset::east(7200).ymd(2016, 9, 22).and_hms(19, 51, 7));
+    assert_eq!(r.confidence, Confidence::High);
+
+    let r = recover("2016-09-22 19:51:07.123Z");
+    assert_eq!(r.date_time, FixedOffset::east(0).ymd(2016, 9, 22).and_hms(19, 51, 7));
+    assert!(r.assumptions.is_empty());
+
+    let r = recover("Thu, 22 Sep 2016 19:51 -0700");
+    assert_eq!(r.date_time, FixedOffset::west(7 * 3600).ymd(2016, 9, 22).and_hms(19, 51, 0));
+    assert_eq!(r.assumptions, vec!(Assumption::NoSeconds));
+    assert_eq!(r.confidence, Confidence::High);
+
+    let r = recover("Thursday, September 22, 2016 7:51:07 PM CEST");
+    assert_eq!(r.date_time, FixedOffset::east(7200).ymd(2016, 9, 22).and_hms(19, 51, 7));
+
+    let r = recover("22 Sep 16 19:51:07 (PDT)");
+    assert_eq!(r.date_time, FixedOffset::west(7 * 3600).ymd(2016, 9, 22).and_hms(19, 51, 7));
+    assert_eq!(r.assumptions, vec!(Assumption::ShortYear(2016), Assumption::ZoneFromComment));
+    assert_eq!(r.confidence, Confidence::Medium);
+
+    let r = recover("09/22/2016 19:51:07");
+    assert_eq!(r.date_time, FixedOffset::east(0).ymd(2016, 9, 22).and_hms(19, 51, 7));
+    assert_eq!(r.assumptions, vec!(Assumption::NoZone));
+    assert_eq!(r.confidence, Confidence::Low);
+    assert_eq!(r.description(), "no zone, assumed UTC");
+
+    let r = recover("05.09.2016 19:51:07 +0200");
+    assert_eq!(r.date_time.month(), 9);
+    assert_eq!(r.assumptions, vec!(Assumption::DayFirst));
+
+    let r = recover("2016-22-09 19:51:07 +0000");
+    assert_eq!(r.date_time, FixedOffset::east(0).ymd(2016, 9, 22).and_hms(19, 51, 7));
+    assert_eq!(r.assumptions, vec!(Assumption::SwappedDayMonth));
+
+    assert!(recover_date_time(b"next thursday").is_none());
+    assert!(recover_date_time(b"31 Feb 2016 10:00 +0000").is_none());
+    assert!(recover_date_time(b"").is_none());
+}
@@ -0,0 +1,193 @@
+//! RFC2047 specifies encoded-words, for non-ASCII text in header fields
+use std::str;
+
+use util::decode_base64;
+
+// An encoded-word may not be more than 75 characters long
+const MAX_ENCODED_WORD_LEN: usize = 75;
+
+// encoded-word = "=?" charset "?" encoding "?" encoded-text "?="
+const PREFIX: &'static str = "=?utf-8?Q?";
+const SUFFIX: &'static str = "?=";
+
+// Characters that may appear unencoded in the "Q" encoding of a phrase, from
+// section 5 (3): upper and lower case ASCII letters, decimal digits, "!",
+// "*", "+", "-", "/". Space is written as "_".
+fn is_phrase_safe(c: u8) -> bool {
+    (c >= b'a' && c <= b'z') || (c >= b'A' && c <= b'Z') || (c >= b'0' && c <= b'9') ||
+        c == b'!' || c == b'*' || c == b'+' || c == b'-' || c == b'/'
+}
+
+/// Encodes `text` as UTF-8 "Q" encoded-words that may replace a phrase, such
+/// as a display name, separated by spaces. Long text is split into several
+/// encoded-words, never within a character.
+pub fn encode_phrase(text: &str) -> String {
+    let mut words: Vec<String> = vec!();
+    let mut word = String::new();
+    let mut buf = [0; 4];
+    for c in text.chars() {
+        let mut encoded = String::new();
+        for &b in c.encode_utf8(&mut buf).as_bytes() {
+            if b == b' ' {
+                encoded.push('_');
+            } else if is_phrase_safe(b) {
+                encoded.push(b as char);
+            } else {
+                encoded.push_str(&format!("={:02X}", b));
+            }
+        }
+        if PREFIX.len() + word.len() + encoded.len() + SUFFIX.len() > MAX_ENCODED_WORD_LEN {
+            words.push(format!("{}{}{}", PREFIX, word, SUFFIX));
+            word.clear();
+        }
+        word.push_str(&encoded);
+    }
+    words.push(format!("{}{}{}", PREFIX, word, SUFFIX));
+
+    words.join(" ")
+}
+
+/// Decodes the encoded-words in unstructured text, such as a Subject, as
+/// section 6 describes. Whitespace between adjacent encoded-words is dropped.
+/// Encoded-words in a charset other than UTF-8, US-ASCII or ISO-8859-1, or
+/// that don't decode, are left as they are.
+pub fn decode(text: &str) -> String {
+    let mut decoded = String::with_capacity(text.len());
+    // Whitespace waiting to be written, and whether the last word written was
+    // an encoded-word
+    let mut space = String::new();
+    let mut after_encoded = false;
+    let mut word = String::new();
+    for c in text.chars() {
+        if c == ' ' || c == '\t' || c == '\r' || c == '\n' {
+            if !word.is_empty() {
+                after_encoded = push_word(&mut decoded, &space, &word, after_encoded);
+                space.clear();
+                word.clear();
+            }
+            space.push(c);
+        } else {
+            word.push(c);
+        }
+    }
+    if !word.is_empty() {
+        push_word(&mut decoded, &space, &word, after_encoded);
+        space.clear();
+    }
+    decoded.push_str(&space);
+
+    decoded
+}
+
+// Writes a word and the whitespace before it, returning whether the word was
+// an encoded-word
+fn push_word(decoded: &mut String, space: &str, word: &str, after_encoded: bool) -> bool {
+    match decode_word(word) {
+        Some(text) => {
+            if !after_encoded {
+                decoded.push_str(space);
+            }
+            decoded.push_str(&text);
+            true
+        },
+        None => {
+            decoded.push_str(space);
+            decoded.push_str(word);
+            false
+        },
+    }
+}
+
+// encoded-word = "=?" charset "?" encoding "?" encoded-text "?="
+fn decode_word(word: &str) -> Option<String> {
+    if word.len() < 8 || !word.starts_with("=?") || !word.ends_with("?=") {
+        return None;
+    }
+    let parts: Vec<&str> = word[2..word.len()-2].split('?').collect();
+    if parts.len() != 3 {
+        return None;
+    }
+    // RFC 2231 allows a language after the charset, as in "utf-8*en"
+    let charset = parts[0].split('*').next().unwrap_or("").to_lowercase();
+    let bytes = match parts[1] {
+        "Q" | "q" => decode_q(parts[2].as_bytes()),
+        "B" | "b" => decode_base64(parts[2].as_bytes()),
+        _ => None,
+    };
+    let bytes = match bytes {
+        Some(b) => b,
+        None => return None,
+    };
+
+    match &charset[..] {
+        "utf-8" | "utf8" => String::from_utf8(bytes).ok(),
+        "us-ascii" if bytes.iter().all(|&b| b < 128) => String::from_utf8(bytes).ok(),
+        "iso-8859-1" | "latin1" => Some(bytes.into_iter().map(|b| b as char).collect()),
+        _ => None,
+    }
+}
+
+/// Returns true if `word` has the syntax of an encoded-word (section 2),
+/// whether or not its charset is one `decode` knows
+pub fn is_encoded_word(word: &str) -> bool {
+    if word.len() > MAX_ENCODED_WORD_LEN || word.len() < 8 || !word.starts_with("=?") || !word.ends_with("?=") {
+        return false;
+    }
+    let parts: Vec<&str> = word[2..word.len()-2].split('?').collect();
+    // especials, which token excludes, are ()<>@,;:"/[]?.=
+    let is_token = |s: &str| !s.is_empty() && s.bytes().all(|c| c > b' ' && c < 127 && !b"()<>@,;:\"/[]?.=".contains(&c));
+    parts.len() == 3 && is_token(parts[0]) && (parts[1] == "Q" || parts[1] == "q" || parts[1] == "B" || parts[1] == "b") &&
+        !parts[2].is_empty() && parts[2].bytes().all(|c| c > b' ' && c < 127)
+}
+
+// The "Q" encoding from section 4.2: "_" is a space, and "=" is followed by
+// two hexadecimal digits
+fn decode_q(text: &[u8]) -> Option<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(text.len());
+    let mut pos = 0;
+    while pos < text.len() {
+        match text[pos] {
+            b'_' => bytes.push(b' '),
+            b'=' => {
+                if pos + 2 >= text.len() {
+                    return None;
+                }
+                let hex = match str::from_utf8(&text[pos+1..pos+3]) {
+                    Ok(h) => h,
+                    Err(_) => return None,
+                };
+                match u8::from_str_radix(hex, 16) {
+                    Ok(b) => bytes.push(b),
+                    Err(_) => return None,
+                }
+                pos += 2;
+            },
+            c => bytes.push(c),
+        }
+        pos += 1;
+    }
+    Some(bytes)
+}
+
+#[test]
+fn test_encode_phrase() {
+    assert_eq!(encode_phrase("Jörg Müller"), "=?utf-8?Q?J=C3=B6rg_M=C3=BCller?=");
+    assert_eq!(encode_phrase("a=b?"), "=?utf-8?Q?a=3Db=3F?=");
+
+    let encoded = encode_phrase(&"ö".repeat(20));
+    let words: Vec<&str> = encoded.split(' ').collect();
+    assert_eq!(words.len(), 2);
+    assert!(words.iter().all(|w| w.len() <= MAX_ENCODED_WORD_LEN));
+    assert!(words[0].ends_with("=C3=B6?="));
+}
+
+#[test]
+fn test_decode() {
+    assert_eq!(decode("=?utf-8?Q?J=C3=B6rg_M=C3=BCller?="), "Jörg Müller");
+    assert_eq!(decode("=?ISO-8859-1?B?SvZyZw==?= and friends"), "Jörg and friends");
+    assert_eq!(decode("Re: =?utf-8?q?caf=C3=A9?= =?utf-8?q?_au_lait?= ok"), "Re: café au lait ok");
+    assert_eq!(decode("=?utf-8?Q?a?=  =?utf-8*en?B?Yg==?="), "ab");
+    assert_eq!(decode("  plain  text "), "  plain  text ");
+    assert_eq!(decode("=?koi8-r?Q?abc?= =?utf-8?Q?=ZZ?="), "=?koi8-r?Q?abc?= =?utf-8?Q?=ZZ?=");
+    assert_eq!(decode(&encode_phrase(&"ö".repeat(20))), "ö".repeat(20));
+}
@@ -0,0 +1,383 @@
+//! RFC2369 specifies the fields mailing lists add for list commands, RFC2919
+//! the List-Id field and RFC8058 the List-Unsubscribe-Post field
+use chomp::types::*;
+use chomp::parsers::*;
+use chomp::combinators::*;
+
+use rfc5322::*;
+use util::*;
+use super::*;
+
+// Characters allowed in a URL inside angle brackets: VCHAR other than "<"
+// and ">", or UTF-8 (RFC 6532)
+fn is_url_char(c: u8) -> bool {
+    c > 32 && c != 127 && c != b'<' && c != b'>'
+}
+
+// list-url        =   [CFWS] "<" url ">" [CFWS]
+//
+// NOTE: Section 2 says clients should ignore whitespace inside the brackets,
+// which poorly behaved MTAs may insert when folding. The obsolete and lenient
+// profiles allow it.
+pub fn list_url<I: U8Input>(i: I) -> SimpleResult<I, String> {
+    list_url_with(i, Profile::Lenient)
+}
+
+pub fn list_url_with<I: U8Input>(i: I, profile: Profile) -> SimpleResult<I, String> {
+    option(i, drop_cfws, ()).then(|i| {
+        token(i, b'<').then(|i| {
+            match profile {
+                Profile::Strict => take_while1(i, is_url_char).map(|buf| string_from_bytes(buf.into_vec())),
+                _ => many1(i, |i| {
+                    option(i, drop_fws, ()).then(|i| take_while1(i, is_url_char))
+                }).bind(|i, bufs: Vec<I::Buffer>| {
+                    option(i, drop_fws, ()).then(|i| i.ret(string_from_bufs::<I>(bufs)))
+                }),
+            }.bind(|i, url| {
+                token(i, b'>').then(|i| {
+                    option(i, drop_cfws, ()).then(|i| i.ret(url))
+                })
+            })
+        })
+    })
+}
+
+// list-urls       =   list-url *("," list-url)
+//
+// NOTE: The obsolete and lenient profiles allow empty list elements, as
+// obs-addr-list does
+pub fn list_urls<I: U8Input>(i: I) -> SimpleResult<I, Vec<String>> {
+    list_urls_with(i, Profile::Lenient)
+}
+
+pub fn list_urls_with<I: U8Input>(i: I, profile: Profile) -> SimpleResult<I, Vec<String>> {
+    match profile {
+        Profile::Strict => list_url_with(i, profile).bind(|i, first| {
+            many(i, |i| {
+                token(i, b',').then(|i| list_url_with(i, profile))
+            }).map(|mut urls: Vec<String>| {
+                urls.insert(0, first);
+                urls
+            })
+        }),
+        _ => skip_many(i, |i| {
+            option(i, drop_cfws, ()).then(|i| token(i, b','))
+        }).then(|i| {
+            list_url_with(i, profile).bind(|i, first| {
+                many(i, |i| {
+                    token(i, b',').then(|i| {
+                        or(i, |i| list_url_with(i, profile).map(Some),
+                              |i| option(i, drop_cfws, ()).map(|_| None))
+                    })
+                }).map(|urls: Vec<Option<String>>| {
+                    Some(first).into_iter().chain(urls.into_iter().filter_map(|u| u)).collect()
+                })
+            })
+        }),
+    }
+}
+
+#[test]
+fn test_list_urls() {
+    let i = b" <mailto:list@host.com?subject=help> (List Instructions),\r\n <http://www.host.com/list/>";
+    let msg = parse_only(|i| list_urls_with(i, Profile::Strict), i);
+    assert_eq!(msg.unwrap(), vec!("mailto:list@host.com?subject=help", "http://www.host.com/list/"));
+
+    let i = b" <ftp://ftp.host.com/list.txt> (FTP),\r\n <mailto:list@host.com?subject=help>";
+    let msg = parse_only(|i| list_urls_with(i, Profile::Strict), i);
+    assert_eq!(msg.unwrap(), vec!("ftp://ftp.host.com/list.txt", "mailto:list@host.com?subject=help"));
+
+    let i = b" <http://www.host.com/list/\r\n cgi-bin/unsub?user=1>, , <mailto:unsub@host.com>";
+    assert!(parse_only(|i| list_urls_with(i, Profile::Strict), i).is_err());
+    let msg = parse_only(list_urls, i);
+    assert_eq!(msg.unwrap(), vec!("http://www.host.com/list/cgi-bin/unsub?user=1", "mailto:unsub@host.com"));
+
+    let i = b" mailto:list@host.com";
+    assert!(parse_only(list_urls, i).is_err());
+}
+
+// list-post       =   list-urls / ([CFWS] "NO" [CFWS])
+//
+// NOTE: The obsolete and lenient profiles accept "NO" in any case
+pub fn list_post<I: U8Input>(i: I) -> SimpleResult<I, ListPost> {
+    list_post_with(i, Profile::Lenient)
+}
+
+pub fn list_post_with<I: U8Input>(i: I, profile: Profile) -> SimpleResult<I, ListPost> {
+    let no = |i: I| {
+        option(i, drop_cfws, ()).then(|i| {
+            match profile {
+                Profile::Strict => string(i, b"NO"),
+                _ => downcased_string(i, b"no"),
+            }.then(|i| {
+                option(i, drop_cfws, ()).then(|i| i.ret(ListPost::No))
+            })
+        })
+    };
+    or(i, |i| list_urls_with(i, profile).map(ListPost::Urls), no)
+}
+
+#[test]
+fn test_list_post() {
+    let i = b" <mailto:list@host.com>";
+    let msg = parse_only(|i| list_post_with(i, Profile::Strict), i);
+    assert_eq!(msg.unwrap(), ListPost::Urls(vec!("mailto:list@host.com".to_string())));
+
+    let i = b" NO (posting not allowed on this list)";
+    let msg = parse_only(|i| list_post_with(i, Profile::Strict), i);
+    assert_eq!(msg.unwrap(), ListPost::No);
+
+    let i = b" no";
+    assert!(parse_only(|i| list_post_with(i, Profile::Strict), i).is_err());
+    assert_eq!(parse_only(list_post, i).unwrap(), ListPost::No);
+}
+
+// list-id-header  =   "List-ID:" [phrase] "<" list-id ">" CRLF
+// list-id         =   list-label "." list-id-namespace
+// list-label      =   dot-atom-text
+// list-id-namespace = domain-name / unmanaged-list-id-namespace
+// unmanaged-list-id-namespace = "localhost"
+// domain-name     =   dot-atom-text
+//
+// NOTE: Allowing CFWS around the brackets. The strict profile only allows
+// words in the phrase, and requires a "." in the list-id. The lenient
+// profile also accepts an identifier without brackets.
+pub fn list_id<I: U8Input>(i: I) -> SimpleResult<I, ListId> {
+    list_id_with(i, Profile::Lenient)
+}
+
+pub fn list_id_with<I: U8Input>(i: I, profile: Profile) -> SimpleResult<I, ListId> {
+    let bracketed = |i: I| {
+        option(i, |i| display_name_with(i, profile).map(Some), None).bind(|i, p| {
+            option(i, drop_cfws, ()).then(|i| {
+                token(i, b'<').then(|i| {
+                    dot_atom_text(i).bind(|i, id| {
+                        token(i, b'>').then(|i| {
+                            option(i, drop_cfws, ()).then(|i| i.ret((p, id)))
+                        })
+                    })
+                })
+            })
+        })
+    };
+    let bare = |i: I| {
+        option(i, drop_cfws, ()).then(|i| {
+            dot_atom_text(i).bind(|i, id| {
+                option(i, drop_cfws, ()).then(|i| i.ret((None, id)))
+            })
+        })
+    };
+    match profile {
+        Profile::Lenient => or(i, bracketed, bare),
+        _ => bracketed(i),
+    }.bind(|i, (p, id): (Option<Vec<I::Buffer>>, I::Buffer)| {
+        let id = string_from_bytes(id.into_vec());
+        if profile == Profile::Strict && !id.contains('.') {
+            return i.err(Error::unexpected());
+        }
+        let description = p.map(|p| unescape(string_from_bufs::<I>(p).trim()))
+            .and_then(|d| if d.is_empty() { None } else { Some(d) });
+
+        i.ret(ListId{
+            description: description,
+            id: id,
+        })
+    })
+}
+
+#[test]
+fn test_list_id() {
+    let i = b" List Header Mailing List <list-header.nisto.com>";
+    let msg = parse_only(|i| list_id_with(i, Profile::Strict), i);
+    assert_eq!(msg.unwrap(), ListId{
+        description: Some("List Header Mailing List".to_string()),
+        id: "list-header.nisto.com".to_string(),
+    });
+
+    let i = b" <commonspace-users.list-id.within.com>";
+    let msg = parse_only(|i| list_id_with(i, Profile::Strict), i);
+    assert_eq!(msg.unwrap(), ListId{ description: None, id: "commonspace-users.list-id.within.com".to_string() });
+
+    let i = b" \"Rust \\\"users\\\"\" <users.rust-lang.org>";
+    let msg = parse_only(|i| list_id_with(i, Profile::Strict), i);
+    assert_eq!(msg.unwrap().description, Some("Rust \"users\"".to_string()));
+
+    let i = b" \"Rust (beta) users\" (comment) <users.rust-lang.org>";
+    let msg = parse_only(|i| list_id_with(i, Profile::Strict), i);
+    assert_eq!(msg.unwrap().description, Some("Rust (beta) users".to_string()));
+
+    let i = b" <mylist>";
+    assert!(parse_only(|i| list_id_with(i, Profile::Strict), i).is_err());
+    assert_eq!(parse_only(list_id, i).unwrap().id, "mylist");
+
+    let i = b" users.rust-lang.org";
+    assert!(parse_only(|i| list_id_with(i, Profile::Obsolete), i).is_err());
+    assert_eq!(parse_only(list_id, i).unwrap().id, "users.rust-lang.org");
+}
+
+// List-Unsubscribe-Post: List-Unsubscribe=One-Click
+//
+// NOTE: RFC 8058 requires exactly this key/value pair. The obsolete and
+// lenient profiles accept it in any case, with FWS around the "=".
+pub fn list_unsubscribe_post<I: U8Input>(i: I) -> SimpleResult<I, ()> {
+    list_unsubscribe_post_with(i, Profile::Lenient)
+}
+
+pub fn list_unsubscribe_post_with<I: U8Input>(i: I, profile: Profile) -> SimpleResult<I, ()> {
+    option(i, drop_cfws, ()).then(|i| {
+        match profile {
+            Profile::Strict => string(i, b"List-Unsubscribe=One-Click").map(|_| ()),
+            _ => downcased_string(i, b"list-unsubscribe").then(|i| {
+                option(i, drop_fws, ()).then(|i| {
+                    token(i, b'=').then(|i| {
+                        option(i, drop_fws, ()).then(|i| {
+                            downcased_string(i, b"one-click").map(|_| ())
+                        })
+                    })
+                })
+            }),
+        }.then(|i| {
+            option(i, drop_cfws, ())
+        })
+    })
+}
+
+#[test]
+fn test_list_unsubscribe_post() {
+    let i = b" List-Unsubscribe=One-Click";
+    assert!(parse_only(|i| list_unsubscribe_post_with(i, Profile::Strict), i).is_ok());
+
+    let i = b" list-unsubscribe = one-click";
+    assert!(parse_only(|i| list_unsubscribe_post_with(i, Profile::Strict), i).is_err());
+    assert!(parse_only(list_unsubscribe_post, i).is_ok());
+
+    let i = b" List-Unsubscribe=Maybe";
+    assert!(parse_only(list_unsubscribe_post, i).is_err());
+}
+
+// List-Id, from RFC 2919
+// NOTE: Accepting case-insensitive header name values
+pub fn raw_list_id<I: U8Input>(i: I) -> SimpleResult<I, Field<I>> {
+    drop_field_name(i, b"List-Id").then(|i| {
+        till_crlf(i).bind(|i, v| {
+            let value = ListIdField {data: v};
+
+            i.ret(Field::ListId(value))
+        })
+    })
+}
+
+// list-help       =   "List-Help:" list-urls CRLF
+// NOTE: Accepting case-insensitive header name values
+pub fn raw_list_help<I: U8Input>(i: I) -> SimpleResult<I, Field<I>> {
+    drop_field_name(i, b"List-Help").then(|i| {
+        till_crlf(i).bind(|i, v| {
+            let value = ListUrlsField {data: v};
+
+            i.ret(Field::ListHelp(value))
+        })
+    })
+}
+
+// list-unsubscribe =  "List-Unsubscribe:" list-urls CRLF
+// NOTE: Accepting case-insensitive header name values
+pub fn raw_list_unsubscribe<I: U8Input>(i: I) -> SimpleResult<I, Field<I>> {
+    drop_field_name(i, b"List-Unsubscribe").then(|i| {
+        till_crlf(i).bind(|i, v| {
+            let value = ListUrlsField {data: v};
+
+            i.ret(Field::ListUnsubscribe(value))
+        })
+    })
+}
+
+// List-Unsubscribe-Post, from RFC 8058
+// NOTE: Accepting case-insensitive header name values
+pub fn raw_list_unsubscribe_post<I: U8Input>(i: I) -> SimpleResult<I, Field<I>> {
+    drop_field_name(i, b"List-Unsubscribe-Post").then(|i| {
+        till_crlf(i).bind(|i, v| {
+            let value = ListUnsubscribePostField {data: v};
+
+            i.ret(Field::ListUnsubscribePost(value))
+        })
+    })
+}
+
+// list-subscribe  =   "List-Subscribe:" list-urls CRLF
+// NOTE: Accepting case-insensitive header name values
+pub fn raw_list_subscribe<I: U8Input>(i: I) -> SimpleResult<I, Field<I>> {
+    drop_field_name(i, b"List-Subscribe").then(|i| {
+        till_crlf(i).bind(|i, v| {
+            let value = ListUrlsField {data: v};
+
+            i.ret(Field::ListSubscribe(value))
+        })
+    })
+}
+
+// list-post       =   "List-Post:" list-post CRLF
+// NOTE: Accepting case-insensitive header name values
+pub fn raw_list_post<I: U8Input>(i: I) -> SimpleResult<I, Field<I>> {
+    drop_field_name(i, b"List-Post").then(|i| {
+        till_crlf(i).bind(|i, v| {
+            let value = ListUrlsField {data: v};
+
+            i.ret(Field::ListPost(value))
+        })
+    })
+}
+
+// list-owner      =   "List-Owner:" list-urls CRLF
+// NOTE: Accepting case-insensitive header name values
+pub fn raw_list_owner<I: U8Input>(i: I) -> SimpleResult<I, Field<I>> {
+    drop_field_name(i, b"List-Owner").then(|i| {
+        till_crlf(i).bind(|i, v| {
+            let value = ListUrlsField {data: v};
+
+            i.ret(Field::ListOwner(value))
+        })
+    })
+}
+
+// list-archive    =   "List-Archive:" list-urls CRLF
+// NOTE: Accepting case-insensitive header name values
+pub fn raw_list_archive<I: U8Input>(i: I) -> SimpleResult<I, Field<I>> {
+    drop_field_name(i, b"List-Archive").then(|i| {
+        till_crlf(i).bind(|i, v| {
+            let value = ListUrlsField {data: v};
+
+            i.ret(Field::ListArchive(value))
+        })
+    })
+}
+
+// Any of the mailing list fields
+pub fn raw_list_fields<I: U8Input>(i: I) -> SimpleResult<I, Field<I>> {
+    or(i,       raw_list_id,
+    |i| or(i,   raw_list_help,
+    |i| or(i,   raw_list_unsubscribe,
+    |i| or(i,   raw_list_unsubscribe_post,
+    |i| or(i,   raw_list_subscribe,
+    |i| or(i,   raw_list_post,
+    |i| or(i,   raw_list_owner,
+                raw_list_archive,
+                )))))))
+}
+
+#[test]
+fn test_raw_list_fields() {
+    let i = b"List-Unsubscribe-Post: List-Unsubscribe=One-Click\r\n";
+    match parse_only(raw_list_fields, i).unwrap() {
+        Field::ListUnsubscribePost(f) => assert!(f.one_click().is_ok()),
+        f => panic!("unexpected field {:?}", f),
+    }
+
+    let i = b"list-post: NO\r\n";
+    match parse_only(raw_list_fields, i).unwrap() {
+        Field::ListPost(f) => assert_eq!(f.post().unwrap(), ListPost::No),
+        f => panic!("unexpected field {:?}", f),
+    }
+
+    let i = b"List-Unsubscribe-Token: abc\r\n";
+    assert!(parse_only(raw_list_fields, i).is_err());
+}
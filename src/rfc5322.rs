@@ -1,14 +1,15 @@
 //! RFC5322 specifies message bodies (supercedes RFC2822)
 
-use chrono::Datelike;
+use chrono::{Datelike, Timelike};
 use chrono::datetime::DateTime;
 use chrono::offset::LocalResult;
-use chrono::offset::TimeZone;
+use chrono::offset::{Offset, TimeZone};
 use chrono::offset::fixed::FixedOffset;
 use chrono::naive::datetime::NaiveDateTime;
 use chrono::naive::time::NaiveTime;
 use chrono::naive::date::NaiveDate;
-use chrono::offset::utc::UTC;
+use std::cell::Cell;
+use std::net::{Ipv4Addr, Ipv6Addr};
 use bytes::{Bytes, ByteStr};
 
 use chomp::*;
@@ -20,6 +21,10 @@ use chomp::primitives::IntoInner;
 
 use super::*;
 use super::util::*;
+use error::production;
+use rfc2369::raw_list_fields;
+use rfc8601::raw_authentication_results;
+use rfc6376::raw_dkim_signature;
 
 // ALPHA          =  %x41-5A / %x61-7A   ; A-Z / a-z
 // ALPHA          =  %d65-90 / %d97-122  ; A-Z / a-z
@@ -147,8 +152,84 @@ const VCHAR: [bool; 256] = [
     false, false, false, false, false, false, false, false, false, false, false, false, false, false, false, false, false, false, false, false, // 220 - 239
     false, false, false, false, false, false, false, false, false, false, false, false, false, false, false, false                              // 240 - 256
 ];
+//
+// VCHAR          =/ UTF8-non-ascii      ; RFC 6532
+//
+// NOTE: RFC 6532 extends VCHAR, ctext, atext, qtext, dtext and text with
+// UTF8-non-ascii. Those productions only accept it inside `utf8`, and are
+// ASCII-only otherwise.
 pub fn vchar<I: U8Input>(i: I) -> SimpleResult<I, u8> {
-    satisfy(i, |c| VCHAR[c as usize])
+    text_or_utf8(i, &VCHAR)
+}
+
+thread_local!(static UTF8: Cell<bool> = Cell::new(false));
+
+/// Runs `parser` with UTF-8 enabled (RFC 6532), so text productions accept
+/// well-formed UTF8-non-ascii as well as ASCII, e.g.
+/// `parse_only(|i| utf8(i, mailbox), input)`
+pub fn utf8<I: U8Input, T, F>(i: I, parser: F) -> SimpleResult<I, T> where
+F: FnOnce(I) -> SimpleResult<I, T>,
+{
+    let outer = UTF8.with(|u| u.replace(true));
+    let result = parser(i);
+    UTF8.with(|u| u.set(outer));
+    result
+}
+
+// Matches a byte in `table`, or inside `utf8`, a UTF8-non-ascii character.
+// Returns the first byte of the character.
+fn text_or_utf8<I: U8Input>(i: I, table: &'static [bool; 256]) -> SimpleResult<I, u8> {
+    if UTF8.with(|u| u.get()) {
+        or(i, |i| satisfy(i, |c| table[c as usize]), utf8_non_ascii)
+    } else {
+        satisfy(i, |c| table[c as usize])
+    }
+}
+
+// UTF8-non-ascii  =   UTF8-2 / UTF8-3 / UTF8-4     ; RFC 6532
+//
+// UTF8-2          =   %xC2-DF UTF8-tail            ; RFC 3629
+// UTF8-3          =   %xE0 %xA0-BF UTF8-tail / %xE1-EC 2( UTF8-tail ) /
+//                     %xED %x80-9F UTF8-tail / %xEE-EF 2( UTF8-tail )
+// UTF8-4          =   %xF0 %x90-BF 2( UTF8-tail ) / %xF1-F3 3( UTF8-tail ) /
+//                     %xF4 %x80-8F 2( UTF8-tail )
+// UTF8-tail       =   %x80-BF
+pub fn utf8_non_ascii<I: U8Input>(i: I) -> SimpleResult<I, u8> {
+    satisfy(i, |c| c >= 0xc2 && c <= 0xf4).bind(|i, lead| {
+        // The range of the second byte, and how many tail bytes follow it
+        let (low, high, tail) = if lead <= 0xdf {
+            (0x80, 0xbf, 0)
+        } else if lead == 0xe0 {
+            (0xa0, 0xbf, 1)
+        } else if lead == 0xed {
+            (0x80, 0x9f, 1)
+        } else if lead <= 0xef {
+            (0x80, 0xbf, 1)
+        } else if lead == 0xf0 {
+            (0x90, 0xbf, 2)
+        } else if lead == 0xf4 {
+            (0x80, 0x8f, 2)
+        } else {
+            (0x80, 0xbf, 2)
+        };
+        satisfy(i, |c| c >= low && c <= high).then(|i| {
+            count(i, tail, |i| satisfy(i, |c| c >= 0x80 && c <= 0xbf)).map(|_: Vec<u8>| lead)
+        })
+    })
+}
+
+#[test]
+fn test_utf8_non_ascii() {
+    let word = |s: &[u8]| parse_only(|i| skip_many1(i, atext).then(eof), s).is_ok();
+    let utf8_word = |s: &[u8]| parse_only(|i| utf8(i, |i| skip_many1(i, atext)).then(eof), s).is_ok();
+    assert!(word(b"jdoe"));
+    assert!(!word("jörg".as_bytes()));
+    assert!(utf8_word("jörg".as_bytes()));
+    assert!(utf8_word("日本語𝄞".as_bytes()));
+    assert!(!utf8_word(b"j\xf6rg"));
+    assert!(!utf8_word(b"\xe6\x97"));
+    assert!(!utf8_word(b"\xed\xa0\x80"));
+    assert!(!utf8_word(b"\xc0\xaf"));
 }
 
 // WSP            =  SP / HTAB
@@ -230,8 +311,10 @@ const CTEXT: [bool; 256] = [
     false, false, false, false, false, false, false, false, false, false, false, false, false, false, false, false, false, false, false, false, // 220 - 239
     false, false, false, false, false, false, false, false, false, false, false, false, false, false, false, false                              // 240 - 256
 ];
+//
+// ctext           =/  UTF8-non-ascii     ; RFC 6532
 pub fn ctext<I: U8Input>(i: I) -> SimpleResult<I, u8> {
-    satisfy(i, |c| CTEXT[c as usize])
+    text_or_utf8(i, &CTEXT)
 }
 
 // ccontent        =   ctext / quoted-pair / comment
@@ -361,8 +444,10 @@ const ATEXT: [bool; 256] = [
     false, false, false, false, false, false, false, false, false, false, false, false, false, false, false, false, false, false, false, false, // 220 - 239
     false, false, false, false, false, false, false, false, false, false, false, false, false, false, false, false                              // 240 - 256
 ];
+//
+// atext           =/  UTF8-non-ascii     ; RFC 6532
 pub fn atext<I: U8Input>(i: I) -> SimpleResult<I, u8> {
-    satisfy(i, |c| ATEXT[c as usize])
+    text_or_utf8(i, &ATEXT)
 }
 
 #[test]
@@ -411,7 +496,7 @@ fn test_atom() {
 pub fn dot_atom_text<I: U8Input>(i: I) -> SimpleResult<I, I::Buffer> {
     matched_by(i, |i| {
         skip_many1(i, atext).then(|i| {
-            skip_many1(i, |i| {
+            skip_many(i, |i| {
                 token(i, b'.').then(|i| {
                     skip_many1(i, atext)
                 })
@@ -420,6 +505,29 @@ pub fn dot_atom_text<I: U8Input>(i: I) -> SimpleResult<I, I::Buffer> {
     }).map(|(buf, _)| buf)
 }
 
+#[test]
+fn test_dot_atom_text() {
+    let whole = |i: &'static [u8]| parse_only(|i| dot_atom_text(i).bind(|i, b| eof(i).map(|_| b)), i);
+    // A single label has no dots, which *("." 1*atext) allows
+    assert_eq!(whole(b"jdoe"), Ok(&b"jdoe"[..]));
+    assert_eq!(whole(b"localhost"), Ok(&b"localhost"[..]));
+    assert_eq!(whole(b"john.q.public"), Ok(&b"john.q.public"[..]));
+    assert!(whole(b"jdoe.").is_err());
+    assert!(whole(b".jdoe").is_err());
+    assert!(whole(b"j..doe").is_err());
+}
+
+// Returns true if `s` is a dot-atom-text, so needn't be quoted. `s` is a
+// string, so may contain UTF-8 (RFC 6532).
+pub fn is_dot_atom(s: &str) -> bool {
+    parse_only(|i| utf8(i, dot_atom_text).then(|i| eof(i)), s.as_bytes()).is_ok()
+}
+
+// Returns true if `s` is 1*atext, so is a word without quoting
+pub fn is_atom(s: &str) -> bool {
+    parse_only(|i| utf8(i, |i| skip_many1(i, atext)).then(|i| eof(i)), s.as_bytes()).is_ok()
+}
+
 // dot-atom        =   [CFWS] dot-atom-text [CFWS]
 pub fn dot_atom<I: U8Input>(i: I) -> SimpleResult<I, Vec<I::Buffer>> {
     option(i, cfws, vec!()).bind(|i, buf1| {
@@ -460,8 +568,10 @@ const QTEXT: [bool; 256] = [
     false, false, false, false, false, false, false, false, false, false, false, false, false, false, false, false, false, false, false, false, // 220 - 239
     false, false, false, false, false, false, false, false, false, false, false, false, false, false, false, false                              // 240 - 256
 ];
+//
+// qtext           =/  UTF8-non-ascii     ; RFC 6532
 pub fn qtext<I: U8Input>(i: I) -> SimpleResult<I, u8> {
-    satisfy(i, |c| QTEXT[c as usize])
+    text_or_utf8(i, &QTEXT)
 }
 
 // qcontent        =   qtext / quoted-pair
@@ -490,29 +600,31 @@ fn test_qcontent() {
 // quoted-string; the quoted-string is what is contained between the two
 // quote characters.
 pub fn quoted_string<I: U8Input>(i: I) -> SimpleResult<I, Vec<I::Buffer>> {
-    option(i, cfws, vec!()).bind(|i, cfws_bytes_pre| {
-        dquote(i).then(|i| {
-            many(i, |i| {
-                option(i, fws, vec!()).bind(|i, mut fws_bytes| {
-                    // NOTE: Take advantage of the buffer
-                    matched_by(i, |i| {
-                        skip_many1(i, qcontent)
-                    }).map(|(buf, _)| {
-                        fws_bytes.push(buf);
-                        fws_bytes
+    production(i, "quoted_string", |i| {
+        option(i, cfws, vec!()).bind(|i, cfws_bytes_pre| {
+            dquote(i).then(|i| {
+                many(i, |i| {
+                    option(i, fws, vec!()).bind(|i, mut fws_bytes| {
+                        // NOTE: Take advantage of the buffer
+                        matched_by(i, |i| {
+                            skip_many1(i, qcontent)
+                        }).map(|(buf, _)| {
+                            fws_bytes.push(buf);
+                            fws_bytes
+                        })
                     })
-                })
-            }).map(|mut bufs: Vec<Vec<I::Buffer>>| {
-                bufs.insert(0, cfws_bytes_pre);
-                bufs.into_iter().flat_map(|v| v).collect()
-
-            }).bind(|i, buf: Vec<I::Buffer>| {
-                option(i, fws, vec!()).bind(|i, fws_bytes| {
-                    dquote(i).then(|i| {
-                        option(i, cfws, vec!()).bind(|i, cfws_bytes_post| {
-                            let v = vec!(buf, fws_bytes, cfws_bytes_post).into_iter().flat_map(|v| v).collect();
-
-                            i.ret(v)
+                }).map(|mut bufs: Vec<Vec<I::Buffer>>| {
+                    bufs.insert(0, cfws_bytes_pre);
+                    bufs.into_iter().flat_map(|v| v).collect()
+
+                }).bind(|i, buf: Vec<I::Buffer>| {
+                    option(i, fws, vec!()).bind(|i, fws_bytes| {
+                        dquote(i).then(|i| {
+                            option(i, cfws, vec!()).bind(|i, cfws_bytes_post| {
+                                let v = vec!(buf, fws_bytes, cfws_bytes_post).into_iter().flat_map(|v| v).collect();
+
+                                i.ret(v)
+                            })
                         })
                     })
                 })
@@ -632,22 +744,53 @@ pub fn unstructured_crlf<I: U8Input>(i: I) -> SimpleResult<I, Vec<I::Buffer>> {
     obs_unstruct_crlf(i)
 }
 
+// Succeeds at the end of a field value: any trailing CFWS, the CRLF ending
+// the field if it's present, then the end of input
+pub fn end_of_value<I: U8Input>(i: I) -> SimpleResult<I, ()> {
+    option(i, drop_cfws, ()).then(|i| {
+        option(i, |i| crlf(i).map(|_| ()), ()).then(eof)
+    })
+}
+
 // date-time       =   [ day-of-week "," ] date time [CFWS]
 pub fn date_time<I: U8Input>(i: I) -> SimpleResult<I, DateTime<FixedOffset>> {
-    option(i, |i| {
-        day_of_week(i).then(|i| {
-            token(i, b',').map(|_| ())
-        })
-    }, ()).then(|i| {
-        date(i).bind(|i, d| {
-            time(i).bind(|i, (t, o)| {
-                option(i, drop_cfws, ()).then(|i| {
-                    let ndt = NaiveDateTime::new(d, t);
+    date_time_with(i, Profile::Lenient)
+}
 
-                    match o.from_local_datetime(&ndt) {
-                        LocalResult::Single(dt) => i.ret(dt),
-                        _ => i.err(Error::unexpected()),
-                    }
+pub fn date_time_with<I: U8Input>(i: I, profile: Profile) -> SimpleResult<I, DateTime<FixedOffset>> {
+    date_time_zones(i, profile, ZoneTable::default())
+}
+
+pub fn date_time_zones<I: U8Input>(i: I, profile: Profile, zones: ZoneTable) -> SimpleResult<I, DateTime<FixedOffset>> {
+    zoned_date_time_with(i, profile, zones).map(|d| d.date_time)
+}
+
+// Parses a date-time, keeping whether the zone gave the sender's local offset
+pub fn zoned_date_time_with<I: U8Input>(i: I, profile: Profile, zones: ZoneTable) -> SimpleResult<I, ZonedDateTime> {
+    date_time_day_with(i, profile, zones).map(|(_, d)| d)
+}
+
+// Parses a date-time along with its day-of-week, which isn't checked against
+// the date
+pub fn date_time_day_with<I: U8Input>(i: I, profile: Profile, zones: ZoneTable) -> SimpleResult<I, (Option<Day>, ZonedDateTime)> {
+    production(i, "date_time", |i| {
+        option(i, |i| {
+            day_of_week_with(i, profile).bind(|i, day| {
+                token(i, b',').map(|_| Some(day))
+            })
+        }, None).bind(|i, day| {
+            date_with(i, profile).bind(|i, d| {
+                time_of_day_with(i, profile).bind(|i, t| {
+                    local_zone_with(i, profile, zones).bind(|i, (o, zone)| {
+                        option(i, drop_cfws, ()).then(|i| {
+                            let ndt = NaiveDateTime::new(d, t);
+
+                            match o.from_local_datetime(&ndt) {
+                                LocalResult::Single(dt) => i.ret((day, ZonedDateTime{ date_time: dt, zone: zone })),
+                                _ => i.err(Error::unexpected()),
+                            }
+                        })
+                    })
                 })
             })
         })
@@ -680,11 +823,91 @@ fn test_date_time() {
     assert!(msg.is_ok());
 }
 
+#[test]
+fn test_date_time_with() {
+    let i = b"Fri, 21 Nov 1997 09:55:06 -0600\r\n";
+    let msg = parse_only(|i| date_time_with(i, Profile::Strict).then(end_of_value), i);
+    assert!(msg.is_ok());
+
+    let i = b"21 Nov 97 09:55:06 GMT\r\n";
+    let msg = parse_only(|i| date_time_with(i, Profile::Strict), i);
+    assert!(msg.is_err());
+    let msg = parse_only(|i| date_time_with(i, Profile::Obsolete), i);
+    assert_eq!(msg.unwrap(), FixedOffset::west(0).ymd(1997, 11, 21).and_hms(9,55,6));
+
+    let i = b"21 Sept 16 1:51 UTC\r\n";
+    let msg = parse_only(|i| date_time_with(i, Profile::Obsolete).then(end_of_value), i);
+    assert!(msg.is_err());
+    let msg = parse_only(|i| date_time_with(i, Profile::Lenient).then(end_of_value), i);
+    assert!(msg.is_ok());
+
+    let i = b"21 Nov 1997 25:55:06 -0600\r\n";
+    let msg = parse_only(date_time, i);
+    assert!(msg.is_err());
+
+    let i = b"Thu, 21 Nov 1997 09:55:06 -0600\r\n";
+    let (day, _) = parse_only(|i| date_time_day_with(i, Profile::Strict, ZoneTable::default()), i).unwrap();
+    assert_eq!(day, Some(Day::Thu));
+}
+
+const DAY_NAMES: [&'static str; 7] = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+
+const MONTH_NAMES: [&'static str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+/// Formats a date-time as section 3.3 specifies, e.g. "Tue, 1 Jul 2003
+/// 10:52:37 +0200". The day-of-week is computed from the date, and an offset
+/// with seconds is truncated to minutes.
+pub fn format_date_time(date_time: &DateTime<FixedOffset>) -> String {
+    let offset = date_time.offset().local_minus_utc().num_minutes();
+    let sign = if offset < 0 { '-' } else { '+' };
+    let offset = offset.abs();
+    format!("{}, {} {} {:04} {:02}:{:02}:{:02} {}{:02}{:02}",
+            DAY_NAMES[date_time.weekday().num_days_from_monday() as usize],
+            date_time.day(),
+            MONTH_NAMES[date_time.month0() as usize],
+            date_time.year(),
+            date_time.hour(),
+            date_time.minute(),
+            date_time.second(),
+            sign, offset / 60, offset % 60)
+}
+
+/// Formats a date-time in UTC with the zone "-0000", which section 3.3 says
+/// means the time is UTC but the sender's local zone is unknown
+pub fn format_date_time_unknown_zone(date_time: &DateTime<FixedOffset>) -> String {
+    let utc = date_time.with_timezone(&FixedOffset::east(0));
+    let formatted = format_date_time(&utc);
+    format!("{}-0000", &formatted[..formatted.len()-5])
+}
+
+#[test]
+fn test_format_date_time() {
+    let dt = FixedOffset::east(2*3600).ymd(2003, 7, 1).and_hms(10, 52, 37);
+    assert_eq!(format_date_time(&dt), "Tue, 1 Jul 2003 10:52:37 +0200");
+    assert_eq!(format_date_time_unknown_zone(&dt), "Tue, 1 Jul 2003 08:52:37 -0000");
+
+    let dt = FixedOffset::west(3*3600 + 30*60).ymd(1969, 2, 13).and_hms(23, 32, 0);
+    let formatted = format_date_time(&dt);
+    assert_eq!(formatted, "Thu, 13 Feb 1969 23:32:00 -0330");
+    let msg = parse_only(|i| date_time_with(i, Profile::Strict).bind(|i, v| end_of_value(i).map(|_| v)), formatted.as_bytes());
+    assert_eq!(msg.unwrap(), dt);
+}
+
 // day-of-week     =   ([FWS] day-name) / obs-day-of-week
 pub fn day_of_week<I: U8Input>(i: I) -> SimpleResult<I, Day> {
-    or(i, 
-       |i| option(i, drop_fws, ()).then(day_name),
-       obs_day_of_week)
+    day_of_week_with(i, Profile::Lenient)
+}
+
+pub fn day_of_week_with<I: U8Input>(i: I, profile: Profile) -> SimpleResult<I, Day> {
+    production(i, "day_of_week", |i| {
+        let new = |i: I| option(i, drop_fws, ()).then(day_name);
+        match profile {
+            Profile::Strict => new(i),
+            _ => or(i, new, obs_day_of_week),
+        }
+    })
 }
 
 // day-name        =   "Mon" / "Tue" / "Wed" / "Thu" /
@@ -701,25 +924,25 @@ pub fn day_name<I: U8Input>(i: I) -> SimpleResult<I, Day> {
 
 // date            =   day month year
 pub fn date<I: U8Input>(i: I) -> SimpleResult<I, NaiveDate> {
-    day(i).bind(|i, d| {
-        month(i).bind(|i, m| {
-            year(i).bind(|i, mut y| {
-                // NOTE: For two-digit years, assume the century is either the
-                // current one or the last one, and that the message date is in 
-                // the past
-                let this_year: usize = UTC::today().year() as usize;
-                if y < 100 {
-                    let prefix = (this_year / 100) * 100;
-                    if y + prefix <= this_year {
-                        y += prefix;
-                    } else {
-                        y += prefix - 100;
+    date_with(i, Profile::Lenient)
+}
+
+pub fn date_with<I: U8Input>(i: I, profile: Profile) -> SimpleResult<I, NaiveDate> {
+    production(i, "date", |i| {
+        day_with(i, profile).bind(|i, d| {
+            month_with(i, profile).bind(|i, m| {
+                year_with(i, profile).bind(|i, mut y| {
+                    if profile != Profile::Strict && y < 1000 {
+                        // Section 4.3: two-digit years from 00 to 49 are in the
+                        // 2000s, and other two- or three-digit years have 1900
+                        // added
+                        y += if y < 50 { 2000 } else { 1900 };
                     }
-                }
-                match NaiveDate::from_ymd_opt(y as i32, 1 + (m as u32), d as u32) {
-                    Some(nd) => i.ret(nd),
-                    None => i.err(Error::unexpected()),
-                }
+                    match NaiveDate::from_ymd_opt(y as i32, 1 + (m as u32), d as u32) {
+                        Some(nd) => i.ret(nd),
+                        None => i.err(Error::unexpected()),
+                    }
+                })
             })
         })
     })
@@ -736,21 +959,38 @@ fn test_date() {
     let msg = parse_only(date, i);
     assert!(msg.is_ok());
     assert_eq!(msg.unwrap(), NaiveDate::from_ymd(1997, 11, 21));
+
+    for &profile in [Profile::Obsolete, Profile::Lenient].iter() {
+        let year = |i: &[u8]| parse_only(|i| date_with(i, profile), i).unwrap().year();
+        assert_eq!(year(b"1 Jan 30"), 2030);
+        assert_eq!(year(b"1 Jan 49"), 2049);
+        assert_eq!(year(b"1 Jan 50"), 1950);
+        assert_eq!(year(b"1 Jan 097"), 1997);
+        assert_eq!(year(b"1 Jan 103"), 2003);
+    }
 }
  
 // day             =   ([FWS] 1*2DIGIT FWS) / obs-day
 pub fn day<I: U8Input>(i: I) -> SimpleResult<I, usize> {
-    or(i,
-       |i| {
-           option(i, drop_fws, ()).then(|i| {
-               parse_digits(i, (1..3)).bind(|i, d| {
-                   drop_fws(i).then(|i| {
-                       i.ret(d)
-                   })
-               })
-           })
-       },
-       obs_day)
+    day_with(i, Profile::Lenient)
+}
+
+pub fn day_with<I: U8Input>(i: I, profile: Profile) -> SimpleResult<I, usize> {
+    production(i, "day", |i| {
+        let new = |i: I| {
+            option(i, drop_fws, ()).then(|i| {
+                parse_digits(i, (1..3)).bind(|i, d| {
+                    drop_fws(i).then(|i| {
+                        i.ret(d)
+                    })
+                })
+            })
+        };
+        match profile {
+            Profile::Strict => new(i),
+            _ => or(i, new, obs_day),
+        }
+    })
 }
 
 #[test]
@@ -763,20 +1003,31 @@ fn test_day() {
 // month           =   "Jan" / "Feb" / "Mar" / "Apr" /
 //                     "May" / "Jun" / "Jul" / "Aug" /
 //                     "Sep" / "Oct" / "Nov" / "Dec"
+//
+// NOTE: The lenient profile also accepts "Sept"
 pub fn month<I: U8Input>(i: I) -> SimpleResult<I, Month> {
-    or(i, |i| string(i, b"Jan").then(|i| i.ret(Month::Jan)),
-    |i| or(i, |i| string(i, b"Feb").then(|i| i.ret(Month::Feb)),
-    |i| or(i, |i| string(i, b"Mar").then(|i| i.ret(Month::Mar)),
-    |i| or(i, |i| string(i, b"Apr").then(|i| i.ret(Month::Apr)),
-    |i| or(i, |i| string(i, b"May").then(|i| i.ret(Month::May)),
-    |i| or(i, |i| string(i, b"Jun").then(|i| i.ret(Month::Jun)),
-    |i| or(i, |i| string(i, b"Jul").then(|i| i.ret(Month::Jul)),
-    |i| or(i, |i| string(i, b"Aug").then(|i| i.ret(Month::Aug)),
-    |i| or(i, |i| string(i, b"Sept").then(|i| i.ret(Month::Sep)),
-    |i| or(i, |i| string(i, b"Sep").then(|i| i.ret(Month::Sep)),
-    |i| or(i, |i| string(i, b"Oct").then(|i| i.ret(Month::Oct)),
-    |i| or(i, |i| string(i, b"Nov").then(|i| i.ret(Month::Nov)),
-    |i| string(i, b"Dec").then(|i| i.ret(Month::Dec))))))))))))))
+    month_with(i, Profile::Lenient)
+}
+
+pub fn month_with<I: U8Input>(i: I, profile: Profile) -> SimpleResult<I, Month> {
+    production(i, "month", |i| {
+        or(i, |i| string(i, b"Jan").then(|i| i.ret(Month::Jan)),
+        |i| or(i, |i| string(i, b"Feb").then(|i| i.ret(Month::Feb)),
+        |i| or(i, |i| string(i, b"Mar").then(|i| i.ret(Month::Mar)),
+        |i| or(i, |i| string(i, b"Apr").then(|i| i.ret(Month::Apr)),
+        |i| or(i, |i| string(i, b"May").then(|i| i.ret(Month::May)),
+        |i| or(i, |i| string(i, b"Jun").then(|i| i.ret(Month::Jun)),
+        |i| or(i, |i| string(i, b"Jul").then(|i| i.ret(Month::Jul)),
+        |i| or(i, |i| string(i, b"Aug").then(|i| i.ret(Month::Aug)),
+        |i| or(i, |i| match profile {
+            Profile::Lenient => string(i, b"Sept").then(|i| i.ret(Month::Sep)),
+            _ => i.err(Error::unexpected()),
+        },
+        |i| or(i, |i| string(i, b"Sep").then(|i| i.ret(Month::Sep)),
+        |i| or(i, |i| string(i, b"Oct").then(|i| i.ret(Month::Oct)),
+        |i| or(i, |i| string(i, b"Nov").then(|i| i.ret(Month::Nov)),
+        |i| string(i, b"Dec").then(|i| i.ret(Month::Dec))))))))))))))
+    })
 }
 
 #[test]
@@ -788,9 +1039,23 @@ fn test_month() {
  
 // year            =   (FWS 4*DIGIT FWS) / obs-year
 pub fn year<I: U8Input>(i: I) -> SimpleResult<I, usize> {
-    or(i,
-       |i| drop_fws(i).then(|i| parse_digits(i, (4..))),
-       obs_year)
+    year_with(i, Profile::Lenient)
+}
+
+pub fn year_with<I: U8Input>(i: I, profile: Profile) -> SimpleResult<I, usize> {
+    production(i, "year", |i| {
+        let new = |i: I| {
+            drop_fws(i).then(|i| {
+                parse_digits(i, (4..)).bind(|i, y| {
+                    drop_fws(i).then(|i| i.ret(y))
+                })
+            })
+        };
+        match profile {
+            Profile::Strict => new(i),
+            _ => or(i, new, obs_year),
+        }
+    })
 }
 
 #[test]
@@ -806,8 +1071,12 @@ fn test_year() {
 
 // time            =   time-of-day zone
 pub fn time<I: U8Input>(i: I) -> SimpleResult<I, (NaiveTime, FixedOffset)> {
-    time_of_day(i).bind(|i, t| {
-        zone(i).bind(|i, z| {
+    time_with(i, Profile::Lenient, ZoneTable::default())
+}
+
+pub fn time_with<I: U8Input>(i: I, profile: Profile, zones: ZoneTable) -> SimpleResult<I, (NaiveTime, FixedOffset)> {
+    time_of_day_with(i, profile).bind(|i, t| {
+        zone_with(i, profile, zones).bind(|i, z| {
             i.ret((t, z))
         })
     })
@@ -840,13 +1109,22 @@ fn test_time() {
 
 // time-of-day     =   hour ":" minute [ ":" second ]
 pub fn time_of_day<I: U8Input>(i: I) -> SimpleResult<I, NaiveTime> {
-    hour(i).bind(|i, h| {
-        token(i, b':').then(|i| {
-            minute(i).bind(|i, m| {
-                option(i, |i| {
-                    token(i, b':').then(|i| second(i))
-                }, 0).bind(|i, s| {
-                    i.ret(NaiveTime::from_hms(h as u32, m as u32, s as u32))
+    time_of_day_with(i, Profile::Lenient)
+}
+
+pub fn time_of_day_with<I: U8Input>(i: I, profile: Profile) -> SimpleResult<I, NaiveTime> {
+    production(i, "time_of_day", |i| {
+        hour_with(i, profile).bind(|i, h| {
+            token(i, b':').then(|i| {
+                minute_with(i, profile).bind(|i, m| {
+                    option(i, |i| {
+                        token(i, b':').then(|i| second_with(i, profile))
+                    }, 0).bind(|i, s| {
+                        match NaiveTime::from_hms_opt(h as u32, m as u32, s as u32) {
+                            Some(t) => i.ret(t),
+                            None => i.err(Error::unexpected()),
+                        }
+                    })
                 })
             })
         })
@@ -854,46 +1132,85 @@ pub fn time_of_day<I: U8Input>(i: I) -> SimpleResult<I, NaiveTime> {
 }
 
 // hour            =   2DIGIT / obs-hour
+//
+// NOTE: The lenient profile also accepts single digit hours
 pub fn hour<I: U8Input>(i: I) -> SimpleResult<I, usize> {
-    or(i,
-       |i| parse_digits(i, (1..3)),
-       obs_hour)
+    hour_with(i, Profile::Lenient)
+}
+
+pub fn hour_with<I: U8Input>(i: I, profile: Profile) -> SimpleResult<I, usize> {
+    match profile {
+        Profile::Strict => parse_digits(i, 2),
+        Profile::Obsolete => or(i, |i| parse_digits(i, 2), |i| {
+            option(i, drop_cfws, ()).then(|i| {
+                parse_digits(i, 2).bind(|i, n| {
+                    option(i, drop_cfws, ()).then(|i| i.ret(n))
+                })
+            })
+        }),
+        Profile::Lenient => or(i, |i| parse_digits(i, (1..3)), obs_hour),
+    }
 }
 
 // minute          =   2DIGIT / obs-minute
 pub fn minute<I: U8Input>(i: I) -> SimpleResult<I, usize> {
-    or(i,
-       |i| parse_digits(i, 2),
-       obs_minute)
+    minute_with(i, Profile::Lenient)
+}
+
+pub fn minute_with<I: U8Input>(i: I, profile: Profile) -> SimpleResult<I, usize> {
+    match profile {
+        Profile::Strict => parse_digits(i, 2),
+        _ => or(i, |i| parse_digits(i, 2), obs_minute),
+    }
 }
 
 // second          =   2DIGIT / obs-second
 pub fn second<I: U8Input>(i: I) -> SimpleResult<I, usize> {
-    or(i,
-       |i| parse_digits(i, 2),
-       obs_second)
+    second_with(i, Profile::Lenient)
+}
+
+pub fn second_with<I: U8Input>(i: I, profile: Profile) -> SimpleResult<I, usize> {
+    match profile {
+        Profile::Strict => parse_digits(i, 2),
+        _ => or(i, |i| parse_digits(i, 2), obs_second),
+    }
 }
 
 // zone            =   (FWS ( "+" / "-" ) 4DIGIT) / obs-zone
 pub fn zone<I: U8Input>(i: I) -> SimpleResult<I, FixedOffset> {
-    or(i,
-       |i| {
-           drop_fws(i).then(|i| {
-               or(i, |i| token(i, b'+'), |i| token(i, b'-')).bind(|i, s| {
-                   parse_digits(i, 2).bind(|i, offset_h: i32| {
-                       parse_digits(i, 2).bind(|i, offset_m: i32| {
-                           let offset = (offset_h * 3600) + (offset_m * 60);
-                           let zone = match s {
-                               b'+' => FixedOffset::east(offset),
-                               _ => FixedOffset::west(offset),
-                           };
-                           i.ret(zone)
-                       })
-                   })
-               })
-           })
-       },
-       obs_zone)
+    zone_with(i, Profile::Lenient, ZoneTable::default())
+}
+
+pub fn zone_with<I: U8Input>(i: I, profile: Profile, zones: ZoneTable) -> SimpleResult<I, FixedOffset> {
+    local_zone_with(i, profile, zones).map(|(o, _)| o)
+}
+
+// Parses a zone, along with whether it gives the sender's local offset.
+// Section 3.3 says "-0000" means the local zone is unknown, unlike "+0000".
+pub fn local_zone_with<I: U8Input>(i: I, profile: Profile, zones: ZoneTable) -> SimpleResult<I, (FixedOffset, LocalZone)> {
+    production(i, "zone", |i| {
+        let new = |i: I| {
+            drop_fws(i).then(|i| {
+                or(i, |i| token(i, b'+'), |i| token(i, b'-')).bind(|i, s| {
+                    parse_digits(i, 2).bind(|i, offset_h: i32| {
+                        parse_digits(i, 2).bind(|i, offset_m: i32| {
+                            let offset = (offset_h * 3600) + (offset_m * 60);
+                            let zone = match s {
+                                b'+' => (FixedOffset::east(offset), LocalZone::Known),
+                                _ if offset == 0 => (FixedOffset::west(0), LocalZone::Unknown),
+                                _ => (FixedOffset::west(offset), LocalZone::Known),
+                            };
+                            i.ret(zone)
+                        })
+                    })
+                })
+            })
+        };
+        match profile {
+            Profile::Strict => new(i),
+            _ => or(i, new, |i| obs_local_zone_with(i, profile, zones)),
+        }
+    })
 }
 
 #[test]
@@ -901,30 +1218,116 @@ fn test_zone() {
     let i = b" -0330";
     let msg = parse_only(zone, i);
     assert!(msg.is_ok());
+
+    let local_zone = |i: &[u8]| parse_only(|i| local_zone_with(i, Profile::Lenient, ZoneTable::default()), i).unwrap();
+    assert_eq!(local_zone(b" +0000"), (FixedOffset::east(0), LocalZone::Known));
+    assert_eq!(local_zone(b" -0000"), (FixedOffset::east(0), LocalZone::Unknown));
+    assert_eq!(local_zone(b" -0330"), (FixedOffset::west(3*3600 + 30*60), LocalZone::Known));
+    assert_eq!(local_zone(b" GMT"), (FixedOffset::east(0), LocalZone::Known));
+    assert_eq!(local_zone(b" Nowhere"), (FixedOffset::east(0), LocalZone::Unknown));
+    assert_eq!(local_zone(b" A"), (FixedOffset::east(3600), LocalZone::Known));
+    assert_eq!(local_zone(b" Y"), (FixedOffset::west(12*3600), LocalZone::Known));
+    assert_eq!(local_zone(b" Z"), (FixedOffset::east(0), LocalZone::Known));
+
+    let zones = ZoneTable{ names: ZONE_ABBREVIATIONS, military: false };
+    let msg = parse_only(|i| local_zone_with(i, Profile::Lenient, zones), b" A");
+    assert_eq!(msg.unwrap(), (FixedOffset::east(0), LocalZone::Unknown));
 }
 
 // address         =   mailbox / group
 pub fn address<I: U8Input>(i: I) -> SimpleResult<I, Address> {
-    or(i, mailbox, group)
+    address_with(i, Profile::Lenient)
+}
+
+pub fn address_with<I: U8Input>(i: I, profile: Profile) -> SimpleResult<I, Address> {
+    production(i, "address", |i| {
+        or(i, |i| mailbox_with(i, profile), |i| group_with(i, profile))
+    })
 }
 
 // mailbox         =   name-addr / addr-spec
 pub fn mailbox<I: U8Input>(i: I) -> SimpleResult<I, Address> {
-    or(i,
-       |i| name_addr(i).map(|(local_part, domain, maybe_display_name)| {
-           Address::Mailbox{
-               local_part: unchecked_string_from_bufs::<I>(local_part),
-               domain: unsafe { String::from_utf8_unchecked(domain.into_vec()) },
-               display_name: maybe_display_name.map(|v| unchecked_string_from_bufs::<I>(v)),
-           }
-       }),
-       |i| addr_spec(i).map(|(local_part, domain)| {
-           Address::Mailbox{
-               local_part: unchecked_string_from_bufs::<I>(local_part),
-               domain: unsafe { String::from_utf8_unchecked(domain.into_vec()) },
-               display_name: None,
-           }
-       }))
+    mailbox_with(i, Profile::Lenient)
+}
+
+// NOTE: Comments are collected from the matched text rather than threaded
+// through every production that allows CFWS
+pub fn mailbox_with<I: U8Input>(i: I, profile: Profile) -> SimpleResult<I, Address> {
+    production(i, "mailbox", |i| {
+        let name_addr = |i: I| {
+            matched_by(i, |i| name_addr_with(i, profile)).bind(|i, (buf, (local_part, domain, maybe_display_name))| {
+                match typed_domain::<I>(domain) {
+                    Some(d) => i.ret(new_mailbox::<I>(buf, local_part, d, maybe_display_name, None)),
+                    None => i.err(Error::unexpected()),
+                }
+            })
+        };
+        let addr_spec = |i: I| {
+            matched_by(i, |i| addr_spec_with(i, profile)).bind(|i, (buf, (local_part, domain))| {
+                match typed_domain::<I>(domain) {
+                    Some(d) => i.ret(new_mailbox::<I>(buf, local_part, d, None, None)),
+                    None => i.err(Error::unexpected()),
+                }
+            })
+        };
+        match profile {
+            Profile::Strict => or(i, name_addr, addr_spec),
+            _ => or(i, name_addr, |i| or(i, |i| obs_name_addr(i, profile), addr_spec)),
+        }
+    })
+}
+
+// A name-addr whose angle-addr is an obs-angle-addr
+fn obs_name_addr<I: U8Input>(i: I, profile: Profile) -> SimpleResult<I, Address> {
+    matched_by(i, |i| {
+        option(i, |i| display_name_with(i, profile).map(|n| Some(n)), None).bind(|i, n| {
+            obs_angle_addr(i).map(|(route, l, d)| (route, l, d, n))
+        })
+    }).bind(|i, (buf, (route, local_part, domain, maybe_display_name))| {
+        let route: Option<Vec<Domain>> = route.into_iter().map(typed_domain::<I>).collect();
+        match (route, typed_domain::<I>(domain)) {
+            (Some(r), Some(d)) => i.ret(new_mailbox::<I>(buf, local_part, d, maybe_display_name, Some(r))),
+            _ => i.err(Error::unexpected()),
+        }
+    })
+}
+
+// Builds a mailbox from its parts and the text it was parsed from, resolving
+// quoted-pairs. Without a display name, a comment following the address is
+// used, as older mailers wrote "jdoe@example.com (John Doe)".
+fn new_mailbox<I: U8Input>(buf: I::Buffer, local_part: Vec<I::Buffer>, domain: Domain, display_name: Option<Vec<I::Buffer>>, route: Option<Vec<Domain>>) -> Address {
+    let bytes = buf.into_vec();
+    let comments = comments(&bytes);
+    let display_name = match display_name {
+        Some(name) => Some(unescape(&string_from_bufs::<I>(name))),
+        None => match bytes.iter().rposition(|&c| c != b' ' && c != b'\t' && c != b'\r' && c != b'\n') {
+            Some(last) if bytes[last] == b')' => comments.last().cloned(),
+            _ => None,
+        },
+    };
+
+    Address::Mailbox{
+        // NOTE: Trimming CFWS around the local part also trims whitespace at
+        // the ends of a quoted-string, which is rarely intended
+        local_part: unescape(string_from_bufs::<I>(local_part).trim()),
+        domain: domain,
+        display_name: display_name,
+        comments: comments,
+        route: route,
+    }
+}
+
+// Converts the text matched by `domain` into a Domain, or None if it's a
+// domain-literal that isn't a valid RFC 5321 address-literal
+fn typed_domain<I: U8Input>(buf: I::Buffer) -> Option<Domain> {
+    let bytes = buf.into_vec();
+    if bytes.first() == Some(&b'[') {
+        parse_only(address_literal, &bytes).ok()
+    } else {
+        // obs-domain allows CFWS around the dots
+        let name = strip_comments(&string_from_bytes(bytes));
+        Some(Domain::Name(name.split_whitespace().collect()))
+    }
 }
 
 #[test]
@@ -933,9 +1336,20 @@ fn test_mailbox() {
     let msg = parse_only(mailbox, i);
     assert!(msg.is_ok());
 
-    // let i = b"Mary Smith <@machine.tld:mary@example.net>";
-    // let msg = parse_only(mailbox, i);
-    // assert!(msg.is_ok());
+    let i = b"Mary Smith <@machine.tld:mary@example.net>";
+    let msg = parse_only(mailbox, i);
+    let expected = Address::Mailbox{
+        local_part: "mary".to_string(),
+        domain: Domain::Name("example.net".to_string()),
+        display_name: Some("Mary Smith ".to_string()),
+        comments: vec!(),
+        route: Some(vec!(Domain::Name("machine.tld".to_string()))),
+    };
+    assert_eq!(msg.unwrap(), expected);
+
+    let i = b"Mary Smith <@machine.tld:mary@example.net>";
+    let msg = parse_only(|i| mailbox_with(i, Profile::Strict), i);
+    assert!(msg.is_err());
 
     let i = b"jdoe@test   . example";
     let msg = parse_only(mailbox, i);
@@ -950,19 +1364,48 @@ fn test_mailbox() {
     assert!(msg.is_ok());
     let expected = Address::Mailbox{
         local_part: "john.q.public".to_string(),
-        domain: "example.com".to_string(),
+        domain: Domain::Name("example.com".to_string()),
         display_name: Some(" Joe Q. Public ".to_string()),
+        comments: vec!(),
+        route: None,
+    };
+    assert_eq!(msg.unwrap(), expected);
+
+    let i = b"jdoe@example.com (John Doe)";
+    let msg = parse_only(mailbox, i);
+    let expected = Address::Mailbox{
+        local_part: "jdoe".to_string(),
+        domain: Domain::Name("example.com".to_string()),
+        display_name: Some("John Doe".to_string()),
+        comments: vec!("John Doe".to_string()),
+        route: None,
     };
     assert_eq!(msg.unwrap(), expected);
+
+    let i = b"Pete(A wonderful \\) chap) <pete(his account)@silly.test(his host)>";
+    let msg = parse_only(mailbox, i);
+    match msg.unwrap() {
+        Address::Mailbox{display_name, comments, ..} => {
+            assert_eq!(display_name, Some("Pete ".to_string()));
+            assert_eq!(comments, vec!("A wonderful ) chap", "his account", "his host"));
+        },
+        _ => panic!("expected a mailbox"),
+    }
 }
 
 // name-addr       =   [display-name] angle-addr
 pub fn name_addr<I: U8Input>(i: I) -> SimpleResult<I, (Vec<I::Buffer>, I::Buffer, Option<Vec<I::Buffer>>)> {
-    option(i, |i| {
-        display_name(i).map(|n| Some(n))
-    }, None).bind(|i, n| {
-        angle_addr(i).bind(|i, (l, d)| {
-            i.ret((l, d, n))
+    name_addr_with(i, Profile::Lenient)
+}
+
+pub fn name_addr_with<I: U8Input>(i: I, profile: Profile) -> SimpleResult<I, (Vec<I::Buffer>, I::Buffer, Option<Vec<I::Buffer>>)> {
+    production(i, "name_addr", |i| {
+        option(i, |i| {
+            display_name_with(i, profile).map(|n| Some(n))
+        }, None).bind(|i, n| {
+            angle_addr_with(i, profile).bind(|i, (l, d)| {
+                i.ret((l, d, n))
+            })
         })
     })
 }
@@ -1037,14 +1480,20 @@ fn test_name_addr() {
 
 // angle-addr      =   [CFWS] "<" addr-spec ">" [CFWS] /
 //                     obs-angle-addr
-// NOTE: Not implementing obs-angle-addr because "routing" is bs
+// NOTE: obs-angle-addr is parsed by `mailbox_with`, which keeps the route
 pub fn angle_addr<I: U8Input>(i: I) -> SimpleResult<I, (Vec<I::Buffer>, I::Buffer)> {
-    option(i, drop_cfws, ()).then(|i| {
-        token(i, b'<').then(|i| {
-            addr_spec(i).bind(|i, (l, d)| {
-                token(i, b'>').then(|i| {
-                    option(i, drop_cfws, ()).then(|i| {
-                        i.ret((l, d))
+    angle_addr_with(i, Profile::Lenient)
+}
+
+pub fn angle_addr_with<I: U8Input>(i: I, profile: Profile) -> SimpleResult<I, (Vec<I::Buffer>, I::Buffer)> {
+    production(i, "angle_addr", |i| {
+        option(i, drop_cfws, ()).then(|i| {
+            token(i, b'<').then(|i| {
+                addr_spec_with(i, profile).bind(|i, (l, d)| {
+                    token(i, b'>').then(|i| {
+                        option(i, drop_cfws, ()).then(|i| {
+                            i.ret((l, d))
+                        })
                     })
                 })
             })
@@ -1069,23 +1518,29 @@ fn test_angle_addr() {
 
 // group           =   display-name ":" [group-list] ";" [CFWS]
 pub fn group<I: U8Input>(i: I) -> SimpleResult<I, Address> {
-    display_name(i).bind(|i, n| {
-        token(i, b':').then(|i| {
-            option(i, group_list, None).bind(|i, l| {
-                token(i, b';').then(|i| {
-                    option(i, drop_cfws, ()).then(|i| {
-                        let g = if l.is_some() {
-                            Address::Group{
-                                display_name: unchecked_string_from_bufs::<I>(n),
-                                mailboxes: l.unwrap(),
-                            }
-                        } else {
-                            Address::Group{
-                                display_name: unchecked_string_from_bufs::<I>(n),
-                                mailboxes: vec!(),
-                            }
-                        };
-                        i.ret(g)
+    group_with(i, Profile::Lenient)
+}
+
+pub fn group_with<I: U8Input>(i: I, profile: Profile) -> SimpleResult<I, Address> {
+    production(i, "group", |i| {
+        display_name_with(i, profile).bind(|i, n| {
+            token(i, b':').then(|i| {
+                option(i, |i| group_list_with(i, profile), None).bind(|i, l| {
+                    token(i, b';').then(|i| {
+                        option(i, drop_cfws, ()).then(|i| {
+                            let g = if l.is_some() {
+                                Address::Group{
+                                    display_name: unescape(&string_from_bufs::<I>(n)),
+                                    mailboxes: l.unwrap(),
+                                }
+                            } else {
+                                Address::Group{
+                                    display_name: unescape(&string_from_bufs::<I>(n)),
+                                    mailboxes: vec!(),
+                                }
+                            };
+                            i.ret(g)
+                        })
                     })
                 })
             })
@@ -1107,7 +1562,19 @@ fn test_group() {
 //
 // display-name    =   phrase
 pub fn display_name<I: U8Input>(i: I) -> SimpleResult<I, Vec<I::Buffer>> {
-    phrase(i)
+    display_name_with(i, Profile::Lenient)
+}
+
+// NOTE: `phrase` includes obs-phrase, so the strict profile only allows words
+pub fn display_name_with<I: U8Input>(i: I, profile: Profile) -> SimpleResult<I, Vec<I::Buffer>> {
+    production(i, "display_name", |i| {
+        match profile {
+            Profile::Strict => many1(i, word).map(|ws: Vec<Vec<I::Buffer>>| {
+                ws.into_iter().flat_map(|w| w.into_iter()).collect()
+            }),
+            _ => phrase(i),
+        }
+    })
 }
 
 #[test]
@@ -1150,18 +1617,28 @@ fn test_display_name() {
 
 // mailbox-list    =   (mailbox *("," mailbox)) / obs-mbox-list
 pub fn mailbox_list<I: U8Input>(i: I) -> SimpleResult<I, Vec<Address>> {
-    or(i,
-       |i| {
-           mailbox(i).bind(|i, mb1| {
-               many(i, |i| {
-                   token(i, b',').then(mailbox)
-               }).map(|mut mbs: Vec<Address>| {
-                   mbs.insert(0, mb1);
-                   mbs
-               })
-           })
-       },
-       obs_mbox_list)
+    mailbox_list_with(i, Profile::Lenient)
+}
+
+pub fn mailbox_list_with<I: U8Input>(i: I, profile: Profile) -> SimpleResult<I, Vec<Address>> {
+    production(i, "mailbox_list", |i| {
+        let new = |i: I| {
+            mailbox_with(i, profile).bind(|i, mb1| {
+                many(i, |i| {
+                    token(i, b',').then(|i| mailbox_with(i, profile))
+                }).map(|mut mbs: Vec<Address>| {
+                    mbs.insert(0, mb1);
+                    mbs
+                })
+            })
+        };
+        // NOTE: obs-mbox-list matches everything mailbox-list does, and trying it
+        // second would only ever parse the first mailbox of an obsolete list
+        match profile {
+            Profile::Strict => new(i),
+            _ => obs_mbox_list(i),
+        }
+    })
 }
 
 #[test]
@@ -1179,8 +1656,10 @@ fn test_mailbox_list() {
     assert!(msg.is_ok());
     let expected = Address::Mailbox{
         local_part: "john.q.public".to_string(),
-        domain: "example.com".to_string(),
+        domain: Domain::Name("example.com".to_string()),
         display_name: Some(" Joe Q. Public ".to_string()),
+        comments: vec!(),
+        route: None,
     };
     assert_eq!(msg.unwrap(), vec![expected]);
 
@@ -1195,18 +1674,28 @@ fn test_mailbox_list() {
 
 // address-list    =   (address *("," address)) / obs-addr-list
 pub fn address_list<I: U8Input>(i: I) -> SimpleResult<I, Vec<Address>> {
-    or(i,
-       |i| {
-           address(i).bind(|i, ad1| {
-               many(i, |i| {
-                   token(i, b',').then(address)
-               }).map(|mut ads: Vec<Address>| {
-                   ads.insert(0, ad1);
-                   ads
-               })
-           })
-       },
-       obs_addr_list)
+    address_list_with(i, Profile::Lenient)
+}
+
+pub fn address_list_with<I: U8Input>(i: I, profile: Profile) -> SimpleResult<I, Vec<Address>> {
+    production(i, "address_list", |i| {
+        let new = |i: I| {
+            address_with(i, profile).bind(|i, ad1| {
+                many(i, |i| {
+                    token(i, b',').then(|i| address_with(i, profile))
+                }).map(|mut ads: Vec<Address>| {
+                    ads.insert(0, ad1);
+                    ads
+                })
+            })
+        };
+        // NOTE: obs-addr-list matches everything address-list does, and trying it
+        // second would only ever parse the first address of an obsolete list
+        match profile {
+            Profile::Strict => new(i),
+            _ => obs_addr_list(i),
+        }
+    })
 }
 
 #[test]
@@ -1223,9 +1712,9 @@ fn test_address_list() {
     let msg = parse_only(address_list, i);
     assert!(msg.is_ok());
 
-    // let i = b"Mary Smith <@machine.tld:mary@example.net>, , jdoe@test   . example";
-    // let msg = parse_only(address_list, i);
-    // assert!(msg.is_ok());
+    let i = b"Mary Smith <@machine.tld:mary@example.net>, , jdoe@test   . example";
+    let msg = parse_only(address_list, i);
+    assert_eq!(msg.unwrap().len(), 2);
 
     let i = b"(Empty list)(start)Undisclosed recipients  :(nobody(that I know))  ;";
     let msg = parse_only(address_list, i);
@@ -1240,21 +1729,50 @@ fn test_address_list() {
     assert!(msg.is_ok());
 }
 
+#[test]
+fn test_address_list_with() {
+    let i = b"Mary Smith <mary@example.net>, \"Joe Q. Public\" <john.q.public@example.com>\r\n";
+    let msg = parse_only(|i| address_list_with(i, Profile::Strict).then(end_of_value), i);
+    assert!(msg.is_ok());
+
+    let i = b"Mary Smith <mary@example.net>, , jdoe@test   . example\r\n";
+    let msg = parse_only(|i| address_list_with(i, Profile::Strict).then(end_of_value), i);
+    assert!(msg.is_err());
+    let msg = parse_only(|i| address_list_with(i, Profile::Obsolete).then(end_of_value), i);
+    assert!(msg.is_ok());
+
+    let i = b"Joe Q. Public <john.q.public@example.com>\r\n";
+    let msg = parse_only(|i| address_list_with(i, Profile::Strict).then(end_of_value), i);
+    assert!(msg.is_err());
+}
+
 // group-list      =   mailbox-list / CFWS / obs-group-list
 // NOTE: Ignoring obs-group-list, as it appears to be wrong
 pub fn group_list<I: U8Input>(i: I) -> SimpleResult<I, Option<Vec<Address>>> {
-    or(i,
-       |i| mailbox_list(i).map(|v| Some(v)),
-       |i| cfws(i).map(|_| None))
+    group_list_with(i, Profile::Lenient)
+}
+
+pub fn group_list_with<I: U8Input>(i: I, profile: Profile) -> SimpleResult<I, Option<Vec<Address>>> {
+    production(i, "group_list", |i| {
+        or(i,
+           |i| mailbox_list_with(i, profile).map(|v| Some(v)),
+           |i| cfws(i).map(|_| None))
+    })
 }
 
 //
 // addr-spec       =   local-part "@" domain
 pub fn addr_spec<I: U8Input>(i: I) -> SimpleResult<I, (Vec<I::Buffer>, I::Buffer)> {
-    local_part(i).bind(|i, l| {
-        token(i, b'@').then(|i| {
-            domain(i).bind(|i, d| {
-                i.ret((l, d))
+    addr_spec_with(i, Profile::Lenient)
+}
+
+pub fn addr_spec_with<I: U8Input>(i: I, profile: Profile) -> SimpleResult<I, (Vec<I::Buffer>, I::Buffer)> {
+    production(i, "addr_spec", |i| {
+        local_part_with(i, profile).bind(|i, l| {
+            token(i, b'@').then(|i| {
+                domain_with(i, profile).bind(|i, d| {
+                    i.ret((l, d))
+                })
             })
         })
     })
@@ -1282,9 +1800,16 @@ fn test_addr_spec() {
 
 // local-part      =   dot-atom / quoted-string / obs-local-part
 pub fn local_part<I: U8Input>(i: I) -> SimpleResult<I, Vec<I::Buffer>> {
-    or(i,
-       dot_atom,
-       |i| or(i, quoted_string, obs_local_part))
+    local_part_with(i, Profile::Lenient)
+}
+
+pub fn local_part_with<I: U8Input>(i: I, profile: Profile) -> SimpleResult<I, Vec<I::Buffer>> {
+    production(i, "local_part", |i| {
+        match profile {
+            Profile::Strict => or(i, dot_atom, quoted_string),
+            _ => or(i, dot_atom, |i| or(i, quoted_string, obs_local_part)),
+        }
+    })
 }
 
 #[test]
@@ -1300,9 +1825,25 @@ fn test_local_part() {
 
 
 // domain          =   dot-atom / domain-literal / obs-domain
-// TODO: Support new fields
 pub fn domain<I: U8Input>(i: I) -> SimpleResult<I, I::Buffer> {
-    obs_domain(i)
+    domain_with(i, Profile::Lenient)
+}
+
+pub fn domain_with<I: U8Input>(i: I, profile: Profile) -> SimpleResult<I, I::Buffer> {
+    production(i, "domain", |i| {
+        match profile {
+            Profile::Strict => {
+                or(i, |i| {
+                    option(i, drop_cfws, ()).then(|i| {
+                        dot_atom_text(i).bind(|i, d| {
+                            option(i, drop_cfws, ()).then(|i| i.ret(d))
+                        })
+                    })
+                }, domain_literal)
+            },
+            _ => or(i, domain_literal, obs_domain),
+        }
+    })
 }
 
 #[test]
@@ -1311,6 +1852,10 @@ fn test_domain() {
     let msg = parse_only(domain, i);
     assert!(msg.is_ok());
 
+    let i = b" [192.0.2.1] ";
+    let msg = parse_only(domain, i);
+    assert_eq!(msg.unwrap(), b"[192.0.2.1]".as_ref());
+
     let i = b"silly.test(his host)";
     let msg = parse_only(domain, i);
     assert!(msg.is_ok());
@@ -1322,6 +1867,7 @@ fn test_domain() {
 
 // domain-literal  =   [CFWS] "[" *([FWS] dtext) [FWS] "]" [CFWS]
 //
+// Returns the literal including the brackets
 // dtext           =   %d33-90 /          ; Printable US-ASCII
 //                     %d94-126 /         ;  characters not including
 //                     obs-dtext          ;  "[", "]", or "\"
@@ -1356,17 +1902,161 @@ const DTEXT: [bool; 256] = [
     false, false, false, false, false, false, false, false, false, false, false, false, false, false, false, false, false, false, false, false, // 220 - 239
     false, false, false, false, false, false, false, false, false, false, false, false, false, false, false, false                              // 240 - 256
 ];
+//
+// dtext           =/  UTF8-non-ascii     ; RFC 6532
 pub fn dtext<I: U8Input>(i: I) -> SimpleResult<I, u8> {
     or(i,
-       |i| satisfy(i, |c| DTEXT[c as usize]),
+       |i| text_or_utf8(i, &DTEXT),
        quoted_pair)
 }
 
+pub fn domain_literal<I: U8Input>(i: I) -> SimpleResult<I, I::Buffer> {
+    production(i, "domain_literal", |i| {
+        option(i, drop_cfws, ()).then(|i| {
+            matched_by(i, |i| {
+                token(i, b'[').then(|i| {
+                    skip_many(i, |i| option(i, drop_fws, ()).then(dtext)).then(|i| {
+                        option(i, drop_fws, ()).then(|i| token(i, b']'))
+                    })
+                })
+            }).bind(|i, (buf, _)| {
+                option(i, drop_cfws, ()).then(|i| i.ret(buf))
+            })
+        })
+    })
+}
+
+// RFC 5321 section 4.1.3
+//
+// address-literal  = "[" ( IPv4-address-literal /
+//                    IPv6-address-literal /
+//                    General-address-literal ) "]"
+//
+// NOTE: Trying IPv6 first since "IPv6" would also match Standardized-tag
+pub fn address_literal<I: U8Input>(i: I) -> SimpleResult<I, Domain> {
+    token(i, b'[').then(|i| {
+        or(i, ipv6_address_literal,
+        |i| or(i, ipv4_address_literal,
+            general_address_literal)).bind(|i, d| {
+            token(i, b']').then(|i| i.ret(d))
+        })
+    })
+}
+
+// IPv4-address-literal  = Snum 3("."  Snum)
+//
+// Snum           = 1*3DIGIT
+//                ; representing a decimal integer
+//                ; value in the range 0 through 255
+pub fn ipv4_address_literal<I: U8Input>(i: I) -> SimpleResult<I, Domain> {
+    parse_digits(i, (1..4)).bind(|i, a: u8| {
+        count(i, 3, |i| {
+            token(i, b'.').then(|i| parse_digits(i, (1..4)))
+        }).bind(|i, rest: Vec<u8>| {
+            i.ret(Domain::Ipv4(Ipv4Addr::new(a, rest[0], rest[1], rest[2])))
+        })
+    })
+}
+
+// IPv6-address-literal  = "IPv6:" IPv6-addr
+//
+// NOTE: Leaving the forms of IPv6-addr to the standard library, which accepts
+// the full, compressed and IPv4-embedded forms
+pub fn ipv6_address_literal<I: U8Input>(i: I) -> SimpleResult<I, Domain> {
+    downcased_string(i, b"IPv6:").then(|i| {
+        take_while1(i, |c| (c as char).is_digit(16) || c == b':' || c == b'.').bind(|i, buf| {
+            let addr = String::from_utf8_lossy(&buf.into_vec()).parse::<Ipv6Addr>();
+            match addr {
+                Ok(addr) => i.ret(Domain::Ipv6(addr)),
+                Err(_) => i.err(Error::unexpected()),
+            }
+        })
+    })
+}
+
+// General-address-literal  = Standardized-tag ":" 1*dcontent
+//
+// Standardized-tag  = Ldh-str
+//                   ; Standardized-tag MUST be specified in a
+//                   ; Standards-Track RFC and registered with IANA
+//
+// dcontent       = %d33-90 / ; Printable US-ASCII
+//                %d94-126 ; excl. "[", "\\", "]"
+//
+// Ldh-str        = *( ALPHA / DIGIT / "-" ) Let-dig
+//
+// NOTE: Not checking the tag against the IANA registry, but rejecting "IPv6"
+// so that a malformed IPv6-address-literal isn't taken for a general one
+pub fn general_address_literal<I: U8Input>(i: I) -> SimpleResult<I, Domain> {
+    take_while1(i, |c| ascii::is_alphanumeric(c) || c == b'-').bind(|i, tag| {
+        token(i, b':').then(|i| {
+            take_while1(i, |c| (33 <= c && c <= 90) || (94 <= c && c <= 126)).bind(|i, content| {
+                let tag = tag.into_vec();
+                if tag.last() == Some(&b'-') || tag.eq_ignore_ascii_case(b"IPv6") {
+                    return i.err(Error::unexpected())
+                }
+                i.ret(Domain::General{
+                    tag: unsafe { String::from_utf8_unchecked(tag) },
+                    content: unsafe { String::from_utf8_unchecked(content.into_vec()) },
+                })
+            })
+        })
+    })
+}
+
+#[test]
+fn test_address_literal() {
+    let i = b"[192.0.2.1]";
+    let msg = parse_only(address_literal, i);
+    assert_eq!(msg.unwrap(), Domain::Ipv4(Ipv4Addr::new(192, 0, 2, 1)));
+
+    let i = b"[IPv6:2001:db8::1]";
+    let msg = parse_only(address_literal, i);
+    assert_eq!(msg.unwrap(), Domain::Ipv6("2001:db8::1".parse().unwrap()));
+
+    let i = b"[x-tag:some-content]";
+    let msg = parse_only(address_literal, i);
+    assert_eq!(msg.unwrap(), Domain::General{ tag: "x-tag".to_string(), content: "some-content".to_string() });
+
+    let i = b"[256.0.2.1]";
+    let msg = parse_only(address_literal, i);
+    assert!(msg.is_err());
+
+    let i = b"[IPv6:2001:db8:::1]";
+    let msg = parse_only(address_literal, i);
+    assert!(msg.is_err());
+
+    let i = b"jdoe@[192.0.2.1]";
+    let msg = parse_only(mailbox, i);
+    assert_eq!(msg.unwrap(), Address::Mailbox{
+        local_part: "jdoe".to_string(),
+        domain: Domain::Ipv4(Ipv4Addr::new(192, 0, 2, 1)),
+        display_name: None,
+        comments: vec!(),
+        route: None,
+    });
+
+    let i = b"<jdoe@[not an address]>";
+    let msg = parse_only(mailbox, i);
+    assert!(msg.is_err());
+}
+
 // message         =   (fields / obs-fields)
 //                     [CRLF body]
 // TODO: Support new fields
 pub fn message<I: U8Input>(i: I) -> SimpleResult<I, Message<I>> {
-    raw_fields(i).bind(|i, f| {
+    message_with(i, Profile::Lenient)
+}
+
+// Parses a message whose field values are interpreted using `options`
+pub fn message_with<I: U8Input, O: Into<ParseOptions>>(i: I, options: O) -> SimpleResult<I, Message<I>> {
+    let options = options.into();
+    many(i, |i| matched_by(i, raw_field)).bind(|i, raw: Vec<(I::Buffer, Field<I>)>| {
+        let obsolete_names = raw.iter().enumerate()
+            .filter(|&(_, &(ref buf, _))| obsolete_field_name(buf))
+            .map(|(n, _)| n)
+            .collect();
+        let f = raw.into_iter().map(|(_, field)| field).collect();
         option(i, |i| {
             crlf(i).then(|i| {
                 body(i).map(|b| Some(b))
@@ -1375,6 +2065,9 @@ pub fn message<I: U8Input>(i: I) -> SimpleResult<I, Message<I>> {
             let message = Message {
                 fields: f,
                 body: b,
+                profile: options.profile,
+                utf8: options.utf8,
+                obsolete_names: obsolete_names,
             };
             debug!("parsed message");
 
@@ -1419,8 +2112,10 @@ const TEXT: [bool; 256] = [
     false, false, false, false, false, false, false, false, false, false, false, false, false, false, false, false, false, false, false, false, // 220 - 239
     false, false, false, false, false, false, false, false, false, false, false, false, false, false, false, false                              // 240 - 256
 ];
+//
+// text            =/  UTF8-non-ascii     ; RFC 6532
 pub fn text<I: U8Input>(i: I) -> SimpleResult<I, u8> {
-    satisfy(i, |c| TEXT[c as usize])
+    text_or_utf8(i, &TEXT)
 }
 
 // fields          =   *(trace
@@ -1489,8 +2184,11 @@ pub fn text<I: U8Input>(i: I) -> SimpleResult<I, u8> {
 // message = (fields / obs-fields).  Since I'm lazy and don't feel like 
 // implementing all the new parsers, I'm going to just mix them up in here
 pub fn raw_fields<I: U8Input>(i: I) -> SimpleResult<I, Vec<Field<I>>> {
-    many(i, |i| {
-        or(i,       raw_received,
+    many(i, raw_field)
+}
+
+pub fn raw_field<I: U8Input>(i: I) -> SimpleResult<I, Field<I>> {
+    or(i,       raw_received,
         |i| or(i,   raw_obs_orig_date,
         |i| or(i,   raw_obs_from,
         |i| or(i,   raw_obs_sender,
@@ -1502,16 +2200,38 @@ pub fn raw_fields<I: U8Input>(i: I) -> SimpleResult<I, Vec<Field<I>>> {
         |i| or(i,   raw_obs_references,
         |i| or(i,   raw_obs_subject,
         |i| or(i,   raw_obs_comments,
+        |i| or(i,   raw_obs_keywords,
+        |i| or(i,   raw_obs_return,
         |i| or(i,   raw_obs_resent_from,
         |i| or(i,   raw_obs_resent_send,
         |i| or(i,   raw_obs_resent_date,
         |i| or(i,   raw_obs_resent_to,
         |i| or(i,   raw_obs_resent_cc,
+        |i| or(i,   raw_obs_resent_bcc,
         |i| or(i,   raw_obs_resent_mid,
         |i| or(i,   raw_obs_resent_rply,
+        |i| or(i,   raw_list_fields,
+        |i| or(i,   raw_authentication_results,
+        |i| or(i,   raw_dkim_signature,
                raw_obs_optional,
-                    )))))))))))))))))))
-    })
+                    )))))))))))))))))))))))))
+}
+
+// Returns true if a field, as matched by `raw_field`, has WSP between its
+// name and the colon
+fn obsolete_field_name<B: Buffer<Token=u8>>(field: &B) -> bool {
+    let mut last = None;
+    let mut in_name = true;
+    field.iterate(|c| {
+        if in_name {
+            if c == b':' {
+                in_name = false;
+            } else {
+                last = Some(c);
+            }
+        }
+    });
+    last == Some(b' ') || last == Some(b'\t')
 }
 
 // orig-date       =   "Date:" date-time CRLF
@@ -1536,29 +2256,39 @@ pub fn raw_fields<I: U8Input>(i: I) -> SimpleResult<I, Vec<Field<I>>> {
 
 ///! msg-id          =   [CFWS] "<" id-left "@" id-right ">" [CFWS]
 ///!
-///! NOTE: Allowing the omission of ``id-left "@"`` to accomodate message IDs 
-///! formed like "<comm-tagged-1077147628989448>"
+///! NOTE: The lenient profile allows the omission of ``id-left "@"`` to
+///! accomodate message IDs formed like "<comm-tagged-1077147628989448>"
 ///! msg-id          =   [CFWS] "<" ?(id-left "@") id-right ">" [CFWS]
 pub fn msg_id<I: U8Input>(i: I) -> SimpleResult<I, MessageID> {
-    option(i, drop_cfws, ()).then(|i| {
-        token(i, b'<').then(|i| {
-            option(i, |i| {
-                id_left(i).bind(|i, l| {
-                    token(i, b'@').then(|i| {
-                        i.ret(Some(unchecked_string_from_bufs::<I>(l)))
-                    })
-                })
-            }, None).bind(|i, l| {
-                id_right(i).bind(|i, r| {
-                    token(i, b'>').then(|i| {
-                        option(i, drop_cfws, ()).then(|i| {
-                            let message_id = MessageID{
-                                id_left: l,
-                                id_right: unsafe { String::from_utf8_unchecked(r.into_vec()) },
-                            };
-                            debug!("parsed msg-id");
+    msg_id_with(i, Profile::Lenient)
+}
 
-                            i.ret(message_id)
+pub fn msg_id_with<I: U8Input>(i: I, profile: Profile) -> SimpleResult<I, MessageID> {
+    production(i, "msg_id", |i| {
+        let left = |i: I| {
+            id_left_with(i, profile).bind(|i, l| {
+                token(i, b'@').then(|i| {
+                    i.ret(Some(string_from_bufs::<I>(l)))
+                })
+            })
+        };
+        option(i, drop_cfws, ()).then(|i| {
+            token(i, b'<').then(|i| {
+                match profile {
+                    Profile::Lenient => option(i, left, None),
+                    _ => left(i),
+                }.bind(|i, l| {
+                    id_right_with(i, profile).bind(|i, r| {
+                        token(i, b'>').then(|i| {
+                            option(i, drop_cfws, ()).then(|i| {
+                                let message_id = MessageID{
+                                    id_left: l,
+                                    id_right: string_from_bytes(r.into_vec()),
+                                };
+                                debug!("parsed msg-id");
+
+                                i.ret(message_id)
+                            })
                         })
                     })
                 })
@@ -1567,31 +2297,58 @@ pub fn msg_id<I: U8Input>(i: I) -> SimpleResult<I, MessageID> {
     })
 }
 
+#[test]
+fn test_msg_id_with() {
+    let i = b"<1234@local.machine.example>";
+    let msg = parse_only(|i| msg_id_with(i, Profile::Strict), i);
+    assert!(msg.is_ok());
+
+    let i = b"<comm-tagged-1077147628989448>";
+    let msg = parse_only(|i| msg_id_with(i, Profile::Obsolete), i);
+    assert!(msg.is_err());
+    let msg = parse_only(|i| msg_id_with(i, Profile::Lenient), i);
+    assert_eq!(msg.unwrap(), MessageID{ id_left: None, id_right: "comm-tagged-1077147628989448".to_string() });
+}
+
 // id-left         =   dot-atom-text / obs-id-left
 pub fn id_left<I: U8Input>(i: I) -> SimpleResult<I, Vec<I::Buffer>> {
-    or(i, 
-       |i| dot_atom_text(i).map(|buf| vec!(buf)), 
-       obs_id_left)
+    id_left_with(i, Profile::Lenient)
+}
+
+pub fn id_left_with<I: U8Input>(i: I, profile: Profile) -> SimpleResult<I, Vec<I::Buffer>> {
+    production(i, "id_left", |i| {
+        let new = |i: I| dot_atom_text(i).map(|buf| vec!(buf));
+        match profile {
+            Profile::Strict => new(i),
+            _ => or(i, new, obs_id_left),
+        }
+    })
 }
 
 // id-right        =   dot-atom-text / no-fold-literal / obs-id-right
 pub fn id_right<I: U8Input>(i: I) -> SimpleResult<I, I::Buffer> {
-    or(i, 
-       dot_atom_text,
-       |i| or(i, no_fold_literal, obs_id_right))
+    id_right_with(i, Profile::Lenient)
+}
+
+pub fn id_right_with<I: U8Input>(i: I, profile: Profile) -> SimpleResult<I, I::Buffer> {
+    production(i, "id_right", |i| {
+        match profile {
+            Profile::Strict => or(i, dot_atom_text, no_fold_literal),
+            _ => or(i, dot_atom_text, |i| or(i, no_fold_literal, obs_id_right)),
+        }
+    })
 }
 
 // no-fold-literal =   "[" *dtext "]"
+// NOTE: Keeping the brackets, so a literal isn't mistaken for a dot-atom-text
 pub fn no_fold_literal<I: U8Input>(i: I) -> SimpleResult<I, I::Buffer> {
-    token(i, b'[').then(|i| {
-        matched_by(i, |i| {
-            skip_many(i, dtext)
-        }).bind(|i, (buf, _)| {
-            token(i, b']').then(|i| {
-                i.ret(buf)
+    matched_by(i, |i| {
+        token(i, b'[').then(|i| {
+            skip_many(i, dtext).then(|i| {
+                token(i, b']')
             })
         })
-    })
+    }).map(|(buf, _)| buf)
 }
 
 pub fn drop_field_name<I: U8Input>(i: I, name: &[u8]) -> SimpleResult<I, ()> {
@@ -1646,7 +2403,44 @@ pub fn till_crlf<I: U8Input>(mut i: I) -> SimpleResult<I, I::Buffer> {
 // comments        =   "Comments:" unstructured CRLF
 //
 // keywords        =   "Keywords:" phrase *("," phrase) CRLF
-//
+pub fn keywords<I: U8Input>(i: I) -> SimpleResult<I, Vec<String>> {
+    keywords_with(i, Profile::Lenient)
+}
+
+pub fn keywords_with<I: U8Input>(i: I, profile: Profile) -> SimpleResult<I, Vec<String>> {
+    let new = |i: I| {
+        phrase(i).bind(|i, p1| {
+            many(i, |i| {
+                token(i, b',').then(phrase)
+            }).map(|mut ps: Vec<Vec<I::Buffer>>| {
+                ps.insert(0, p1);
+                ps
+            })
+        })
+    };
+    let to_strings = |ps: Vec<Vec<I::Buffer>>| {
+        ps.into_iter().map(|p| unescape(string_from_bufs::<I>(p).trim())).collect()
+    };
+    match profile {
+        Profile::Strict => new(i).map(to_strings),
+        _ => obs_phrase_list(i).map(to_strings),
+    }
+}
+
+#[test]
+fn test_keywords() {
+    let i = b" mail, \"parser combinators\" (chomp), rust";
+    let msg = parse_only(|i| keywords_with(i, Profile::Strict), i);
+    assert_eq!(msg.unwrap(), vec!("mail", "parser combinators", "rust"));
+
+    let i = b" , mail,, rust";
+    assert!(parse_only(|i| keywords_with(i, Profile::Strict), i).is_err());
+    assert_eq!(parse_only(keywords, i).unwrap(), vec!("mail", "rust"));
+
+    let i = b" \"release (beta)\", mail";
+    assert_eq!(parse_only(keywords, i).unwrap(), vec!("release (beta)", "mail"));
+}
+
 // resent-date     =   "Resent-Date:" date-time CRLF
 //
 // resent-from     =   "Resent-From:" mailbox-list CRLF
@@ -1658,7 +2452,28 @@ pub fn till_crlf<I: U8Input>(mut i: I) -> SimpleResult<I, I::Buffer> {
 // resent-cc       =   "Resent-Cc:" address-list CRLF
 //
 // resent-bcc      =   "Resent-Bcc:" [address-list / CFWS] CRLF
-//
+// obs-resent-bcc  =   "Resent-Bcc" *WSP ":"
+//                     (address-list / (*([CFWS] ",") [CFWS])) CRLF
+// NOTE: The same holds for bcc and obs-bcc
+pub fn bcc_list_with<I: U8Input>(i: I, profile: Profile) -> SimpleResult<I, Vec<Address>> {
+    or(i,
+       |i| address_list_with(i, profile),
+       |i| {
+           let commas = |i: I| match profile {
+               Profile::Strict => i.ret(()),
+               _ => skip_many(i, |i| option(i, drop_cfws, ()).then(|i| token(i, b','))),
+           };
+           commas(i).then(|i| option(i, drop_cfws, ())).map(|_| vec!())
+       })
+}
+
+#[test]
+fn test_bcc_list() {
+    assert_eq!(parse_only(|i| bcc_list_with(i, Profile::Strict), b" (hidden) ").unwrap(), vec!());
+    assert_eq!(parse_only(|i| bcc_list_with(i, Profile::Strict), b"jdoe@example.com").unwrap().len(), 1);
+    assert_eq!(parse_only(|i| bcc_list_with(i, Profile::Obsolete), b" , ,").unwrap(), vec!());
+}
+
 // resent-msg-id   =   "Resent-Message-ID:" msg-id CRLF
 //
 // trace           =   [return]
@@ -1667,8 +2482,87 @@ pub fn till_crlf<I: U8Input>(mut i: I) -> SimpleResult<I, I::Buffer> {
 // return          =   "Return-Path:" path CRLF
 //
 // path            =   angle-addr / ([CFWS] "<" [CFWS] ">" [CFWS])
+// NOTE: The null path "<>", used by bounces, is None. Other profiles accept
+// an obs-angle-addr or a mailbox, which some servers write.
+pub fn path<I: U8Input>(i: I) -> SimpleResult<I, Option<Address>> {
+    path_with(i, Profile::Lenient)
+}
+
+pub fn path_with<I: U8Input>(i: I, profile: Profile) -> SimpleResult<I, Option<Address>> {
+    let null_path = |i: I| {
+        option(i, drop_cfws, ()).then(|i| {
+            token(i, b'<').then(|i| {
+                option(i, drop_cfws, ()).then(|i| {
+                    token(i, b'>').then(|i| {
+                        option(i, drop_cfws, ()).map(|_| None)
+                    })
+                })
+            })
+        })
+    };
+    let angle_addr = |i: I| {
+        matched_by(i, |i| angle_addr_with(i, profile)).bind(|i, (buf, (local_part, domain))| {
+            match typed_domain::<I>(domain) {
+                Some(d) => i.ret(Some(new_mailbox::<I>(buf, local_part, d, None, None))),
+                None => i.err(Error::unexpected()),
+            }
+        })
+    };
+    match profile {
+        Profile::Strict => or(i, null_path, angle_addr),
+        _ => or(i, null_path, |i| mailbox_with(i, profile).map(|m| Some(m))),
+    }
+}
+
+#[test]
+fn test_path() {
+    let i = b" <> ";
+    assert_eq!(parse_only(|i| path_with(i, Profile::Strict), i).unwrap(), None);
+
+    let i = b"<jdoe@machine.example>";
+    let msg = parse_only(|i| path_with(i, Profile::Strict), i).unwrap();
+    assert_eq!(msg.unwrap().to_string(), "jdoe@machine.example");
+
+    let i = b"<@relay.example:jdoe@machine.example>";
+    assert!(parse_only(|i| path_with(i, Profile::Strict), i).is_err());
+    assert!(parse_only(path, i).unwrap().is_some());
+}
 
 // received        =   "Received:" *received-token ";" date-time CRLF
+pub fn received<I: U8Input>(i: I) -> SimpleResult<I, Received> {
+    received_with(i, Profile::Lenient)
+}
+
+// obs-received    =   "Received" *WSP ":" *received-token CRLF
+// NOTE: Other profiles make the date optional, as obs-received does
+pub fn received_with<I: U8Input>(i: I, profile: Profile) -> SimpleResult<I, Received> {
+    let date = |i: I| {
+        token(i, b';').then(|i| date_time_with(i, profile))
+    };
+    many(i, received_token).bind(|i, tokens: Vec<Vec<I::Buffer>>| {
+        let tokens = tokens.into_iter()
+            .map(|t| strip_comments(&string_from_bufs::<I>(t)).trim().to_string())
+            .filter(|t| !t.is_empty())
+            .collect();
+        match profile {
+            Profile::Strict => date(i).map(|d| Received{ tokens: tokens, date_time: Some(d) }),
+            _ => option(i, |i| date(i).map(|d| Some(d)), None).map(|d| Received{ tokens: tokens, date_time: d }),
+        }
+    })
+}
+
+#[test]
+fn test_received() {
+    let i = b" from x.y.test\x0d\x0a   by example.net (Postfix)\x0d\x0a   via TCP\x0d\x0a   with ESMTP\x0d\x0a   id ABC12345\x0d\x0a   for <mary@example.net>;  21 Nov 1997 10:05:43 -0600";
+    let msg = parse_only(|i| received_with(i, Profile::Strict), i).unwrap();
+    assert_eq!(msg.tokens, vec!("from", "x.y.test", "by", "example.net", "via", "TCP", "with", "ESMTP", "id", "ABC12345", "for", "<mary@example.net>"));
+    assert_eq!(msg.date_time.unwrap().to_rfc2822(), "Fri, 21 Nov 1997 10:05:43 -0600");
+
+    let i = b" from machine.example by x.y.test";
+    assert!(parse_only(|i| received_with(i, Profile::Strict), i).is_err());
+    assert_eq!(parse_only(received, i).unwrap().date_time, None);
+}
+
 // NOTE: Accepting case-insensitive header name values
 pub fn raw_received<I: U8Input>(i: I) -> SimpleResult<I, Field<I>> {
     drop_field_name(i, b"Received").then(|i| {
@@ -2073,41 +2967,159 @@ pub fn obs_second<I: U8Input>(i: I) -> SimpleResult<I, usize> {
 //    PDT is semantically equivalent to -0700
 //    PST is semantically equivalent to -0800
 //    
-// NOTE: Modifying to allow preceeding FWS, adding 'UTC'
+// NOTE: Modifying to allow preceeding FWS. The whole name is read before
+// it's interpreted, so "CEST" isn't taken as the military zone "C". The
+// lenient profile also accepts the names in `zones`, and reads any other
+// alphabetic name as UTC.
 pub fn obs_zone<I: U8Input>(i: I) -> SimpleResult<I, FixedOffset> {
+    obs_zone_with(i, Profile::Lenient, ZoneTable::default())
+}
+
+pub fn obs_zone_with<I: U8Input>(i: I, profile: Profile, zones: ZoneTable) -> SimpleResult<I, FixedOffset> {
+    obs_local_zone_with(i, profile, zones).map(|(o, _)| o)
+}
+
+// Parses an obs-zone, along with whether it gives the sender's local offset.
+// Military zones don't unless `zones.military` is set, and names the lenient
+// profile doesn't know never do.
+pub fn obs_local_zone_with<I: U8Input>(i: I, profile: Profile, zones: ZoneTable) -> SimpleResult<I, (FixedOffset, LocalZone)> {
     drop_fws(i).then(|i| {
-        or(i, |i| string(i, b"UT").then(|i| i.ret(0)),
-        |i| or(i, |i| string(i, b"GMT").then(|i| i.ret(0)),
-        |i| or(i, |i| string(i, b"UTC").then(|i| i.ret(0)),
-        |i| or(i, |i| string(i, b"EST").then(|i| i.ret(-5)),
-        |i| or(i, |i| string(i, b"EDT").then(|i| i.ret(-4)),
-        |i| or(i, |i| string(i, b"CST").then(|i| i.ret(-6)),
-        |i| or(i, |i| string(i, b"CDT").then(|i| i.ret(-5)),
-        |i| or(i, |i| string(i, b"MST").then(|i| i.ret(-7)),
-        |i| or(i, |i| string(i, b"MDT").then(|i| i.ret(-6)),
-        |i| or(i, |i| string(i, b"PST").then(|i| i.ret(-8)),
-        |i| or(i, |i| string(i, b"PDT").then(|i| i.ret(-7)),
-        |i| or(i, |i| satisfy(i, |i| 65 <= i && i <= 73).then(|i| i.ret(0)),
-        |i| or(i, |i| satisfy(i, |i| 75 <= i && i <= 90).then(|i| i.ret(0)),
-        |i| or(i, |i| satisfy(i, |i| 97 <= i && i <= 105).then(|i| i.ret(0)),
-        |i| or(i, |i| satisfy(i, |i| 107 <= i && i <= 122).then(|i| i.ret(0)),
-        |i| skip_many1(i, alpha).then(|i| i.ret(0)),
-        ))))))))))))))).map(|o| FixedOffset::west(o))
+        take_while1(i, |c| ALPHA[c as usize]).bind(|i, buf| {
+            let name = String::from_utf8_lossy(&buf.into_vec()).to_uppercase();
+            let known = match obs_zone_offset(&name, zones) {
+                Some(_) => name.len() > 1 || zones.military,
+                None => table_zone_offset(&name, zones).is_some(),
+            };
+            let zone = if known { LocalZone::Known } else { LocalZone::Unknown };
+            match zone_offset(&name, profile, zones) {
+                Some(minutes) => i.ret((FixedOffset::east(minutes * 60), zone)),
+                None => i.err(Error::unexpected()),
+            }
+        })
     })
 }
 
+// Returns the offset east of UTC in minutes of an upper case zone name
+fn zone_offset(name: &str, profile: Profile, zones: ZoneTable) -> Option<i32> {
+    match obs_zone_offset(name, zones) {
+        Some(minutes) => Some(minutes),
+        None if profile == Profile::Lenient => Some(table_zone_offset(name, zones).unwrap_or(0)),
+        None => None,
+    }
+}
+
+// Returns the offset east of UTC in minutes of an upper case obs-zone name
+pub fn obs_zone_offset(name: &str, zones: ZoneTable) -> Option<i32> {
+    let hours = match name {
+        "UT" | "GMT" => Some(0),
+        "EST" => Some(-5),
+        "EDT" => Some(-4),
+        "CST" => Some(-6),
+        "CDT" => Some(-5),
+        "MST" => Some(-7),
+        "MDT" => Some(-6),
+        "PST" => Some(-8),
+        "PDT" => Some(-7),
+        _ => None,
+    };
+    if let Some(h) = hours {
+        return Some(h * 60)
+    }
+
+    let bytes = name.as_bytes();
+    if bytes.len() == 1 && bytes[0] >= b'A' && bytes[0] <= b'Z' && bytes[0] != b'J' {
+        let c = bytes[0];
+        let hours = if !zones.military || c == b'Z' {
+            0
+        } else if c < b'J' {
+            (c - b'A') as i32 + 1
+        } else if c < b'N' {
+            (c - b'K') as i32 + 10
+        } else {
+            -((c - b'N') as i32 + 1)
+        };
+        return Some(hours * 60)
+    }
+    None
+}
+
+// Returns the offset east of UTC in minutes of a zone name in `zones`
+pub fn table_zone_offset(name: &str, zones: ZoneTable) -> Option<i32> {
+    zones.names.iter()
+        .find(|&&(n, _)| n.eq_ignore_ascii_case(name))
+        .map(|&(_, minutes)| minutes)
+}
+
 #[test]
 fn test_obs_zone() {
     let i = b"-0330 (Newfoundland Time)\r\n";
     let msg = parse_only(obs_zone, i);
     assert!(msg.is_err());
+
+    let cases: Vec<(&[u8], i32)> = vec!(
+        (b" EST", -5 * 3600),
+        (b" pdt", -7 * 3600),
+        (b" A", 3600),
+        (b" M", 12 * 3600),
+        (b" N", -3600),
+        (b" Z", 0),
+        (b" CEST", 2 * 3600),
+        (b" IST", 5 * 3600 + 1800),
+        (b" Nowhere", 0),
+    );
+    for (i, offset) in cases.into_iter() {
+        let msg = parse_only(obs_zone, i);
+        assert_eq!(msg.unwrap(), FixedOffset::east(offset));
+    }
+
+    let msg = parse_only(|i| obs_zone_with(i, Profile::Obsolete, ZoneTable::default()), b" CEST");
+    assert!(msg.is_err());
+    let msg = parse_only(|i| obs_zone_with(i, Profile::Obsolete, ZoneTable::default()), b" J");
+    assert!(msg.is_err());
+
+    let zones = ZoneTable{ names: &[("IST", 60)], military: false };
+    let msg = parse_only(|i| obs_zone_with(i, Profile::Lenient, zones), b" IST");
+    assert_eq!(msg.unwrap(), FixedOffset::east(3600));
+    let msg = parse_only(|i| obs_zone_with(i, Profile::Lenient, zones), b" A");
+    assert_eq!(msg.unwrap(), FixedOffset::east(0));
 }
 
 // obs-angle-addr  =   [CFWS] "<" obs-route addr-spec ">" [CFWS]
-// NOTE: Not supporting because obs-route is stupid
+pub fn obs_angle_addr<I: U8Input>(i: I) -> SimpleResult<I, (Vec<I::Buffer>, Vec<I::Buffer>, I::Buffer)> {
+    production(i, "obs_angle_addr", |i| {
+        option(i, drop_cfws, ()).then(|i| {
+            token(i, b'<').then(|i| {
+                obs_route(i).bind(|i, route| {
+                    addr_spec(i).bind(|i, (l, d)| {
+                        token(i, b'>').then(|i| {
+                            option(i, drop_cfws, ()).then(|i| {
+                                i.ret((route, l, d))
+                            })
+                        })
+                    })
+                })
+            })
+        })
+    })
+}
+
+#[test]
+fn test_obs_angle_addr() {
+    let i = b"<@relay1,@relay2:user@host>";
+    let msg = parse_only(obs_angle_addr, i);
+    assert_eq!(msg.unwrap(), (vec!(b"relay1".as_ref(), b"relay2".as_ref()), vec!(b"user".as_ref()), b"host".as_ref()));
+
+    let i = b"<user@host>";
+    let msg = parse_only(obs_angle_addr, i);
+    assert!(msg.is_err());
+}
 
 // obs-route       =   obs-domain-list ":"
-// NOTE: Not supporting because why, even?
+pub fn obs_route<I: U8Input>(i: I) -> SimpleResult<I, Vec<I::Buffer>> {
+    obs_domain_list(i).bind(|i, domains| {
+        token(i, b':').then(|i| i.ret(domains))
+    })
+}
 
 // obs-domain-list =   *(CFWS / ",") "@" domain
 //                     *("," [CFWS] ["@" domain])
@@ -2348,13 +3360,36 @@ fn test_raw_obs_from() {
             let act = f.addresses();
             let exp = vec!(Address::Mailbox{
                 local_part: "john.q.public".to_string(),
-                domain: "example.com".to_string(),
+                domain: Domain::Name("example.com".to_string()),
                 display_name: Some(" Joe Q. Public ".to_string()),
+                comments: vec!(),
+                route: None,
             });
             assert_eq!(act.unwrap(), exp);
         },
         _ => assert!(false),
     }
+
+    let i = b"From: John Doe <jdoe@machine.example\x0d\x0a";
+    let msg = parse_only(raw_obs_from, i);
+    assert!(msg.is_ok());
+    let inner_msg = msg.unwrap();
+    assert!(inner_msg.is_malformed());
+    match inner_msg {
+        Field::From(f) => {
+            let act = f.addresses();
+            let err = act.error().unwrap();
+            assert_eq!(err.production, "angle_addr");
+            assert_eq!((err.offset, err.expected), (31, Some(b'>')));
+        },
+        _ => assert!(false),
+    }
+
+    let i = b"From: \"John Doe <jdoe@machine.example>\x0d\x0a";
+    match parse_only(raw_obs_from, i).unwrap() {
+        Field::From(f) => assert_eq!(f.addresses().error().unwrap().production, "quoted_string"),
+        _ => assert!(false),
+    }
 }
 
 // obs-sender      =   "Sender" *WSP ":" mailbox CRLF
@@ -2525,6 +3560,16 @@ pub fn raw_obs_comments<I: U8Input>(i: I) -> SimpleResult<I, Field<I>> {
 }
 
 // obs-keywords    =   "Keywords" *WSP ":" obs-phrase-list CRLF
+// NOTE: Accepting case-insensitive header name values
+pub fn raw_obs_keywords<I: U8Input>(i: I) -> SimpleResult<I, Field<I>> {
+    drop_field_name(i, b"Keywords").then(|i| {
+        till_crlf(i).bind(|i, v| {
+            let value = KeywordsField {data: v};
+
+            i.ret(Field::Keywords(value))
+        })
+    })
+}
 
 // obs-resent-from =   "Resent-From" *WSP ":" mailbox-list CRLF
 // NOTE: Accepting case-insensitive header name values
@@ -2588,7 +3633,7 @@ pub fn raw_obs_resent_cc<I: U8Input>(i: I) -> SimpleResult<I, Field<I>> {
 
 // obs-resent-bcc  =   "Resent-Bcc" *WSP ":"
 //                     (address-list / (*([CFWS] ",") [CFWS])) CRLF
-/*
+// NOTE: Accepting case-insensitive header name values
 pub fn raw_obs_resent_bcc<I: U8Input>(i: I) -> SimpleResult<I, Field<I>> {
     drop_field_name(i, b"Resent-Bcc").then(|i| {
         till_crlf(i).bind(|i, v| {
@@ -2598,7 +3643,6 @@ pub fn raw_obs_resent_bcc<I: U8Input>(i: I) -> SimpleResult<I, Field<I>> {
         })
     })
 }
-*/
 
 // obs-resent-mid  =   "Resent-Message-ID" *WSP ":" msg-id CRLF
 // NOTE: Accepting case-insensitive header name values
@@ -2625,7 +3669,25 @@ pub fn raw_obs_resent_rply<I: U8Input>(i: I) -> SimpleResult<I, Field<I>> {
 }
 
 // obs-return      =   "Return-Path" *WSP ":" path CRLF
-//
+// NOTE: Accepting case-insensitive header name values
+pub fn raw_obs_return<I: U8Input>(i: I) -> SimpleResult<I, Field<I>> {
+    drop_field_name(i, b"Return-Path").then(|i| {
+        till_crlf(i).bind(|i, v| {
+            let value = AddressField {data: v};
+
+            i.ret(Field::ReturnPath(value))
+        })
+    })
+}
+
+#[test]
+fn test_raw_obs_return() {
+    let i = b"Return-Path: <>\x0d\x0a";
+    let msg = parse_only(raw_obs_return, i);
+    assert!(msg.is_ok());
+    assert!(!msg.unwrap().is_malformed());
+}
+
 // obs-received    =   "Received" *WSP ":" *received-token CRLF
 //
 // obs-optional    =   field-name *WSP ":" unstructured CRLF
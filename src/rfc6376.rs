@@ -0,0 +1,279 @@
+//! RFC6376 specifies DKIM, where a domain signs a message's header and body
+//! to take responsibility for it
+use std::str;
+
+use chomp::types::*;
+use chomp::parsers::*;
+use chomp::combinators::*;
+
+use rfc5322::*;
+use util::*;
+use super::*;
+
+// tag-name  =  ALPHA *ALNUMPUNC
+// ALNUMPUNC =  ALPHA / DIGIT / "_"
+fn is_alnumpunc(c: u8) -> bool {
+    (c >= b'a' && c <= b'z') || (c >= b'A' && c <= b'Z') || (c >= b'0' && c <= b'9') || c == b'_'
+}
+
+// VALCHAR   =  %x21-3A / %x3C-7E
+//           ; EXCLAMATION to TILDE except SEMICOLON
+fn is_valchar(c: u8) -> bool {
+    c >= 0x21 && c <= 0x7e && c != b';'
+}
+
+// tag-list  =  tag-spec *( ";" tag-spec ) [ ";" ]
+//
+// NOTE: Returns each tag name and value in order, including tags this crate
+// doesn't know
+pub fn tag_list<I: U8Input>(i: I) -> SimpleResult<I, Vec<(String, String)>> {
+    tag_spec(i).bind(|i, first| {
+        many(i, |i| {
+            token(i, b';').then(tag_spec)
+        }).bind(|i, mut tags: Vec<(String, String)>| {
+            option(i, |i| token(i, b';').map(|_| ()), ()).then(|i| {
+                tags.insert(0, first);
+                i.ret(tags)
+            })
+        })
+    })
+}
+
+// tag-spec  =  [FWS] tag-name [FWS] "=" [FWS] tag-value [FWS]
+// tag-value =  [ tval *( 1*(WSP / FWS) tval ) ]
+// tval      =  1*VALCHAR
+//
+// NOTE: Whitespace inside a value is dropped, which all the tags of section
+// 3.5 allow, so base64 values and header lists can be folded anywhere
+pub fn tag_spec<I: U8Input>(i: I) -> SimpleResult<I, (String, String)> {
+    option(i, drop_fws, ()).then(|i| {
+        matched_by(i, |i| {
+            alpha(i).then(|i| skip_while(i, is_alnumpunc))
+        }).bind(|i, (name, _)| {
+            option(i, drop_fws, ()).then(|i| {
+                token(i, b'=').then(|i| {
+                    many(i, |i| {
+                        option(i, drop_fws, ()).then(|i| take_while1(i, is_valchar))
+                    }).bind(|i, tvals: Vec<I::Buffer>| {
+                        option(i, drop_fws, ()).then(|i| {
+                            i.ret((string_from_bytes(name.into_vec()), string_from_bufs::<I>(tvals)))
+                        })
+                    })
+                })
+            })
+        })
+    })
+}
+
+#[test]
+fn test_tag_list() {
+    let i = b" v=1; a=rsa-sha256;\r\n\tb=dzdVyOfAKCdLXdJOc9G2q8LoXSlEniSb\r\n  av+yuU4zGeeruD00lszZVoG4ZHRNiYzR;";
+    let msg = parse_only(tag_list, i);
+    assert_eq!(msg.unwrap(), vec!(
+        ("v".to_string(), "1".to_string()),
+        ("a".to_string(), "rsa-sha256".to_string()),
+        ("b".to_string(), "dzdVyOfAKCdLXdJOc9G2q8LoXSlEniSbav+yuU4zGeeruD00lszZVoG4ZHRNiYzR".to_string()),
+    ));
+
+    let i = b"v=1; 1x=2";
+    assert!(parse_only(|i| tag_list(i).then(eof), i).is_err());
+}
+
+// A DKIM-Signature field value, which is a tag-list with the tags of section
+// 3.5. "v", "a", "b", "bh", "d", "h" and "s" are required, and no tag may
+// appear twice.
+//
+// NOTE: The lenient profile allows repeated tags, using the first, and an
+// "h" tag without "From", which section 5.4 says must be signed
+pub fn dkim_signature<I: U8Input>(i: I) -> SimpleResult<I, DKIMSignature> {
+    dkim_signature_with(i, Profile::Lenient)
+}
+
+pub fn dkim_signature_with<I: U8Input>(i: I, profile: Profile) -> SimpleResult<I, DKIMSignature> {
+    tag_list(i).bind(|i, tags| {
+        match signature_from_tags(&tags, profile) {
+            Some(signature) => i.ret(signature),
+            None => i.err(Error::unexpected()),
+        }
+    })
+}
+
+fn signature_from_tags(tags: &[(String, String)], profile: Profile) -> Option<DKIMSignature> {
+    for (n, &(ref name, _)) in tags.iter().enumerate() {
+        if profile != Profile::Lenient && tags[..n].iter().any(|&(ref other, _)| other == name) {
+            return None;
+        }
+    }
+    let tag = |name: &str| tags.iter().find(|&&(ref n, _)| n == name).map(|&(_, ref v)| &v[..]);
+    let number = |name: &str| -> Result<Option<u64>, ()> {
+        match tag(name) {
+            Some(v) => v.parse().map(Some).map_err(|_| ()),
+            None => Ok(None),
+        }
+    };
+
+    if tag("v") != Some("1") {
+        return None;
+    }
+    let algorithm = match tag("a") {
+        Some("rsa-sha1") => SigningAlgorithm::RsaSha1,
+        Some("rsa-sha256") => SigningAlgorithm::RsaSha256,
+        Some("ed25519-sha256") => SigningAlgorithm::Ed25519Sha256,
+        _ => return None,
+    };
+    // The body canonicalization is "simple" when only one is given
+    let (header_canonicalization, body_canonicalization) = {
+        let mut parts = tag("c").unwrap_or("simple/simple").splitn(2, '/');
+        let header = parts.next().and_then(canonicalization);
+        let body = parts.next().map_or(Some(Canonicalization::Simple), canonicalization);
+        match (header, body) {
+            (Some(h), Some(b)) => (h, b),
+            _ => return None,
+        }
+    };
+    let domain = match tag("d") {
+        Some(d) if !d.is_empty() => d.to_string(),
+        _ => return None,
+    };
+    let selector = match tag("s") {
+        Some(s) if !s.is_empty() => s.to_string(),
+        _ => return None,
+    };
+    let headers: Vec<String> = match tag("h") {
+        Some(h) => h.split(':').map(|n| n.to_string()).collect(),
+        None => return None,
+    };
+    if headers.iter().any(|h| h.is_empty()) {
+        return None;
+    }
+    if profile != Profile::Lenient && !headers.iter().any(|h| h.eq_ignore_ascii_case("from")) {
+        return None;
+    }
+    let body_hash = match tag("bh").and_then(|bh| decode_base64(bh.as_bytes())) {
+        Some(bh) => bh,
+        None => return None,
+    };
+    let signature = match tag("b").and_then(|b| decode_base64(b.as_bytes())) {
+        Some(b) => b,
+        None => return None,
+    };
+    let query_methods = tag("q").unwrap_or("dns/txt").split(':').map(|q| q.to_string()).collect();
+    let copied_headers = match tag("z") {
+        Some(z) => {
+            let mut copied = vec!();
+            for header in z.split('|') {
+                let decoded = match decode_dkim_quoted_printable(header) {
+                    Some(d) => d,
+                    None => return None,
+                };
+                match decoded.find(':') {
+                    Some(colon) => copied.push((decoded[..colon].to_string(), decoded[colon+1..].to_string())),
+                    None => return None,
+                }
+            }
+            copied
+        },
+        None => vec!(),
+    };
+    let (length, timestamp, expiration) = match (number("l"), number("t"), number("x")) {
+        (Ok(l), Ok(t), Ok(x)) => (l, t, x),
+        _ => return None,
+    };
+
+    Some(DKIMSignature{
+        version: 1,
+        algorithm: algorithm,
+        header_canonicalization: header_canonicalization,
+        body_canonicalization: body_canonicalization,
+        domain: domain,
+        selector: selector,
+        headers: headers,
+        body_hash: body_hash,
+        signature: signature,
+        identity: tag("i").map(|i| i.to_string()),
+        length: length,
+        query_methods: query_methods,
+        timestamp: timestamp,
+        expiration: expiration,
+        copied_headers: copied_headers,
+    })
+}
+
+fn canonicalization(name: &str) -> Option<Canonicalization> {
+    match name {
+        "simple" => Some(Canonicalization::Simple),
+        "relaxed" => Some(Canonicalization::Relaxed),
+        _ => None,
+    }
+}
+
+// dkim-quoted-printable =  *(FWS / hex-octet / dkim-safe-char)
+// hex-octet             =  "=" 2(DIGIT / "A" / "B" / "C" / "D" / "E" / "F")
+fn decode_dkim_quoted_printable(text: &str) -> Option<String> {
+    let text = text.as_bytes();
+    let mut bytes = Vec::with_capacity(text.len());
+    let mut pos = 0;
+    while pos < text.len() {
+        if text[pos] == b'=' {
+            let hex = match text.get(pos+1..pos+3).and_then(|h| str::from_utf8(h).ok()) {
+                Some(h) => h,
+                None => return None,
+            };
+            match u8::from_str_radix(hex, 16) {
+                Ok(b) => bytes.push(b),
+                Err(_) => return None,
+            }
+            pos += 3;
+        } else {
+            bytes.push(text[pos]);
+            pos += 1;
+        }
+    }
+    Some(string_from_bytes(bytes))
+}
+
+#[test]
+fn test_dkim_signature() {
+    // From RFC 6376 appendix A.2
+    let i = b" v=1; a=rsa-sha256; s=brisbane; d=example.com;\r\n      c=simple/simple; q=dns/txt; i=joe@football.example.com;\r\n      h=Received : From : To : Subject : Date : Message-ID;\r\n      bh=2jUSOH9NhtVGCQWNr9BrIAPreKQjO6Sn7XIkfJVOzv8=;\r\n      b=AuUoFEfDxTDkHlLXSZEpZj79LICEps6eda7W3deTVFOk4yAUoqOB\r\n        4nujc7YopdG5dWLSdNg6xNAZpOPr+kHxt1IrE+NahM6L/LbvaHut\r\n        KVdkLLkpVaVVQPzeRDI009SO2Il5Lu7rDNH6mZckBdrIx0orEtZV\r\n        4bmp/YzhwvcubU4=;\r\n";
+    let msg = parse_only(|i| dkim_signature_with(i, Profile::Strict).bind(|i, v| end_of_value(i).map(|_| v)), i).unwrap();
+    assert_eq!(msg.algorithm, SigningAlgorithm::RsaSha256);
+    assert_eq!(msg.header_canonicalization, Canonicalization::Simple);
+    assert_eq!(msg.body_canonicalization, Canonicalization::Simple);
+    assert_eq!(msg.domain, "example.com");
+    assert_eq!(msg.selector, "brisbane");
+    assert_eq!(msg.headers, vec!("Received", "From", "To", "Subject", "Date", "Message-ID"));
+    assert_eq!(msg.identity, Some("joe@football.example.com".to_string()));
+    assert_eq!(msg.body_hash.len(), 32);
+    assert_eq!(msg.signature.len(), 128);
+    assert_eq!(msg.length, None);
+
+    let i = b" v=1; a=ed25519-sha256; c=relaxed; d=example.com; s=s1; h=from:to; bh=AAAA; b=AAAA;\r\n l=200; t=1117574938; x=1118006938;\r\n z=From:foo@eng.example.net|Subject:demo=20run";
+    let msg = parse_only(|i| dkim_signature_with(i, Profile::Strict), i).unwrap();
+    assert_eq!(msg.header_canonicalization, Canonicalization::Relaxed);
+    assert_eq!(msg.body_canonicalization, Canonicalization::Simple);
+    assert_eq!((msg.length, msg.timestamp, msg.expiration), (Some(200), Some(1117574938), Some(1118006938)));
+    assert_eq!(msg.copied_headers, vec!(
+        ("From".to_string(), "foo@eng.example.net".to_string()),
+        ("Subject".to_string(), "demo run".to_string()),
+    ));
+
+    let i = b" v=1; a=rsa-sha256; d=example.com; s=s1; h=to; bh=AAAA; b=AAAA; d=example.net";
+    assert!(parse_only(|i| dkim_signature_with(i, Profile::Strict), i).is_err());
+    assert_eq!(parse_only(dkim_signature, i).unwrap().domain, "example.com");
+
+    let i = b" v=1; a=rsa-md5; d=example.com; s=s1; h=from; bh=AAAA; b=AAAA";
+    assert!(parse_only(dkim_signature, i).is_err());
+}
+
+// DKIM-Signature, from RFC 6376
+// NOTE: Accepting case-insensitive header name values
+pub fn raw_dkim_signature<I: U8Input>(i: I) -> SimpleResult<I, Field<I>> {
+    drop_field_name(i, b"DKIM-Signature").then(|i| {
+        till_crlf(i).bind(|i, v| {
+            let value = DKIMSignatureField {data: v};
+
+            i.ret(Field::DKIMSignature(value))
+        })
+    })
+}
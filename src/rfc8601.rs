@@ -0,0 +1,322 @@
+//! RFC8601 specifies the Authentication-Results field, where a host records
+//! the results of checking a message's sender, e.g. with SPF, DKIM and DMARC
+use chomp::types::*;
+use chomp::parsers::*;
+use chomp::combinators::*;
+
+use rfc5322::*;
+use util::*;
+use super::*;
+
+// token := 1*<any (US-ASCII) CHAR except SPACE, CTLs, or tspecials>
+// tspecials :=  "(" / ")" / "<" / ">" / "@" / "," / ";" / ":" / "\" / <">
+//               "/" / "[" / "]" / "?" / "="
+fn is_token_char(c: u8) -> bool {
+    c > 32 && c < 127 && !b"()<>@,;:\\\"/[]?=".contains(&c)
+}
+
+// Keyword = ldh-str
+fn is_ldh(c: u8) -> bool {
+    (c >= b'a' && c <= b'z') || (c >= b'A' && c <= b'Z') || (c >= b'0' && c <= b'9') || c == b'-'
+}
+
+// Characters the lenient profile accepts in an unquoted pvalue. Signatures
+// and hashes are often written with "/", "+" and "=" without quoting.
+fn is_lenient_pvalue_char(c: u8) -> bool {
+    c > 32 && c != 127 && c != b';' && c != b'(' && c != b')' && c != b'"'
+}
+
+// Keyword        =   ldh-str
+//
+// NOTE: Keywords are case-insensitive, so they're returned in lower case
+fn keyword<I: U8Input>(i: I) -> SimpleResult<I, String> {
+    take_while1(i, is_ldh).map(|buf| string_from_bytes(buf.into_vec()).to_lowercase())
+}
+
+// value := token / quoted-string
+fn value<I: U8Input>(i: I) -> SimpleResult<I, String> {
+    or(i, |i| take_while1(i, is_token_char).map(|buf| string_from_bytes(buf.into_vec())),
+          |i| quoted_string(i).map(|bufs| unescape(string_from_bufs::<I>(bufs).trim())))
+}
+
+// authres-payload =  [CFWS] authserv-id
+//                    [ CFWS authres-version ]
+//                    ( no-result / 1*resinfo ) [CFWS] CRLF
+// authserv-id     =  value
+// authres-version =  1*DIGIT [CFWS]
+// no-result       =  [CFWS] ";" [CFWS] "none"
+//
+// NOTE: The lenient profile also accepts a ";" after the last resinfo
+pub fn authres_payload<I: U8Input>(i: I) -> SimpleResult<I, AuthenticationResults> {
+    authres_payload_with(i, Profile::Lenient)
+}
+
+pub fn authres_payload_with<I: U8Input>(i: I, profile: Profile) -> SimpleResult<I, AuthenticationResults> {
+    let version = |i: I| {
+        drop_cfws(i).then(|i| {
+            parse_digits(i, 1..).bind(|i, v| {
+                option(i, drop_cfws, ()).then(|i| i.ret(Some(v)))
+            })
+        })
+    };
+    let no_result = |i: I| {
+        option(i, drop_cfws, ()).then(|i| {
+            token(i, b';').then(|i| {
+                option(i, drop_cfws, ()).then(|i| {
+                    keyword(i).bind(|i, k| {
+                        if k == "none" {
+                            i.ret(vec!())
+                        } else {
+                            i.err(Error::unexpected())
+                        }
+                    })
+                })
+            })
+        })
+    };
+    option(i, drop_cfws, ()).then(|i| {
+        value(i).bind(|i, authserv_id| {
+            option(i, version, None).bind(|i, version| {
+                or(i, |i| many1(i, |i| resinfo_with(i, profile)), no_result).bind(|i, results| {
+                    match profile {
+                        Profile::Lenient => option(i, |i| {
+                            option(i, drop_cfws, ()).then(|i| token(i, b';')).map(|_| ())
+                        }, ()),
+                        _ => i.ret(()),
+                    }.then(|i| {
+                        option(i, drop_cfws, ()).then(|i| {
+                            i.ret(AuthenticationResults{
+                                authserv_id: authserv_id,
+                                version: version,
+                                results: results,
+                            })
+                        })
+                    })
+                })
+            })
+        })
+    })
+}
+
+#[test]
+fn test_authres_payload() {
+    let i = b" example.com;\r\n spf=pass smtp.mailfrom=example.net";
+    let msg = parse_only(|i| authres_payload_with(i, Profile::Strict), i).unwrap();
+    assert_eq!(msg.authserv_id, "example.com");
+    assert_eq!(msg.version, None);
+    assert_eq!(msg.results, vec!(AuthResult{
+        method: "spf".to_string(),
+        method_version: None,
+        result: "pass".to_string(),
+        reason: None,
+        properties: vec!(AuthProperty{
+            ptype: "smtp".to_string(),
+            property: "mailfrom".to_string(),
+            value: "example.net".to_string(),
+        }),
+        comments: vec!(),
+    }));
+
+    let i = b" example.com 1; none";
+    let msg = parse_only(|i| authres_payload_with(i, Profile::Strict), i).unwrap();
+    assert_eq!(msg.version, Some(1));
+    assert_eq!(msg.results, vec!());
+
+    let i = b" example.com;\r\n dkim=pass (good signature) header.d=mail-router.example.net;\r\n dkim=fail (bad signature) header.d=newyork.example.com";
+    let msg = parse_only(|i| authres_payload_with(i, Profile::Strict), i).unwrap();
+    assert_eq!(msg.results.len(), 2);
+    assert_eq!(msg.results[0].comments, vec!("good signature"));
+    assert_eq!(msg.results[1].result, "fail");
+    assert_eq!(msg.results[1].property("header", "d"), Some("newyork.example.com"));
+
+    let i = b" mx.example.com; dmarc=pass action=none header.from=example.com;";
+    assert!(parse_only(|i| authres_payload_with(i, Profile::Strict).then(end_of_value), i).is_err());
+    let msg = parse_only(authres_payload, i).unwrap();
+    assert_eq!(msg.results[0].property("", "action"), Some("none"));
+    assert_eq!(msg.results[0].property("header", "from"), Some("example.com"));
+}
+
+// resinfo         =  [CFWS] ";" methodspec [ CFWS reasonspec ]
+//                    [ CFWS 1*propspec ]
+// methodspec      =  [CFWS] method [CFWS] "=" [CFWS] result
+// method          =  Keyword [ [CFWS] "/" [CFWS] method-version ]
+// method-version  =  1*DIGIT [CFWS]
+// result          =  Keyword
+// reasonspec      =  "reason" [CFWS] "=" [CFWS] value
+//
+// NOTE: The comments after the ";" are kept with the result
+pub fn resinfo<I: U8Input>(i: I) -> SimpleResult<I, AuthResult> {
+    resinfo_with(i, Profile::Lenient)
+}
+
+pub fn resinfo_with<I: U8Input>(i: I, profile: Profile) -> SimpleResult<I, AuthResult> {
+    let method_version = |i: I| {
+        option(i, drop_cfws, ()).then(|i| {
+            token(i, b'/').then(|i| {
+                option(i, drop_cfws, ()).then(|i| {
+                    parse_digits(i, 1..).map(Some)
+                })
+            })
+        })
+    };
+    let reason = |i: I| {
+        option(i, drop_cfws, ()).then(|i| {
+            keyword(i).bind(|i, k| {
+                if k != "reason" {
+                    return i.err(Error::unexpected());
+                }
+                option(i, drop_cfws, ()).then(|i| {
+                    token(i, b'=').then(|i| {
+                        option(i, drop_cfws, ()).then(|i| value(i).map(Some))
+                    })
+                })
+            })
+        })
+    };
+    option(i, drop_cfws, ()).then(|i| {
+        token(i, b';').then(|i| {
+            matched_by(i, |i| {
+                option(i, drop_cfws, ()).then(|i| {
+                    keyword(i).bind(|i, method| {
+                        option(i, method_version, None).bind(|i, method_version| {
+                            option(i, drop_cfws, ()).then(|i| {
+                                token(i, b'=').then(|i| {
+                                    option(i, drop_cfws, ()).then(|i| {
+                                        keyword(i).bind(|i, result| {
+                                            option(i, reason, None).bind(|i, reason| {
+                                                many(i, |i| {
+                                                    option(i, drop_cfws, ()).then(|i| propspec_with(i, profile))
+                                                }).bind(|i, properties| {
+                                                    option(i, drop_cfws, ()).then(|i| {
+                                                        i.ret(AuthResult{
+                                                            method: method,
+                                                            method_version: method_version,
+                                                            result: result,
+                                                            reason: reason,
+                                                            properties: properties,
+                                                            comments: vec!(),
+                                                        })
+                                                    })
+                                                })
+                                            })
+                                        })
+                                    })
+                                })
+                            })
+                        })
+                    })
+                })
+            }).map(|(buf, mut result): (I::Buffer, AuthResult)| {
+                result.comments = comments(&buf.into_vec());
+                result
+            })
+        })
+    })
+}
+
+#[test]
+fn test_resinfo() {
+    let i = b"; dkim/1=pass reason=\"good (enough)\" (1024-bit key)\r\n header.i=@example.com";
+    let msg = parse_only(|i| resinfo_with(i, Profile::Strict), i).unwrap();
+    assert_eq!(msg.method, "dkim");
+    assert_eq!(msg.method_version, Some(1));
+    assert_eq!(msg.reason, Some("good (enough)".to_string()));
+    assert_eq!(msg.comments, vec!("1024-bit key"));
+    assert_eq!(msg.property("header", "i"), Some("@example.com"));
+
+    let i = b"; IPREV=Pass policy.iprev=192.0.2.200";
+    let msg = parse_only(|i| resinfo_with(i, Profile::Strict), i).unwrap();
+    assert_eq!((&msg.method[..], &msg.result[..]), ("iprev", "pass"));
+    assert_eq!(msg.property("policy", "iprev"), Some("192.0.2.200"));
+
+    let i = b"; auth=pass smtp.auth=sender@example.com";
+    let msg = parse_only(|i| resinfo_with(i, Profile::Strict), i).unwrap();
+    assert_eq!(msg.property("smtp", "auth"), Some("sender@example.com"));
+
+    let i = b"; dkim=pass header.b=Ab/c+d=";
+    assert!(parse_only(|i| resinfo_with(i, Profile::Strict).then(end_of_value), i).is_err());
+    let msg = parse_only(resinfo, i).unwrap();
+    assert_eq!(msg.property("header", "b"), Some("Ab/c+d="));
+}
+
+// propspec        =  ptype [CFWS] "." [CFWS] property [CFWS] "=" pvalue
+// ptype           =  Keyword
+// property        =  special-smtp-verb / Keyword
+// special-smtp-verb = "mailfrom" / "rcptto"
+// pvalue          =  [CFWS] ( value / [ [ local-part ] "@" ] domain-name )
+//                    [CFWS]
+//
+// NOTE: The lenient profile allows a property without a ptype, which is
+// then empty, and more characters in an unquoted pvalue
+pub fn propspec<I: U8Input>(i: I) -> SimpleResult<I, AuthProperty> {
+    propspec_with(i, Profile::Lenient)
+}
+
+pub fn propspec_with<I: U8Input>(i: I, profile: Profile) -> SimpleResult<I, AuthProperty> {
+    let typed = |i: I| {
+        keyword(i).bind(|i, ptype| {
+            option(i, drop_cfws, ()).then(|i| {
+                token(i, b'.').then(|i| {
+                    option(i, drop_cfws, ()).then(|i| {
+                        keyword(i).map(|property| (ptype, property))
+                    })
+                })
+            })
+        })
+    };
+    let untyped = |i: I| {
+        keyword(i).bind(|i, property| {
+            if property == "reason" {
+                i.err(Error::unexpected())
+            } else {
+                i.ret((String::new(), property))
+            }
+        })
+    };
+    let mailbox = |i: I| {
+        matched_by(i, |i| {
+            skip_while(i, is_token_char).then(|i| {
+                token(i, b'@').then(|i| take_while1(i, is_token_char))
+            })
+        }).map(|(buf, _)| string_from_bytes(buf.into_vec()))
+    };
+    let pvalue = |i: I| {
+        option(i, drop_cfws, ()).then(|i| {
+            match profile {
+                Profile::Lenient => or(i, |i| quoted_string(i).map(|bufs| unescape(string_from_bufs::<I>(bufs).trim())),
+                                          |i| take_while1(i, is_lenient_pvalue_char).map(|buf| string_from_bytes(buf.into_vec()))),
+                _ => or(i, mailbox, value),
+            }.bind(|i, v| {
+                option(i, drop_cfws, ()).then(|i| i.ret(v))
+            })
+        })
+    };
+    match profile {
+        Profile::Lenient => or(i, typed, untyped),
+        _ => typed(i),
+    }.bind(|i, (ptype, property)| {
+        option(i, drop_cfws, ()).then(|i| {
+            token(i, b'=').then(|i| {
+                pvalue(i).map(|value| {
+                    AuthProperty{
+                        ptype: ptype,
+                        property: property,
+                        value: value,
+                    }
+                })
+            })
+        })
+    })
+}
+
+// Authentication-Results, from RFC 8601
+// NOTE: Accepting case-insensitive header name values
+pub fn raw_authentication_results<I: U8Input>(i: I) -> SimpleResult<I, Field<I>> {
+    drop_field_name(i, b"Authentication-Results").then(|i| {
+        till_crlf(i).bind(|i, v| {
+            let value = AuthenticationResultsField {data: v};
+
+            i.ret(Field::AuthenticationResults(value))
+        })
+    })
+}
@@ -0,0 +1,623 @@
+//! Incremental, event-based message parsing
+//!
+//! `message` needs the complete message in memory before it can return a
+//! `Message`.  `EventReader` instead pulls from any `io::Read` and emits
+//! header fields, MIME part boundaries and body chunks as the input arrives,
+//! never holding more than `max_buffer` bytes of the message at a time, plus
+//! one byte of lookahead while reading header fields.
+
+use std::collections::VecDeque;
+use std::error;
+use std::fmt;
+use std::io;
+use std::io::Read;
+use std::mem;
+
+/// Default upper bound on buffered input, in bytes
+pub const DEFAULT_MAX_BUFFER: usize = 64 * 1024;
+
+#[derive(Debug)]
+pub enum Event {
+    /// A header field of the message or of the current MIME part.  The name
+    /// is as it appeared in the input, the value is everything following the
+    /// ":" (including any folding), without the terminating CRLF.
+    Field {
+        name: String,
+        value: Vec<u8>,
+    },
+    /// A MIME part of a multipart body begins; its header fields follow
+    PartStart,
+    /// The most recently started MIME part has ended
+    PartEnd,
+    /// A chunk of body content, at most `max_buffer` bytes long.  Preambles
+    /// and epilogues of multipart bodies are reported as body content of
+    /// the enclosing entity.
+    Body(Vec<u8>),
+}
+
+#[derive(Debug)]
+pub enum EventError {
+    Io(io::Error),
+    /// A single header field didn't fit in the buffer
+    BufferFull,
+}
+
+impl fmt::Display for EventError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match self {
+            &EventError::Io(ref e) => write!(f, "error reading message: {}", e),
+            &EventError::BufferFull => write!(f, "header field exceeds maximum buffer size"),
+        }
+    }
+}
+
+impl error::Error for EventError {
+    fn description(&self) -> &str {
+        match self {
+            &EventError::Io(_) => "error reading message",
+            &EventError::BufferFull => "header field exceeds maximum buffer size",
+        }
+    }
+}
+
+impl From<io::Error> for EventError {
+    fn from(e: io::Error) -> EventError {
+        EventError::Io(e)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum State {
+    Headers,
+    Body,
+    Done,
+}
+
+// An enclosing multipart body
+struct Multipart {
+    boundary: Vec<u8>,
+    // true once the first delimiter has been seen, until the next one
+    in_part: bool,
+}
+
+/// Reads a message from `source`, producing `Event`s
+///
+/// ```ignore
+/// let reader = EventReader::new(io::stdin());
+/// for event in reader {
+///     match try!(event) {
+///         Event::Field{name, value} => ...,
+///         Event::Body(chunk) => ...,
+///         _ => (),
+///     }
+/// }
+/// ```
+pub struct EventReader<R: Read> {
+    source: R,
+    max_buffer: usize,
+    buf: Vec<u8>,
+    eof: bool,
+    state: State,
+    // Content-Type of the entity whose headers are being read
+    content_type: Option<Vec<u8>>,
+    multiparts: Vec<Multipart>,
+    // Line ending withheld from the previous body chunk, since the line
+    // ending before a delimiter belongs to the delimiter
+    pending_newline: Vec<u8>,
+    line_start: bool,
+    events: VecDeque<Event>,
+}
+
+impl<R: Read> EventReader<R> {
+    pub fn new(source: R) -> EventReader<R> {
+        EventReader::with_max_buffer(source, DEFAULT_MAX_BUFFER)
+    }
+
+    /// Creates a reader which buffers at most `max_buffer` bytes.  Header
+    /// fields longer than this produce `EventError::BufferFull`.
+    pub fn with_max_buffer(source: R, max_buffer: usize) -> EventReader<R> {
+        // A buffer needs room for at least a delimiter line to be useful
+        let max_buffer = if max_buffer < 128 { 128 } else { max_buffer };
+
+        EventReader {
+            source: source,
+            max_buffer: max_buffer,
+            buf: Vec::with_capacity(max_buffer + 1),
+            eof: false,
+            state: State::Headers,
+            content_type: None,
+            multiparts: vec!(),
+            pending_newline: vec!(),
+            line_start: true,
+            events: VecDeque::new(),
+        }
+    }
+
+    // Reads more input, returning false if no more input is available or
+    // the buffer is full
+    fn fill(&mut self) -> Result<bool, EventError> {
+        let limit = self.max_buffer;
+        self.fill_to(limit)
+    }
+
+    // Reads more input into at most `limit` bytes of buffer
+    fn fill_to(&mut self, limit: usize) -> Result<bool, EventError> {
+        if self.eof || self.buf.len() >= limit {
+            return Ok(false)
+        }
+
+        let mut chunk = vec!(0; limit - self.buf.len());
+        loop {
+            match self.source.read(&mut chunk) {
+                Ok(0) => {
+                    self.eof = true;
+                    return Ok(false)
+                },
+                Ok(n) => {
+                    self.buf.extend_from_slice(&chunk[..n]);
+                    return Ok(true)
+                },
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(EventError::Io(e)),
+            }
+        }
+    }
+
+    fn consume(&mut self, n: usize) -> Vec<u8> {
+        let rest = self.buf.split_off(n);
+        mem::replace(&mut self.buf, rest)
+    }
+
+    // Queues events for the end of input, closing any unterminated parts
+    fn finish(&mut self) {
+        if !self.pending_newline.is_empty() {
+            let newline = self.pending_newline.split_off(0);
+            self.events.push_back(Event::Body(newline));
+        }
+        while let Some(m) = self.multiparts.pop() {
+            if m.in_part {
+                self.events.push_back(Event::PartEnd);
+            }
+        }
+        self.state = State::Done;
+    }
+
+    fn read_header(&mut self) -> Result<(), EventError> {
+        // Locate the end of the field, including continuation lines. The
+        // buffer holds one byte more than `max_buffer`, so the start of the
+        // next line can be seen after a field of exactly `max_buffer` bytes.
+        let limit = self.max_buffer + 1;
+        let mut search = 0;
+        let end = loop {
+            match find_lf(&self.buf[search..]) {
+                Some(p) => {
+                    let lf = search + p;
+                    if lf + 1 < self.buf.len() {
+                        let empty = search == 0 && trim_newline(&self.buf[..lf + 1]).is_empty();
+                        if is_wsp(self.buf[lf + 1]) && !empty {
+                            search = lf + 1;
+                            continue
+                        }
+                        break Some(lf + 1)
+                    } else if self.eof {
+                        break Some(lf + 1)
+                    }
+                },
+                None if self.eof => {
+                    break if self.buf.is_empty() { None } else { Some(self.buf.len()) }
+                },
+                None => (),
+            }
+            if !try!(self.fill_to(limit)) && !self.eof {
+                return Err(EventError::BufferFull)
+            }
+        };
+
+        let end = match end {
+            Some(e) => e,
+            None => {
+                self.finish();
+                return Ok(())
+            },
+        };
+
+        let line_len = trim_newline(&self.buf[..end]).len();
+        if line_len == 0 {
+            // Empty line, headers are done
+            self.consume(end);
+            self.start_body();
+            return Ok(())
+        }
+
+        let colon = self.buf[..line_len].iter().position(|&c| c == b':');
+        match colon {
+            Some(c) if c > 0 && self.buf[..c].iter().all(|&t| t > 32 && t < 127 || is_wsp(t)) => {
+                let line = self.consume(end);
+                let name = String::from_utf8_lossy(trim_wsp(&line[..c])).into_owned();
+                let value = &line[c + 1..line_len];
+
+                if name.eq_ignore_ascii_case("Content-Type") {
+                    self.content_type = Some(value.to_vec());
+                }
+                self.events.push_back(Event::Field {
+                    name: name,
+                    value: value.to_vec(),
+                });
+            },
+            _ => {
+                // Not a header field, so the headers must have ended without
+                // the empty line
+                self.start_body();
+            },
+        }
+        Ok(())
+    }
+
+    fn start_body(&mut self) {
+        if let Some(boundary) = self.content_type.take().and_then(|v| multipart_boundary(&v)) {
+            self.multiparts.push(Multipart {
+                boundary: boundary,
+                in_part: false,
+            });
+        }
+        self.line_start = true;
+        self.state = State::Body;
+    }
+
+    fn read_body(&mut self) -> Result<(), EventError> {
+        let mut pos = 0;
+
+        loop {
+            match find_lf(&self.buf[pos..]) {
+                Some(p) => {
+                    let line_end = pos + p + 1;
+                    if self.line_start {
+                        if let Some(delimiter) = self.delimiter(&self.buf[pos..line_end]) {
+                            self.emit_body(pos);
+                            self.consume(line_end - pos);
+                            self.on_delimiter(delimiter);
+                            return Ok(())
+                        }
+                    }
+                    self.line_start = true;
+                    pos = line_end;
+                },
+                None if self.eof => {
+                    let len = self.buf.len();
+                    if self.line_start && len > pos {
+                        if let Some(delimiter) = self.delimiter(&self.buf[pos..]) {
+                            self.emit_body(pos);
+                            self.consume(len - pos);
+                            self.on_delimiter(delimiter);
+                            return Ok(())
+                        }
+                    }
+                    let content = self.consume(len);
+                    self.push_body(content);
+                    self.finish();
+                    return Ok(())
+                },
+                None => {
+                    if pos > 0 {
+                        // Emit complete lines before reading any more
+                        self.emit_body(pos);
+                        return Ok(())
+                    }
+                    if !try!(self.fill()) && !self.eof {
+                        // A single line fills the buffer, so it can't be a
+                        // delimiter
+                        let len = self.buf.len();
+                        let content = self.consume(len);
+                        self.line_start = false;
+                        self.push_body(content);
+                        return Ok(())
+                    }
+                },
+            }
+        }
+    }
+
+    // Emits buffered body content up to `end` as a single chunk, withholding
+    // the final line ending if a delimiter might follow it
+    fn emit_body(&mut self, end: usize) {
+        let mut content = self.consume(end);
+        if content.is_empty() {
+            return
+        }
+        let newline = if self.multiparts.is_empty() {
+            vec!()
+        } else {
+            let len = trim_newline(&content).len();
+            content.split_off(len)
+        };
+        self.push_body(content);
+        self.pending_newline = newline;
+    }
+
+    // Emits any withheld line ending, then `content`. The line ending is a
+    // chunk of its own, so no chunk is longer than `max_buffer`.
+    fn push_body(&mut self, content: Vec<u8>) {
+        if !self.pending_newline.is_empty() {
+            let newline = self.pending_newline.split_off(0);
+            self.events.push_back(Event::Body(newline));
+        }
+        if !content.is_empty() {
+            self.events.push_back(Event::Body(content));
+        }
+    }
+
+    // If `line` is a delimiter for an enclosing multipart, returns the index
+    // of the multipart and whether it is the close-delimiter
+    fn delimiter(&self, line: &[u8]) -> Option<(usize, bool)> {
+        if line.len() < 3 || &line[..2] != b"--" {
+            return None
+        }
+        let line = trim_wsp(trim_newline(&line[2..]));
+        for (idx, m) in self.multiparts.iter().enumerate().rev() {
+            if line == &m.boundary[..] {
+                return Some((idx, false))
+            }
+            if line.len() == m.boundary.len() + 2 && line.starts_with(&m.boundary) && line.ends_with(b"--") {
+                return Some((idx, true))
+            }
+        }
+        None
+    }
+
+    fn on_delimiter(&mut self, (idx, close): (usize, bool)) {
+        // The pending newline belongs to the delimiter
+        self.pending_newline.clear();
+
+        // A delimiter for an outer multipart implicitly ends the inner ones
+        while self.multiparts.len() > idx + 1 {
+            if self.multiparts.pop().unwrap().in_part {
+                self.events.push_back(Event::PartEnd);
+            }
+        }
+        if self.multiparts[idx].in_part {
+            self.events.push_back(Event::PartEnd);
+        }
+
+        self.line_start = true;
+        if close {
+            self.multiparts.pop();
+            self.state = State::Body;
+        } else {
+            self.multiparts[idx].in_part = true;
+            self.content_type = None;
+            self.events.push_back(Event::PartStart);
+            self.state = State::Headers;
+        }
+    }
+}
+
+impl<R: Read> Iterator for EventReader<R> {
+    type Item = Result<Event, EventError>;
+
+    fn next(&mut self) -> Option<Result<Event, EventError>> {
+        while self.events.is_empty() {
+            let result = match self.state {
+                State::Headers => self.read_header(),
+                State::Body => self.read_body(),
+                State::Done => return None,
+            };
+            if let Err(e) = result {
+                self.state = State::Done;
+                return Some(Err(e))
+            }
+        }
+        self.events.pop_front().map(|e| Ok(e))
+    }
+}
+
+fn is_wsp(c: u8) -> bool {
+    c == b' ' || c == b'\t'
+}
+
+fn find_lf(buf: &[u8]) -> Option<usize> {
+    buf.iter().position(|&c| c == b'\n')
+}
+
+fn trim_newline(line: &[u8]) -> &[u8] {
+    let mut end = line.len();
+    if end > 0 && line[end - 1] == b'\n' {
+        end -= 1;
+        if end > 0 && line[end - 1] == b'\r' {
+            end -= 1;
+        }
+    }
+    &line[..end]
+}
+
+fn trim_wsp(line: &[u8]) -> &[u8] {
+    let start = line.iter().position(|&c| !is_wsp(c)).unwrap_or(line.len());
+    let end = line.iter().rposition(|&c| !is_wsp(c)).map(|p| p + 1).unwrap_or(start);
+    &line[start..end]
+}
+
+// Extracts the boundary parameter of a multipart Content-Type value
+fn multipart_boundary(value: &[u8]) -> Option<Vec<u8>> {
+    let unfolded: Vec<u8> = value.iter()
+        .filter(|&&c| c != b'\r' && c != b'\n')
+        .cloned()
+        .collect();
+    let mut params = unfolded.split(|&c| c == b';');
+
+    let media_type = trim_wsp(params.next().unwrap_or(b""));
+    if media_type.len() < 10 || !media_type[..10].eq_ignore_ascii_case(b"multipart/") {
+        return None
+    }
+
+    for param in params {
+        let eq = match param.iter().position(|&c| c == b'=') {
+            Some(eq) => eq,
+            None => continue,
+        };
+        if !trim_wsp(&param[..eq]).eq_ignore_ascii_case(b"boundary") {
+            continue
+        }
+        let mut v = trim_wsp(&param[eq + 1..]);
+        if v.len() >= 2 && v[0] == b'"' && v[v.len() - 1] == b'"' {
+            v = &v[1..v.len() - 1];
+        }
+        if !v.is_empty() {
+            return Some(v.to_vec())
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+fn describe<R: Read>(reader: EventReader<R>) -> Vec<String> {
+    // Adjacent body chunks are merged, since chunking depends on buffering
+    let mut out: Vec<String> = vec!();
+    for event in reader {
+        let s = match event.unwrap() {
+            Event::Field{name, value} => format!("{}:{}", name, String::from_utf8_lossy(&value)),
+            Event::PartStart => "start".to_string(),
+            Event::PartEnd => "end".to_string(),
+            Event::Body(b) => {
+                let text = format!("body {}", String::from_utf8_lossy(&b));
+                match out.last_mut() {
+                    Some(ref mut last) if last.starts_with("body ") => {
+                        last.push_str(&text[5..]);
+                        continue
+                    },
+                    _ => text,
+                }
+            },
+        };
+        out.push(s);
+    }
+    out
+}
+
+#[test]
+fn test_event_reader() {
+    let i = b"From: John Doe <jdoe@machine.example>\r\nSubject: Saying\r\n Hello\r\n\r\nThis is a message just to say hello.\r\n";
+    let events = describe(EventReader::new(&i[..]));
+    assert_eq!(events, vec!(
+            "From: John Doe <jdoe@machine.example>",
+            "Subject: Saying\r\n Hello",
+            "body This is a message just to say hello.\r\n",
+            ));
+}
+
+#[test]
+fn test_event_reader_multipart() {
+    let i = b"Content-Type: multipart/mixed;\r\n boundary=\"simple boundary\"\r\n\r\npreamble\r\n--simple boundary\r\n\r\nfirst part\r\n--simple boundary\r\nContent-Type: text/plain\r\n\r\nsecond part\r\n\r\n--simple boundary--\r\nepilogue\r\n";
+    let events = describe(EventReader::new(&i[..]));
+    assert_eq!(events, vec!(
+            "Content-Type: multipart/mixed;\r\n boundary=\"simple boundary\"",
+            "body preamble",
+            "start",
+            "body first part",
+            "end",
+            "start",
+            "Content-Type: text/plain",
+            "body second part\r\n",
+            "end",
+            "body epilogue\r\n",
+            ));
+}
+
+#[test]
+fn test_event_reader_nested_multipart() {
+    let i = b"Content-Type: multipart/mixed; boundary=outer\r\n\r\n--outer\r\nContent-Type: multipart/alternative; boundary=inner\r\n\r\n--inner\r\n\r\nplain\r\n--inner\r\n\r\nhtml\r\n--outer--\r\n";
+    let events = describe(EventReader::new(&i[..]));
+    assert_eq!(events, vec!(
+            "Content-Type: multipart/mixed; boundary=outer",
+            "start",
+            "Content-Type: multipart/alternative; boundary=inner",
+            "start",
+            "body plain",
+            "end",
+            "start",
+            "body html",
+            "end",
+            "end",
+            ));
+}
+
+#[test]
+fn test_event_reader_max_buffer() {
+    let mut i = b"Subject: hello\r\n\r\n".to_vec();
+    i.extend_from_slice(&[b'a'; 1000]);
+    let reader = EventReader::with_max_buffer(&i[..], 128);
+    for event in reader {
+        match event.unwrap() {
+            Event::Body(b) => assert!(b.len() <= 128),
+            _ => (),
+        }
+    }
+
+    let mut i = b"Subject: ".to_vec();
+    i.extend_from_slice(&[b'a'; 1000]);
+    i.extend_from_slice(b"\r\n\r\n");
+    let mut reader = EventReader::with_max_buffer(&i[..], 128);
+    match reader.next() {
+        Some(Err(EventError::BufferFull)) => (),
+        e => panic!("expected BufferFull, got {:?}", e),
+    }
+
+    // A field of exactly `max_buffer` bytes fits, continuation lines included
+    for &fold in [false, true].iter() {
+        let mut field = b"Subject: ".to_vec();
+        let fill = 128 - field.len() - if fold { 6 } else { 2 };
+        field.extend_from_slice(&vec!(b'a'; fill));
+        if fold {
+            field.extend_from_slice(b"\r\n a");
+        }
+        field.extend_from_slice(b"\r\n");
+        assert_eq!(field.len(), 128);
+        let mut i = field.clone();
+        i.extend_from_slice(b"To: jdoe@example.com\r\n\r\nbody\r\n");
+        let events = describe(EventReader::with_max_buffer(&i[..], 128));
+        assert_eq!(events.len(), 3);
+        assert_eq!(events[0].as_bytes(), &field[..126]);
+    }
+
+    // One byte more doesn't
+    let mut i = b"Subject: ".to_vec();
+    i.extend_from_slice(&[b'a'; 118]);
+    i.extend_from_slice(b"\r\nTo: jdoe@example.com\r\n\r\n");
+    let mut reader = EventReader::with_max_buffer(&i[..], 128);
+    match reader.next() {
+        Some(Err(EventError::BufferFull)) => (),
+        e => panic!("expected BufferFull, got {:?}", e),
+    }
+}
+
+#[test]
+fn test_event_reader_max_buffer_multipart() {
+    // Withheld line endings don't make body chunks longer than `max_buffer`
+    let mut i = b"Content-Type: multipart/mixed; boundary=xyz\r\n\r\n--xyz\r\n\r\n".to_vec();
+    for n in 0..200 {
+        i.extend_from_slice(&vec!(b'a'; n % 130));
+        i.extend_from_slice(if n % 2 == 0 { b"\n" } else { b"\r\n" });
+    }
+    i.extend_from_slice(&[b'b'; 127]);
+    for event in EventReader::with_max_buffer(&i[..], 128) {
+        match event.unwrap() {
+            Event::Body(b) => assert!(b.len() <= 128, "chunk of {} bytes", b.len()),
+            _ => (),
+        }
+    }
+    let small = describe(EventReader::with_max_buffer(&i[..], 128));
+    let large = describe(EventReader::new(&i[..]));
+    assert_eq!(small, large);
+}
+
+#[test]
+fn test_event_reader_small_buffer_multipart() {
+    let mut i = b"Content-Type: multipart/mixed; boundary=xyz\r\n\r\n--xyz\r\n\r\n".to_vec();
+    for n in 0..50 {
+        i.extend_from_slice(format!("line {} of the first part\r\n", n).as_bytes());
+    }
+    i.extend_from_slice(b"--xyz\r\n\r\nsecond\r\n--xyz--\r\n");
+
+    let small = describe(EventReader::with_max_buffer(&i[..], 128));
+    let large = describe(EventReader::new(&i[..]));
+    assert_eq!(small, large);
+    assert_eq!(large.len(), 7);
+}
@@ -0,0 +1,144 @@
+//! Base subjects, for matching replies and forwards to the messages they
+//! answer, as RFC 5256 section 2.1 describes
+use util::*;
+use super::*;
+
+/// The reply and forward prefixes RFC 5256 strips
+pub const REPLY_PREFIXES: &'static [&'static str] = &["re", "fw", "fwd"];
+
+// Reply and forward prefixes written by localized mail clients, along with
+// the RFC 5256 ones. Prefixes match case-insensitively.
+pub const LOCALIZED_PREFIXES: &'static [&'static str] = &[
+    "re", "fw", "fwd",
+    "aw", "wg",                 // German
+    "sv", "vs", "vb",           // Danish, Norwegian, Swedish
+    "antw", "doorst",           // Dutch
+    "tr", "réf",                // French
+    "rif", "inoltro",           // Italian
+    "odp", "pd",                // Polish
+    "ynt", "ilt",               // Turkish
+    "回复", "回覆", "答复", "转发", "轉寄", // Chinese
+    "返信", "転送",              // Japanese
+];
+
+impl<I: U8Input> Message<I> {
+    /// Returns the subject without reply and forward prefixes, list tags or
+    /// "(fwd)" trailers, as RFC 5256 describes
+    pub fn base_subject(&self) -> FieldValue<String> {
+        self.base_subject_with(REPLY_PREFIXES)
+    }
+
+    /// Returns the base subject, stripping `prefixes` in place of "Re", "Fw"
+    /// and "Fwd"
+    pub fn base_subject_with(&self, prefixes: &[&str]) -> FieldValue<String> {
+        match self.subject() {
+            FieldValue::Ok(subject) => FieldValue::Ok(base_subject(&subject, prefixes)),
+            v => v,
+        }
+    }
+}
+
+/// Returns the base subject of decoded subject text. Each of `prefixes` is
+/// stripped like "Re" when followed by a colon, which may be full-width.
+pub fn base_subject(subject: &str, prefixes: &[&str]) -> String {
+    // (1) Collapse whitespace
+    let mut base = collapse_whitespace(subject);
+    loop {
+        // (2) Remove subj-trailer = "(fwd)" / WSP
+        loop {
+            let len = base.trim_right().len();
+            base.truncate(len);
+            let len = base.len();
+            if len >= 5 && base.is_char_boundary(len - 5) && base[len-5..].eq_ignore_ascii_case("(fwd)") {
+                base.truncate(len - 5);
+            } else {
+                break;
+            }
+        }
+
+        // (3) Remove subj-leader, and (4) a subj-blob if something is left,
+        // (5) until neither matches
+        loop {
+            let rest = match strip_leader(&base, prefixes) {
+                Some(rest) => rest.to_string(),
+                None => match strip_blob(&base) {
+                    Some(rest) if !rest.is_empty() => rest.to_string(),
+                    _ => break,
+                },
+            };
+            base = rest;
+        }
+
+        // (6) Remove a subj-fwd-hdr "[fwd:" and subj-fwd-trl "]", then start
+        // again from (2)
+        let len = base.len();
+        if len > 5 && base.is_char_boundary(5) && base[..5].eq_ignore_ascii_case("[fwd:") && base.ends_with(']') {
+            base = base[5..len-1].to_string();
+        } else {
+            return base;
+        }
+    }
+}
+
+// subj-leader     = (*subj-blob subj-refwd) / WSP
+fn strip_leader<'a>(s: &'a str, prefixes: &[&str]) -> Option<&'a str> {
+    if s.starts_with(' ') {
+        return Some(s.trim_left());
+    }
+    let mut rest = s;
+    while let Some(r) = strip_blob(rest) {
+        rest = r;
+    }
+    strip_refwd(rest, prefixes)
+}
+
+// subj-refwd      = ("re" / ("fw" ["d"])) *WSP [subj-blob] ":"
+fn strip_refwd<'a>(s: &'a str, prefixes: &[&str]) -> Option<&'a str> {
+    for &prefix in prefixes.iter() {
+        let len = prefix.len();
+        if s.len() < len || !s.is_char_boundary(len) || !s[..len].eq_ignore_ascii_case(prefix) {
+            continue;
+        }
+        let mut rest = s[len..].trim_left();
+        if let Some(r) = strip_blob(rest) {
+            rest = r;
+        }
+        if rest.starts_with(':') {
+            return Some(&rest[1..]);
+        } else if rest.starts_with('：') {
+            return Some(&rest['：'.len_utf8()..]);
+        }
+    }
+    None
+}
+
+// subj-blob       = "[" *BLOBCHAR "]" *WSP
+// BLOBCHAR        = %x01-5a / %x5c / %x5e-7f
+//                 ; any CHAR except '[' and ']'
+fn strip_blob(s: &str) -> Option<&str> {
+    if !s.starts_with('[') {
+        return None;
+    }
+    match s[1..].find(|c| c == '[' || c == ']') {
+        Some(pos) if s[1 + pos..].starts_with(']') => Some(s[pos + 2..].trim_left()),
+        _ => None,
+    }
+}
+
+#[test]
+fn test_base_subject() {
+    assert_eq!(base_subject("Saying Hello", REPLY_PREFIXES), "Saying Hello");
+    assert_eq!(base_subject("Re: Saying  Hello", REPLY_PREFIXES), "Saying Hello");
+    assert_eq!(base_subject("RE: re:FWD: Saying Hello (fwd) (FWD)", REPLY_PREFIXES), "Saying Hello");
+    assert_eq!(base_subject("[rust-users] Re[2]: Saying Hello", REPLY_PREFIXES), "Saying Hello");
+    assert_eq!(base_subject("Re: [rust-users] Saying Hello", REPLY_PREFIXES), "Saying Hello");
+    assert_eq!(base_subject("[Fwd: Re: Saying Hello]", REPLY_PREFIXES), "Saying Hello");
+    assert_eq!(base_subject("[rust-users]", REPLY_PREFIXES), "[rust-users]");
+    assert_eq!(base_subject("Re: ", REPLY_PREFIXES), "");
+    assert_eq!(base_subject("Regarding: Saying Hello", REPLY_PREFIXES), "Regarding: Saying Hello");
+
+    assert_eq!(base_subject("AW: SV: Saying Hello", REPLY_PREFIXES), "AW: SV: Saying Hello");
+    assert_eq!(base_subject("AW: SV: Saying Hello", LOCALIZED_PREFIXES), "Saying Hello");
+    assert_eq!(base_subject("RE : Antw: TR: Saying Hello", LOCALIZED_PREFIXES), "Saying Hello");
+    assert_eq!(base_subject("回复：回复: 你好", LOCALIZED_PREFIXES), "你好");
+}
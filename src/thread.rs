@@ -0,0 +1,353 @@
+//! Conversation threading with Jamie Zawinski's algorithm, as described at
+//! https://www.jwz.org/doc/threading.html and in RFC 5256 section 2.2
+use std::collections::HashMap;
+
+use chrono::datetime::DateTime;
+use chrono::offset::fixed::FixedOffset;
+
+use subject::*;
+use util::*;
+use super::*;
+
+/// A message and its replies. Messages that were replied to but aren't in
+/// the collection, and conversations grouped only by subject, have an empty
+/// container with no message.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Thread {
+    /// The index of the message in the collection that was threaded
+    pub message: Option<usize>,
+    /// The message's ID, e.g. "1234@local.machine.example", which empty
+    /// containers grouped by subject don't have
+    pub message_id: Option<String>,
+    /// Replies, oldest first
+    pub children: Vec<Thread>,
+}
+
+impl Thread {
+    /// Returns the indexes of the messages in the thread, each before its
+    /// replies
+    pub fn messages(&self) -> Vec<usize> {
+        let mut messages: Vec<usize> = self.message.into_iter().collect();
+        for child in self.children.iter() {
+            messages.extend(child.messages());
+        }
+        messages
+    }
+}
+
+// A container in the ID table, linked to others by index
+struct Container {
+    message: Option<usize>,
+    message_id: Option<String>,
+    parent: Option<usize>,
+    children: Vec<usize>,
+}
+
+// What's known about each message that threading needs
+struct Summary {
+    base_subject: String,
+    is_reply: bool,
+    date: Option<DateTime<FixedOffset>>,
+}
+
+/// Threads `messages` into conversations, oldest first, using References and
+/// In-Reply-To, then grouping what's left by base subject
+pub fn thread<I: U8Input>(messages: &[Message<I>]) -> Vec<Thread> {
+    thread_with(messages, REPLY_PREFIXES)
+}
+
+/// Threads `messages`, stripping `prefixes` from subjects to group them
+pub fn thread_with<I: U8Input>(messages: &[Message<I>], prefixes: &[&str]) -> Vec<Thread> {
+    let mut containers: Vec<Container> = vec!();
+    let mut ids: HashMap<String, usize> = HashMap::new();
+
+    // 1. Link each message to its references
+    for (n, message) in messages.iter().enumerate() {
+        let id = message.message_id().ok().map(|id| id_key(&id));
+        // A. The message's container, or a new one if the ID is missing or
+        // already taken by another message
+        let this = match id.as_ref().and_then(|id| ids.get(id).cloned()) {
+            Some(c) if containers[c].message.is_none() => c,
+            _ => {
+                let c = new_container(&mut containers, id.clone());
+                if let Some(ref id) = id {
+                    ids.entry(id.clone()).or_insert(c);
+                }
+                c
+            },
+        };
+        containers[this].message = Some(n);
+
+        // B. Link the references in order, each the parent of the next,
+        // without replacing links already made or creating loops
+        let references = references(message);
+        let mut parent: Option<usize> = None;
+        for id in references.iter() {
+            let c = match ids.get(id).cloned() {
+                Some(c) => c,
+                None => {
+                    let c = new_container(&mut containers, Some(id.clone()));
+                    ids.insert(id.clone(), c);
+                    c
+                },
+            };
+            if let Some(p) = parent {
+                if containers[c].parent.is_none() && p != c && !is_ancestor(&containers, c, p) {
+                    link(&mut containers, p, c);
+                }
+            }
+            parent = Some(c);
+        }
+
+        // C. The last reference is the message's parent, even if it had one
+        match parent {
+            Some(p) if p != this && !is_ancestor(&containers, this, p) => {
+                unlink(&mut containers, this);
+                link(&mut containers, p, this);
+            },
+            Some(_) => {},
+            None => unlink(&mut containers, this),
+        }
+    }
+
+    // 2. The root set is the containers without parents
+    let roots: Vec<Thread> = (0..containers.len())
+        .filter(|&c| containers[c].parent.is_none())
+        .map(|c| build(&containers, c))
+        .collect();
+
+    // 4. Prune empty containers
+    let roots = prune(roots, true);
+
+    // 5. Group the root set by subject
+    let summaries: Vec<Summary> = messages.iter().map(|m| summarize(m, prefixes)).collect();
+    let mut roots = group_by_subject(roots, &summaries);
+
+    // 7. Sort siblings by date
+    sort(&mut roots, &summaries);
+    roots
+}
+
+fn new_container(containers: &mut Vec<Container>, message_id: Option<String>) -> usize {
+    containers.push(Container{
+        message: None,
+        message_id: message_id,
+        parent: None,
+        children: vec!(),
+    });
+    containers.len() - 1
+}
+
+// Message IDs compare exactly, other than the angle brackets and CFWS the
+// parser drops
+fn id_key(id: &MessageID) -> String {
+    match id.id_left {
+        Some(ref left) => format!("{}@{}", left, id.id_right),
+        None => id.id_right.clone(),
+    }
+}
+
+// The References field, or if it's missing the first ID in In-Reply-To, since
+// In-Reply-To may list several messages that aren't each other's replies
+fn references<I: U8Input>(message: &Message<I>) -> Vec<String> {
+    match message.references().ok() {
+        Some(ref ids) if !ids.is_empty() => ids.iter().map(id_key).collect(),
+        _ => match message.in_reply_to().ok() {
+            Some(ids) => ids.iter().take(1).map(id_key).collect(),
+            None => vec!(),
+        },
+    }
+}
+
+// Whether `ancestor` is `c` or one of its parents
+fn is_ancestor(containers: &[Container], ancestor: usize, c: usize) -> bool {
+    let mut c = Some(c);
+    while let Some(n) = c {
+        if n == ancestor {
+            return true;
+        }
+        c = containers[n].parent;
+    }
+    false
+}
+
+fn link(containers: &mut Vec<Container>, parent: usize, child: usize) {
+    containers[child].parent = Some(parent);
+    containers[parent].children.push(child);
+}
+
+fn unlink(containers: &mut Vec<Container>, child: usize) {
+    if let Some(parent) = containers[child].parent.take() {
+        containers[parent].children.retain(|&c| c != child);
+    }
+}
+
+fn build(containers: &[Container], c: usize) -> Thread {
+    Thread{
+        message: containers[c].message,
+        message_id: containers[c].message_id.clone(),
+        children: containers[c].children.iter().map(|&c| build(containers, c)).collect(),
+    }
+}
+
+// Drops empty containers without children, and replaces empty containers
+// with their children, except at the root where that would split up a
+// conversation
+fn prune(threads: Vec<Thread>, root: bool) -> Vec<Thread> {
+    let mut pruned = vec!();
+    for mut thread in threads.into_iter() {
+        thread.children = prune(thread.children, false);
+        if thread.message.is_some() {
+            pruned.push(thread);
+        } else if thread.children.is_empty() {
+            continue;
+        } else if !root || thread.children.len() == 1 {
+            pruned.extend(thread.children);
+        } else {
+            pruned.push(thread);
+        }
+    }
+    pruned
+}
+
+fn summarize<I: U8Input>(message: &Message<I>, prefixes: &[&str]) -> Summary {
+    let subject = message.subject().ok().unwrap_or(String::new());
+    let base = base_subject(&subject, prefixes);
+    Summary{
+        is_reply: base != collapse_whitespace(&subject),
+        base_subject: base,
+        date: message.date().ok(),
+    }
+}
+
+// The message a root's subject comes from, which for an empty container is
+// its first child's
+fn subject_message(thread: &Thread) -> Option<usize> {
+    thread.message.or_else(|| thread.children.first().and_then(|c| c.message))
+}
+
+// 5. Merges roots with the same base subject. An empty container, or failing
+// that a message that isn't a reply, is preferred as the root of each group.
+fn group_by_subject(roots: Vec<Thread>, summaries: &[Summary]) -> Vec<Thread> {
+    let subjects: Vec<Option<&Summary>> = roots.iter()
+        .map(|r| subject_message(r).map(|m| &summaries[m]))
+        .map(|s| match s {
+            Some(s) if !s.base_subject.is_empty() => Some(s),
+            _ => None,
+        })
+        .collect();
+
+    let mut table: HashMap<&str, usize> = HashMap::new();
+    for (n, subject) in subjects.iter().enumerate() {
+        let subject = match *subject {
+            Some(s) => s,
+            None => continue,
+        };
+        let replace = match table.get(&subject.base_subject[..]) {
+            None => true,
+            Some(&old) => {
+                let old_empty = roots[old].message.is_none();
+                let old_reply = subjects[old].map(|s| s.is_reply).unwrap_or(false);
+                (roots[n].message.is_none() && !old_empty) ||
+                    (!old_empty && old_reply && !subject.is_reply)
+            },
+        };
+        if replace {
+            table.insert(&subject.base_subject, n);
+        }
+    }
+
+    let mut grouped: Vec<Option<Thread>> = roots.iter().cloned().map(Some).collect();
+    for (n, subject) in subjects.iter().enumerate() {
+        let subject = match *subject {
+            Some(s) => s,
+            None => continue,
+        };
+        let that = table[&subject.base_subject[..]];
+        if that == n {
+            continue;
+        }
+        let this = grouped[n].take().unwrap();
+        let that_thread = grouped[that].take().unwrap();
+        let that_reply = subjects[that].map(|s| s.is_reply).unwrap_or(false);
+        grouped[that] = Some(match (this.message.is_none(), that_thread.message.is_none()) {
+            // Both empty, so merge their children
+            (true, true) => {
+                let mut that_thread = that_thread;
+                that_thread.children.extend(this.children);
+                that_thread
+            },
+            // Only that is empty, so this joins its children
+            (false, true) => {
+                let mut that_thread = that_thread;
+                that_thread.children.push(this);
+                that_thread
+            },
+            // That is the original and this a reply to it
+            (false, false) if !that_reply && subject.is_reply => {
+                let mut that_thread = that_thread;
+                that_thread.children.push(this);
+                that_thread
+            },
+            // This is the original and that a reply to it
+            (false, false) if that_reply && !subject.is_reply => {
+                let mut this = this;
+                this.children.push(that_thread);
+                this
+            },
+            // Otherwise they're siblings under a new empty container
+            _ => Thread{
+                message: None,
+                message_id: None,
+                children: vec!(that_thread, this),
+            },
+        });
+    }
+    grouped.into_iter().filter_map(|t| t).collect()
+}
+
+// Sorts siblings by date, with an empty container dated by its first child
+fn sort(threads: &mut Vec<Thread>, summaries: &[Summary]) {
+    for thread in threads.iter_mut() {
+        sort(&mut thread.children, summaries);
+    }
+    threads.sort_by_key(|t| date(t, summaries));
+}
+
+fn date(thread: &Thread, summaries: &[Summary]) -> Option<DateTime<FixedOffset>> {
+    match thread.message {
+        Some(m) => summaries[m].date,
+        None => thread.children.first().and_then(|c| date(c, summaries)),
+    }
+}
+
+#[test]
+fn test_thread() {
+    let raw: Vec<&[u8]> = vec!(
+        b"Message-ID: <1@example.com>\r\nDate: Fri, 21 Nov 1997 09:55:06 -0600\r\nSubject: Saying Hello\r\n\r\n",
+        b"Message-ID: <3@example.com>\r\nDate: Fri, 21 Nov 1997 11:00:00 -0600\r\nReferences: <1@example.com> <2@example.com>\r\nSubject: Re: Saying Hello\r\n\r\n",
+        b"Message-ID: <4@example.com>\r\nDate: Fri, 21 Nov 1997 10:30:00 -0600\r\nIn-Reply-To: <1@example.com>\r\nSubject: Re: Saying Hello\r\n\r\n",
+        b"Message-ID: <5@example.com>\r\nDate: Sat, 22 Nov 1997 09:00:00 -0600\r\nSubject: Re: Other\r\n\r\n",
+        b"Message-ID: <6@example.com>\r\nDate: Sat, 22 Nov 1997 08:00:00 -0600\r\nSubject: Other\r\n\r\n",
+        b"Message-ID: <7@example.com>\r\nDate: Sun, 23 Nov 1997 08:00:00 -0600\r\nReferences: <gone@example.com>\r\nSubject: Lost\r\n\r\n",
+        b"Message-ID: <8@example.com>\r\nDate: Sun, 23 Nov 1997 09:00:00 -0600\r\nReferences: <gone@example.com>\r\nSubject: Found\r\n\r\n",
+    );
+    let messages: Vec<Message<&[u8]>> = raw.iter().map(|r| parse_message(r).unwrap()).collect();
+    let threads = thread(&messages);
+    assert_eq!(threads.len(), 3);
+
+    // 2 is missing, so its empty container is pruned and 3 moves up to be a
+    // reply to 1, after 4
+    assert_eq!(threads[0].message, Some(0));
+    assert_eq!(threads[0].message_id, Some("1@example.com".to_string()));
+    assert_eq!(threads[0].messages(), vec!(0, 2, 1));
+    assert_eq!(threads[0].children.len(), 2);
+
+    // "Re: Other" has no references, so it's grouped under "Other" by subject
+    assert_eq!(threads[1].messages(), vec!(4, 3));
+
+    // The missing message both 7 and 8 reply to stays as an empty root
+    assert_eq!(threads[2].message, None);
+    assert_eq!(threads[2].message_id, Some("gone@example.com".to_string()));
+    assert_eq!(threads[2].messages(), vec!(5, 6));
+}
@@ -10,14 +10,181 @@ use chomp::primitives::Primitives;
 use chomp::primitives::IntoInner;
 // use chomp::combinators::bounded;
 
-pub fn unchecked_string_from_bufs<I: U8Input>(bufs: Vec<I::Buffer>) -> String {
+pub fn string_from_bufs<I: U8Input>(bufs: Vec<I::Buffer>) -> String {
     let len = bufs.iter().fold(0, |l, buf| l + buf.len());
     let mut bytes = Vec::with_capacity(len);
     for buffer in bufs.into_iter() {
         bytes.append(&mut buffer.into_vec());
     }
 
-    unsafe { String::from_utf8_unchecked(bytes) }
+    string_from_bytes(bytes)
+}
+
+// Field accessors check values are ASCII, or well-formed UTF-8 (RFC 6532),
+// before they get here, but replace invalid sequences rather than fail.
+pub fn string_from_bytes(bytes: Vec<u8>) -> String {
+    match String::from_utf8(bytes) {
+        Ok(s) => s,
+        Err(e) => String::from_utf8_lossy(e.as_bytes()).into_owned(),
+    }
+}
+
+// Removes comments, which may nest and contain quoted-pairs, from a value
+// that has no quoted-strings
+pub fn strip_comments(s: &str) -> String {
+    let mut stripped = String::with_capacity(s.len());
+    let mut depth = 0;
+    let mut escaped = false;
+    for c in s.chars() {
+        if escaped {
+            escaped = false;
+        } else if c == '\\' && depth > 0 {
+            escaped = true;
+        } else if c == '(' {
+            depth += 1;
+        } else if c == ')' && depth > 0 {
+            depth -= 1;
+        } else if depth == 0 {
+            stripped.push(c);
+        }
+    }
+    stripped
+}
+
+// Returns the text of each top-level comment in `bytes`, unfolded and with
+// quoted-pairs resolved. Parentheses in quoted-strings and domain-literals
+// aren't comments, and nested comments are kept in the text of the outer one.
+pub fn comments(bytes: &[u8]) -> Vec<String> {
+    let mut comments = vec!();
+    let mut comment = vec!();
+    let mut depth = 0;
+    let mut delimiter = None;
+    let mut escaped = false;
+    for &c in bytes.iter() {
+        if escaped {
+            escaped = false;
+            if depth > 0 {
+                comment.push(c);
+            }
+        } else if c == b'\\' {
+            escaped = true;
+        } else if let Some(end) = delimiter {
+            if c == end {
+                delimiter = None;
+            }
+        } else if depth == 0 {
+            match c {
+                b'"' => delimiter = Some(b'"'),
+                b'[' => delimiter = Some(b']'),
+                b'(' => depth = 1,
+                _ => {},
+            }
+        } else {
+            match c {
+                b'(' => depth += 1,
+                b')' => depth -= 1,
+                _ => {},
+            }
+            if depth == 0 {
+                comments.push(string_from_bytes(comment));
+                comment = vec!();
+            } else if c != b'\r' && c != b'\n' {
+                comment.push(c);
+            }
+        }
+    }
+    comments
+}
+
+// Unfolds a field value as section 2.2.3 describes, removing each line break
+// that's followed by WSP. obs-unstruct also allows bare CR and LF, so any run
+// of CR and LF is a line break, and one that isn't followed by WSP becomes a
+// space rather than joining the text around it.
+pub fn unfold(s: &str) -> String {
+    let mut unfolded = String::with_capacity(s.len());
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\r' && c != '\n' {
+            unfolded.push(c);
+            continue;
+        }
+        while chars.peek() == Some(&'\r') || chars.peek() == Some(&'\n') {
+            chars.next();
+        }
+        match chars.peek() {
+            Some(&' ') | Some(&'\t') | None => {},
+            Some(_) => unfolded.push(' '),
+        }
+    }
+    unfolded
+}
+
+// Replaces each run of whitespace, including line breaks, with a single space
+// and trims the ends
+pub fn collapse_whitespace(s: &str) -> String {
+    s.split(|c| c == ' ' || c == '\t' || c == '\r' || c == '\n')
+        .filter(|w| !w.is_empty())
+        .collect::<Vec<&str>>()
+        .join(" ")
+}
+
+// Resolves quoted-pairs, e.g. a\"b to a"b
+pub fn unescape(s: &str) -> String {
+    let mut unescaped = String::with_capacity(s.len());
+    let mut escaped = false;
+    for c in s.chars() {
+        if c == '\\' && !escaped {
+            escaped = true;
+        } else {
+            escaped = false;
+            unescaped.push(c);
+        }
+    }
+    unescaped
+}
+
+// Quotes a value as a quoted-string, escaping "\" and DQUOTE
+pub fn quote(s: &str) -> String {
+    let mut quoted = String::with_capacity(s.len() + 2);
+    quoted.push('"');
+    for c in s.chars() {
+        if c == '\\' || c == '"' {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    quoted.push('"');
+    quoted
+}
+
+// Decodes base64, stopping at the first "=" of any padding. Used for the "B"
+// encoding of RFC 2047 and DKIM's base64 tag values.
+pub fn decode_base64(text: &[u8]) -> Option<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(text.len() * 3 / 4);
+    let mut bits: u32 = 0;
+    let mut count = 0;
+    for &c in text.iter().take_while(|&&c| c != b'=') {
+        let value = if c >= b'A' && c <= b'Z' {
+            c - b'A'
+        } else if c >= b'a' && c <= b'z' {
+            c - b'a' + 26
+        } else if c >= b'0' && c <= b'9' {
+            c - b'0' + 52
+        } else if c == b'+' {
+            62
+        } else if c == b'/' {
+            63
+        } else {
+            return None;
+        };
+        bits = (bits << 6) | value as u32;
+        count += 6;
+        if count >= 8 {
+            count -= 8;
+            bytes.push((bits >> count) as u8);
+        }
+    }
+    Some(bytes)
 }
 
 fn is_digit(c: u8) -> bool {
@@ -161,6 +328,24 @@ pub fn downcased_string<I: Input<Token=u8>>(mut i: I, s: &[u8]) -> SimpleResult<
     }
 }
 
+#[test]
+fn test_comments() {
+    let i = b"Pete(A wonderful \\) chap) <pete(his account)@silly.test(his host)>";
+    assert_eq!(comments(i), vec!("A wonderful ) chap", "his account", "his host"));
+
+    let i = b"\"Joe (Q.) Public\" <joe@[(x)]> (nobody\r\n (that I know))";
+    assert_eq!(comments(i), vec!("nobody (that I know)"));
+}
+
+#[test]
+fn test_unfold() {
+    assert_eq!(unfold(" Saying\r\n Hello"), " Saying Hello");
+    assert_eq!(unfold(" a\r\n\t\r\n  b"), " a\t  b");
+    assert_eq!(unfold(" a\n b\r c"), " a b c");
+    assert_eq!(unfold(" a\rb\n\r\nc\r\n"), " a b c");
+    assert_eq!(collapse_whitespace(" a\r\n\t  b\rc "), "a b c");
+}
+
 #[test]
 fn test_downcased_string() {
     let i = b"Reply-to";
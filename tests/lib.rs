@@ -347,3 +347,185 @@ fn example_6_3() {
         assert!(!field.is_malformed());
     }
 }
+
+#[test]
+fn example_6_2_profiles() {
+    let raw = include_bytes!("example_6_2.eml");
+
+    let msg = parse_only(|i| message_with(i, mail::Profile::Strict), raw).unwrap();
+    assert!(msg.date().is_raw());
+    assert!(msg.from().is_ok());
+
+    let msg = parse_only(|i| message_with(i, mail::Profile::Obsolete), raw).unwrap();
+    assert!(msg.date().is_accepted());
+    assert_eq!(msg.warnings(), vec!(("Date".to_string(), mail::Warning{
+        production: "date_time",
+        deviation: mail::Deviation::Obsolete,
+    })));
+
+    let raw = include_bytes!("example_1_1.1.eml");
+    let msg = parse_only(|i| message_with(i, mail::Profile::Strict), raw).unwrap();
+    assert!(msg.warnings().is_empty());
+}
+
+#[test]
+fn example_lint() {
+    let raw = include_bytes!("example_1_1.1.eml");
+    let msg = parse_only(message, raw).unwrap();
+    assert!(msg.lint().is_empty());
+
+    let raw = include_bytes!("example_6_2.eml");
+    let msg = parse_only(message, raw).unwrap();
+    let lint = msg.lint();
+    assert_eq!(lint.len(), 1);
+    assert_eq!(lint[0].category, mail::lint::Category::Obsolete);
+    assert_eq!(lint[0].to_string(), "Date: date_time uses obsolete syntax");
+}
+
+#[test]
+fn example_utf8() {
+    let raw = "From: Jörg Müller <jörg@example.com>\r\nSubject: Grüße\r\n\r\n".as_bytes();
+    let utf8 = mail::ParseOptions{ profile: mail::Profile::Strict, utf8: true };
+
+    let msg = parse_only(|i| message_with(i, utf8), raw).unwrap();
+    assert_eq!(msg.from().unwrap(), vec!(mail::Address::Mailbox{
+        local_part: "jörg".to_string(),
+        domain: mail::Domain::Name("example.com".to_string()),
+        display_name: Some(" Jörg Müller ".to_string()),
+        comments: vec!(),
+        route: None,
+    }));
+
+    let msg = parse_only(|i| message_with(i, mail::Profile::Strict), raw).unwrap();
+    let from = msg.from();
+    assert!(from.is_raw());
+    assert_eq!(from.error().unwrap().offset, 2);
+
+    // The lenient profile accepts UTF-8 without the option, with a warning
+    let msg = parse_only(message, raw).unwrap();
+    let from = msg.from();
+    assert!(from.is_accepted());
+    assert!(from.warnings().iter().any(|w| w.deviation == mail::Deviation::Lenient));
+
+    let raw = b"From: J\xf6rg <joerg@example.com>\r\n\r\n";
+    let msg = parse_only(|i| message_with(i, utf8), raw).unwrap();
+    assert!(msg.from().is_raw());
+    let msg = parse_only(message, b"From:\xf6\r\n\r\n").unwrap();
+    assert_eq!(msg.from().raw().trim(), "\u{fffd}");
+}
+
+#[test]
+fn example_accessors() {
+    let raw = b"Return-Path: <>\r\nReceived: from machine.example by x.y.test; 21 Nov 1997 10:01:22 -0600\r\nResent-From: Mary Smith <mary@example.net>\r\nResent-Bcc:\r\nFrom: John Doe <jdoe@machine.example>\r\nSubject: =?utf-8?Q?Saying?= =?utf-8?Q?_H=C3=A9llo?=\r\nComments: first\r\nComments: =?iso-8859-1?Q?caf=E9?=\r\nKeywords: hello, \"first contact\"\r\n\r\nbody";
+    let msg = parse_only(message, raw).unwrap();
+    for field in msg.fields.iter() {
+        assert!(!field.is_unstructured());
+        assert!(!field.is_malformed());
+    }
+
+    assert_eq!(msg.subject().unwrap(), "Saying Héllo");
+    let comments: Vec<String> = msg.comments().into_iter().map(|c| c.unwrap()).collect();
+    assert_eq!(comments, vec!("first", "café"));
+    let keywords = msg.keywords().pop().unwrap().unwrap();
+    assert_eq!(keywords, vec!("hello", "first contact"));
+    assert_eq!(msg.return_path().unwrap(), None);
+    let mut received = msg.received();
+    assert_eq!(received.len(), 1);
+    assert_eq!(received.pop().unwrap().unwrap().tokens, vec!("from", "machine.example", "by", "x.y.test"));
+
+    assert_eq!(msg.resent_from().unwrap()[0].to_string(), "Mary Smith <mary@example.net>");
+    assert_eq!(msg.resent_bcc().unwrap(), vec!());
+    assert!(msg.resent_date().is_missing());
+}
+
+#[test]
+fn example_list_info() {
+    let raw = b"From: Rust Users <users@rust-lang.org>\r\nList-Id: Rust users <users.rust-lang.org>\r\nList-Unsubscribe: <mailto:users-unsubscribe@rust-lang.org>,\r\n <https://users.rust-lang.org/unsubscribe?id=42>\r\nList-Unsubscribe-Post: List-Unsubscribe=One-Click\r\nList-Post: NO (read only)\r\nList-Archive: <https://users.rust-lang.org/archive/>\r\n\r\nbody";
+    let msg = parse_only(message, raw).unwrap();
+    for field in msg.fields.iter() {
+        assert!(!field.is_unstructured());
+        assert!(!field.is_malformed());
+    }
+
+    let info = msg.list_info().unwrap();
+    assert_eq!(info.id.as_ref().unwrap().id, "users.rust-lang.org");
+    assert_eq!(info.id.as_ref().unwrap().description, Some("Rust users".to_string()));
+    assert_eq!(info.unsubscribe.len(), 2);
+    assert_eq!(info.one_click_url(), Some("https://users.rust-lang.org/unsubscribe?id=42"));
+    assert_eq!(info.post, Some(mail::ListPost::No));
+    assert_eq!(info.archive, vec!("https://users.rust-lang.org/archive/"));
+    assert_eq!(info.help, Vec::<String>::new());
+
+    let raw = b"From: John Doe <jdoe@machine.example>\r\n\r\nbody";
+    let msg = parse_only(message, raw).unwrap();
+    assert_eq!(msg.list_info(), None);
+}
+
+#[test]
+fn example_authentication_results() {
+    let raw = b"Authentication-Results: mx.example.com;\r\n spf=pass smtp.mailfrom=example.net;\r\n dkim=pass (2048-bit key) header.d=example.net header.s=sel;\r\n dmarc=pass header.from=example.net\r\nAuthentication-Results: gateway.example.org; none\r\nFrom: John Doe <jdoe@example.net>\r\n\r\nbody";
+    let msg = parse_only(message, raw).unwrap();
+    for field in msg.fields.iter() {
+        assert!(!field.is_unstructured());
+        assert!(!field.is_malformed());
+    }
+
+    let mut results = msg.authentication_results();
+    assert_eq!(results.len(), 2);
+    assert_eq!(results.pop().unwrap().unwrap().results, vec!());
+    let first = results.pop().unwrap().unwrap();
+    assert_eq!(first.authserv_id, "mx.example.com");
+    let methods: Vec<(&str, &str)> = first.results.iter().map(|r| (&r.method[..], &r.result[..])).collect();
+    assert_eq!(methods, vec!(("spf", "pass"), ("dkim", "pass"), ("dmarc", "pass")));
+    assert_eq!(first.results[1].property("header", "s"), Some("sel"));
+    assert_eq!(first.results[1].comments, vec!("2048-bit key"));
+}
+
+#[test]
+fn example_dkim_signature() {
+    let raw = b"DKIM-Signature: v=1; a=rsa-sha256; c=relaxed/relaxed; d=example.net;\r\n s=sel; h=from:to:subject; bh=2jUSOH9NhtVGCQWNr9BrIAPreKQjO6Sn7XIkfJVOzv8=;\r\n b=AuUoFEfDxTDkHlLXSZEpZj79LICEps6eda7W3deTVFOk4yAUoqOB\r\n 4nujc7YopdG5dWLSdNg6xNAZpOPr+kHxt1IrE+NahM6L/LbvaHut\r\nFrom: John Doe <jdoe@example.net>\r\n\r\nbody";
+    let msg = parse_only(message, raw).unwrap();
+    for field in msg.fields.iter() {
+        assert!(!field.is_unstructured());
+        assert!(!field.is_malformed());
+    }
+
+    let signature = msg.dkim_signatures().pop().unwrap().unwrap();
+    assert_eq!(signature.algorithm, mail::SigningAlgorithm::RsaSha256);
+    assert_eq!(signature.body_canonicalization, mail::Canonicalization::Relaxed);
+    assert_eq!(signature.domain, "example.net");
+    assert_eq!(signature.headers, vec!("from", "to", "subject"));
+    assert_eq!(signature.query_methods, vec!("dns/txt"));
+}
+
+#[test]
+fn example_dkim_verification() {
+    use std::collections::HashMap;
+    use mail::dkim::*;
+
+    let raw = include_bytes!("example_dkim.eml");
+    let mut keys = HashMap::new();
+    keys.insert("brisbane._domainkey.football.example.com".to_string(),
+        "v=DKIM1; k=ed25519; p=KVGLetYSp6FgdpTlJgEl0LbxWMWFc6cbhQYhKdilkF0=".to_string());
+    keys.insert("test._domainkey.football.example.com".to_string(),
+        "v=DKIM1; k=rsa;\r\n p=MIGfMA0GCSqGSIb3DQEBAQUAA4GNADCBiQKBgQDMwbnHSaRw1uSPj0mpbJjPKZCceNs0qLv/M/cEq9BkuHK7M/En77T1qao9urRBPb1K3b4evpczg+iaihdYc7Ttgil3bGoyOxgXYc2IZs4lEnV6cJj9mVN/f+/np94ylHlaLtjfqFeD9bV8XOyxdDp93ELpk7UiBSTMs7MWF+89yQIDAQAB".to_string());
+
+    let results = verify(raw, &keys);
+    let statuses: Vec<Status> = results.iter().map(|r| r.status.clone()).collect();
+    assert_eq!(statuses, vec!(Status::Pass, Status::Pass));
+    assert_eq!(results[1].signature.as_ref().unwrap().length, Some(49));
+
+    // The RSA signature only covers the first 49 octets of the body
+    let footer = [&raw[..], b"--\r\nSent from my phone\r\n"].concat();
+    let statuses: Vec<&str> = verify(&footer, &keys).iter().map(|r| r.status.result()).collect();
+    assert_eq!(statuses, vec!("fail", "pass"));
+
+    let subject = String::from_utf8_lossy(raw).replace("Is dinner ready?", "Is dinner ready?!");
+    let statuses: Vec<Status> = verify(subject.as_bytes(), &keys).into_iter().map(|r| r.status).collect();
+    assert_eq!(statuses, vec!(Status::Fail(Failure::SignatureMismatch), Status::Fail(Failure::SignatureMismatch)));
+
+    keys.insert("test._domainkey.football.example.com".to_string(), "v=DKIM1; p=".to_string());
+    keys.remove("brisbane._domainkey.football.example.com");
+    let statuses: Vec<Status> = verify(raw, &keys).into_iter().map(|r| r.status).collect();
+    assert_eq!(statuses, vec!(Status::PermError(Failure::KeyNotFound), Status::PermError(Failure::KeyRevoked)));
+}